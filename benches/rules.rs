@@ -0,0 +1,229 @@
+//! How much a frame costs with thousands of windows and rules.
+//!
+//! `cargo bench --bench rules` compares the compiled `RuleSet` against
+//! the paint loop from before it, on rules that loop understood, and a
+//! warm `WindowRegistry` against a cold one.
+
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use cute_borders::backend::{MockBackend, MockWindow, Rect, WindowAttributes, WindowBackend};
+use cute_borders::color::ColorSpec;
+use cute_borders::config::{Config, Matcher, Needles, Rule, RuleMatch};
+use cute_borders::registry::WindowRegistry;
+use cute_borders::rules::{RuleSet, WindowInfo};
+use cute_borders::util::hex_to_colorref;
+use cute_borders::DWMWA_COLOR_DEFAULT;
+
+const WINDOWS: usize = 2_000;
+const RULES: usize = 2_000;
+const FRAMES: u32 = 5;
+
+fn main() {
+  let windows = synthetic_windows(WINDOWS);
+  println!("{} windows, {} rules, {} frames", WINDOWS, RULES, FRAMES);
+
+  // The old loop only knew `contains`, so both sides get only that.
+  let contains_config = synthetic_config(RULES, true);
+  let baseline_rules = baseline_rules(&contains_config);
+  let baseline = time_frames(|| {
+    for (title, class) in &windows {
+      black_box(baseline_colors_for(&baseline_rules, title, class));
+    }
+  });
+  report("baseline, contains rules", baseline);
+
+  let contains_rules = RuleSet::compile(&contains_config).unwrap();
+  let compiled = time_frames(|| {
+    for (title, class) in &windows {
+      black_box(contains_rules.colors_for(&WindowInfo::new(title, class, 0)));
+    }
+  });
+  report("RuleSet, contains rules", compiled);
+
+  let rules = RuleSet::compile(&synthetic_config(RULES, false)).unwrap();
+  let compiled = time_frames(|| {
+    for (title, class) in &windows {
+      black_box(rules.colors_for(&WindowInfo::new(title, class, 0)));
+    }
+  });
+  report("RuleSet, mixed rules", compiled);
+
+  let backend = Arc::new(MockBackend::new());
+  for (index, (title, class)) in windows.iter().enumerate() {
+    backend.open(
+      index + 1,
+      MockWindow {
+        attributes: WindowAttributes {
+          title: title.clone(),
+          class: class.clone(),
+          pid: 0,
+          is_menu: false,
+        },
+        owner: None,
+        bounds: Rect::default(),
+      },
+    );
+  }
+  let rules = Arc::new(rules);
+  let cold = time_frames(|| {
+    let mut registry = WindowRegistry::new(backend.clone() as Arc<dyn WindowBackend>);
+    black_box(registry.matched(&rules));
+  });
+  report("registry, matching every window", cold);
+
+  let mut registry = WindowRegistry::new(backend.clone() as Arc<dyn WindowBackend>);
+  registry.matched(&rules);
+  let warm = time_frames(|| {
+    for matched in registry.matched(&rules) {
+      black_box(rules.colors_of(matched.rule, 0.0));
+    }
+  });
+  report("registry, cached matches", warm);
+}
+
+fn time_frames(mut frame: impl FnMut()) -> Duration {
+  frame();
+  let start = Instant::now();
+  for _ in 0..FRAMES {
+    frame();
+  }
+  start.elapsed() / FRAMES
+}
+
+fn report(name: &str, per_frame: Duration) {
+  println!(
+    "{:32} {:>10.3} ms/frame",
+    name,
+    per_frame.as_secs_f64() * 1000.0
+  );
+}
+
+/// Mostly rules that don't match, like a config with rules for many apps
+/// of which few are open. Without `only_contains`, a quarter each of
+/// `contains`, `equals`, `glob` and `regex` rules.
+fn synthetic_config(count: usize, only_contains: bool) -> Config {
+  let mut window_rules = vec![Rule::global(
+    ColorSpec::Hex(0x0000ff),
+    ColorSpec::Hex(0x444444),
+  )];
+  for index in 0..count {
+    // Even rules look at the title, odd ones at the class.
+    let mut matcher = Matcher::new(if index % 2 == 0 {
+      RuleMatch::Title
+    } else {
+      RuleMatch::Class
+    });
+    match if only_contains { 0 } else { index % 4 } {
+      0 => matcher.contains = Some(Needles::One(format!("Document {}", index))),
+      1 => matcher.equals = Some(format!("AppWindowClass{}", index)),
+      2 => matcher.glob = Some(format!("* - App {}", index)),
+      _ => matcher.regex = Some(format!("^Class{}$", index)),
+    }
+    window_rules.push(Rule {
+      matcher: Some(matcher),
+      when: None,
+      active_border_color: format!("#{:06x}", index * 97 % 0xffffff).parse().unwrap(),
+      inactive_border_color: ColorSpec::Default,
+    });
+  }
+
+  Config {
+    rainbow_degrees_per_second: None,
+    rainbow_speed: None,
+    rainbow_saturation: None,
+    rainbow_lightness: None,
+    rainbow_direction: None,
+    rainbow_offset: None,
+    rainbow_spread: None,
+    transition_ms: None,
+    transition_easing: None,
+    frame_rate: None,
+    hide_tray_icon: None,
+    log_level: None,
+    tiling_wm: None,
+    alpha_backdrop: None,
+    palette: Default::default(),
+    window_rules,
+  }
+}
+
+fn synthetic_windows(count: usize) -> Vec<(String, String)> {
+  (0..count)
+    .map(|index| {
+      (
+        format!("Document {} - App {}", index * 7, index % 50),
+        format!("AppWindowClass{}", index * 3),
+      )
+    })
+    .collect()
+}
+
+/// A rule as the config had it before `RuleSet`: only `contains`, and
+/// colors kept as the strings from config.yaml.
+#[derive(Clone)]
+struct BaselineRule {
+  rule_match: RuleMatch,
+  contains: Option<String>,
+  active_border_color: String,
+  inactive_border_color: String,
+}
+
+fn baseline_rules(config: &Config) -> Vec<BaselineRule> {
+  config
+    .window_rules
+    .iter()
+    .filter_map(|rule| {
+      let matcher = rule.matcher.as_ref()?;
+      Some(BaselineRule {
+        rule_match: matcher.rule_match.clone(),
+        contains: matcher
+          .contains
+          .as_ref()
+          .and_then(|c| c.iter().next().cloned()),
+        active_border_color: rule.active_border_color.to_string(),
+        inactive_border_color: rule.inactive_border_color.to_string(),
+      })
+    })
+    .collect()
+}
+
+/// `get_colors_for_window` from before `RuleSet`: every window got its
+/// own copy of the config, lowercased its title or class again for each
+/// rule and parsed the colors of every rule that applied.
+fn baseline_colors_for(rules: &[BaselineRule], title: &str, class: &str) -> (u32, u32) {
+  let rules = black_box(rules.to_vec());
+  let mut colors = (DWMWA_COLOR_DEFAULT, DWMWA_COLOR_DEFAULT);
+  for rule in &rules {
+    let applies = match rule.rule_match {
+      RuleMatch::Global => true,
+      RuleMatch::Title => rule
+        .contains
+        .as_ref()
+        .is_some_and(|c| title.to_lowercase().contains(&c.to_lowercase())),
+      RuleMatch::Class => rule
+        .contains
+        .as_ref()
+        .is_some_and(|c| class.to_lowercase().contains(&c.to_lowercase())),
+      _ => false,
+    };
+    if applies {
+      colors = (
+        baseline_color(&rule.active_border_color),
+        baseline_color(&rule.inactive_border_color),
+      );
+      if rule.rule_match != RuleMatch::Global {
+        break;
+      }
+    }
+  }
+  colors
+}
+
+fn baseline_color(color: &str) -> u32 {
+  match color {
+    "default" => DWMWA_COLOR_DEFAULT,
+    color => hex_to_colorref(color).unwrap_or(DWMWA_COLOR_DEFAULT),
+  }
+}
@@ -1,4 +1,8 @@
 fn main() {
+  // The icon and manifest are Windows resources, other targets have none.
+  if std::env::var("CARGO_CFG_WINDOWS").is_err() {
+    return;
+  }
   let mut res = winres::WindowsResource::new();
   res.set_icon("src/data/icon.ico");
   res.compile().unwrap();
@@ -0,0 +1,259 @@
+use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, RwLock};
+#[cfg(windows)]
+use std::{ptr, time::Duration};
+#[cfg(windows)]
+use winapi::shared::minwindef::{BOOL, HKEY};
+#[cfg(windows)]
+use winapi::shared::winerror::{ERROR_SUCCESS, SUCCEEDED};
+#[cfg(windows)]
+use winapi::um::dwmapi::DwmGetColorizationColor;
+#[cfg(windows)]
+use winapi::um::winnt::{KEY_NOTIFY, REG_NOTIFY_CHANGE_LAST_SET};
+#[cfg(windows)]
+use winapi::um::winreg::{RegCloseKey, RegNotifyChangeKeyValue, RegOpenKeyExW, HKEY_CURRENT_USER};
+
+use crate::logger::Logger;
+use crate::DWMWA_COLOR_DEFAULT;
+
+static ACCENT: Lazy<RwLock<Arc<Accent>>> =
+  Lazy::new(|| RwLock::new(Arc::new(Accent::new(system_source()))));
+
+/// Where the accent color comes from. The real one asks DWM, a test can
+/// swap in its own to simulate accent changes.
+pub trait AccentSource: Send + Sync {
+  /// The current accent as a COLORREF (0x00bbggrr), `None` if it can't be read.
+  fn read(&self) -> Option<u32>;
+  /// Blocks until the accent may have changed. Returning `false` stops watching.
+  fn wait_for_change(&self) -> bool;
+}
+
+/// The accent color, read once and then only again when the source
+/// reports a change, instead of once per window on every frame.
+pub struct Accent {
+  source: Box<dyn AccentSource>,
+  color: AtomicU32,
+}
+
+impl Accent {
+  pub fn new(source: Box<dyn AccentSource>) -> Accent {
+    let color = source.read().unwrap_or(DWMWA_COLOR_DEFAULT);
+    Accent {
+      source,
+      color: AtomicU32::new(color),
+    }
+  }
+
+  pub fn get(&self) -> u32 {
+    self.color.load(Ordering::Relaxed)
+  }
+
+  /// Reads the accent again, returns whether it changed.
+  pub fn refresh(&self) -> bool {
+    // Keep the last known color if DWM fails to answer.
+    let Some(color) = self.source.read() else {
+      return false;
+    };
+    let previous = self.color.swap(color, Ordering::Relaxed);
+    if previous == color {
+      return false;
+    }
+    Logger::log(&format!(
+      "[INFO] Accent color changed to #{:06x}",
+      // COLORREF is 0x00bbggrr, log it the way it's written in config.yaml
+      ((color & 0xFF) << 16) | (color & 0xFF00) | ((color >> 16) & 0xFF)
+    ));
+    true
+  }
+
+  /// Calls `on_change` after every accent change until the source stops.
+  pub fn watch(&self, mut on_change: impl FnMut(u32)) {
+    while self.source.wait_for_change() {
+      if self.refresh() {
+        on_change(self.get());
+      }
+    }
+  }
+}
+
+fn shared() -> Arc<Accent> {
+  ACCENT.read().unwrap().clone()
+}
+
+/// The cached system accent color.
+pub fn current() -> u32 {
+  ACCENT.read().unwrap().get()
+}
+
+/// Blocks the calling thread, calling `on_change` whenever the system accent changes.
+pub fn watch(on_change: impl FnMut(u32)) {
+  shared().watch(on_change)
+}
+
+/// Replaces where `current` and `watch` get the accent from. A `watch`
+/// already running keeps the source it started with.
+pub fn set_source(source: Box<dyn AccentSource>) {
+  *ACCENT.write().unwrap() = Arc::new(Accent::new(source));
+}
+
+#[cfg(windows)]
+fn system_source() -> Box<dyn AccentSource> {
+  Box::new(DwmAccent::open())
+}
+
+// Without DWM there's no accent, `accent` falls back to the default border.
+#[cfg(not(windows))]
+fn system_source() -> Box<dyn AccentSource> {
+  Box::new(NoAccent)
+}
+
+#[cfg(not(windows))]
+struct NoAccent;
+
+#[cfg(not(windows))]
+impl AccentSource for NoAccent {
+  fn read(&self) -> Option<u32> {
+    None
+  }
+
+  fn wait_for_change(&self) -> bool {
+    false
+  }
+}
+
+/// Reads the accent from DWM and waits on the registry key DWM writes its
+/// colorization settings to, so no window is needed to hear about changes.
+#[cfg(windows)]
+struct DwmAccent {
+  // Stored as usize so the source is Send + Sync, it's only used as an HKEY.
+  key: Option<usize>,
+}
+
+#[cfg(windows)]
+impl DwmAccent {
+  fn open() -> DwmAccent {
+    let path: Vec<u16> = "Software\\Microsoft\\Windows\\DWM\0"
+      .encode_utf16()
+      .collect();
+    let mut key: HKEY = ptr::null_mut();
+    let result =
+      unsafe { RegOpenKeyExW(HKEY_CURRENT_USER, path.as_ptr(), 0, KEY_NOTIFY, &mut key) };
+    if result as u32 != ERROR_SUCCESS {
+      Logger::log(&format!(
+        "[ERROR] Failed to open DWM registry key, accent changes won't be noticed: {}",
+        result
+      ));
+      return DwmAccent { key: None };
+    }
+    DwmAccent {
+      key: Some(key as usize),
+    }
+  }
+}
+
+#[cfg(windows)]
+impl AccentSource for DwmAccent {
+  fn read(&self) -> Option<u32> {
+    let mut colorization: u32 = 0;
+    let mut opaqueblend: BOOL = 0;
+    let result = unsafe { DwmGetColorizationColor(&mut colorization, &mut opaqueblend) };
+    if !SUCCEEDED(result) {
+      Logger::log(&format!(
+        "[ERROR] Failed to retrieve accent color: 0x{:08X}",
+        result
+      ));
+      return None;
+    }
+    let red = (colorization & 0x00FF0000) >> 16;
+    let green = (colorization & 0x0000FF00) >> 8;
+    let blue = colorization & 0x000000FF;
+    Some((blue << 16) | (green << 8) | red)
+  }
+
+  fn wait_for_change(&self) -> bool {
+    let Some(key) = self.key else {
+      return false;
+    };
+    let result = unsafe {
+      RegNotifyChangeKeyValue(
+        key as HKEY,
+        0,
+        REG_NOTIFY_CHANGE_LAST_SET,
+        ptr::null_mut(),
+        0,
+      )
+    };
+    if result as u32 != ERROR_SUCCESS {
+      Logger::log(&format!(
+        "[ERROR] Failed to watch for accent changes: {}",
+        result
+      ));
+      return false;
+    }
+    // Personalization writes a burst of values, let it settle before reading.
+    std::thread::sleep(Duration::from_millis(100));
+    true
+  }
+}
+
+#[cfg(windows)]
+impl Drop for DwmAccent {
+  fn drop(&mut self) {
+    if let Some(key) = self.key {
+      unsafe { RegCloseKey(key as HKEY) };
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::Config;
+  use crate::rules::{RuleSet, WindowInfo};
+  use std::sync::atomic::AtomicUsize;
+
+  /// Answers with `color`, and reports a change `changes` times.
+  struct FakeAccent {
+    color: Arc<AtomicU32>,
+    changes: AtomicUsize,
+  }
+
+  impl AccentSource for FakeAccent {
+    fn read(&self) -> Option<u32> {
+      Some(self.color.load(Ordering::Relaxed))
+    }
+
+    fn wait_for_change(&self) -> bool {
+      self
+        .changes
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+        .is_ok()
+    }
+  }
+
+  #[test]
+  fn accent_rules_follow_the_source() {
+    let color = Arc::new(AtomicU32::new(0x00f6a0c6));
+    set_source(Box::new(FakeAccent {
+      color: color.clone(),
+      changes: AtomicUsize::new(1),
+    }));
+    let config: Config = serde_yaml::from_str(
+      "window_rules:\n  - Match: Global\n    active_border_color: accent\n    inactive_border_color: \"#000000\"\n",
+    )
+    .unwrap();
+    let rules = RuleSet::compile(&config).unwrap();
+    assert!(rules.uses_accent());
+    let window = WindowInfo::new("Notepad", "Notepad", 1);
+    assert_eq!(rules.colors_for(&window), Some((0x00f6a0c6, 0)));
+
+    color.store(0x00112233, Ordering::Relaxed);
+    // Not read again until the source reports the change.
+    assert_eq!(rules.colors_for(&window), Some((0x00f6a0c6, 0)));
+    let mut changed = Vec::new();
+    watch(|accent| changed.push(accent));
+    assert_eq!(changed, vec![0x00112233]);
+    assert_eq!(rules.colors_for(&window), Some((0x00112233, 0)));
+  }
+}
@@ -0,0 +1,639 @@
+use once_cell::sync::Lazy;
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::color::{format_amount, parse_amount, ColorSpec, Derivation, Moment};
+use crate::css::Rgba;
+
+static CLOCK: Lazy<AnimationClock> =
+  Lazy::new(|| AnimationClock::new(Box::new(SystemClock::new())));
+
+/// Time since some fixed start. The real one uses `Instant`, a test can
+/// step it by hand to sample animations at exact times.
+pub trait Clock: Send + Sync {
+  fn elapsed(&self) -> Duration;
+}
+
+pub struct SystemClock {
+  start: Instant,
+}
+
+impl SystemClock {
+  pub fn new() -> SystemClock {
+    SystemClock {
+      start: Instant::now(),
+    }
+  }
+}
+
+impl Default for SystemClock {
+  fn default() -> SystemClock {
+    SystemClock::new()
+  }
+}
+
+impl Clock for SystemClock {
+  fn elapsed(&self) -> Duration {
+    self.start.elapsed()
+  }
+}
+
+/// A clock that only moves when a test sets it. Clones share the time, so
+/// one can be boxed and the other kept to step it.
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub(crate) struct ManualClock(std::sync::Arc<AtomicU64>);
+
+#[cfg(test)]
+impl ManualClock {
+  pub(crate) fn set_ms(&self, ms: u64) {
+    self.0.store(ms, Ordering::Relaxed);
+  }
+}
+
+#[cfg(test)]
+impl Clock for ManualClock {
+  fn elapsed(&self) -> Duration {
+    Duration::from_millis(self.0.load(Ordering::Relaxed))
+  }
+}
+
+/// The time every animation is sampled at. It only moves on `tick`, so all
+/// windows painted in the same frame see the same point of an animation.
+pub struct AnimationClock {
+  clock: Box<dyn Clock>,
+  // f64 seconds, stored as bits so painting never takes a lock.
+  now: AtomicU64,
+}
+
+impl AnimationClock {
+  pub fn new(clock: Box<dyn Clock>) -> AnimationClock {
+    AnimationClock {
+      clock,
+      now: AtomicU64::new(0f64.to_bits()),
+    }
+  }
+
+  pub fn tick(&self) {
+    let now = self.clock.elapsed().as_secs_f64();
+    self.now.store(now.to_bits(), Ordering::Relaxed);
+  }
+
+  pub fn now(&self) -> f64 {
+    f64::from_bits(self.now.load(Ordering::Relaxed))
+  }
+}
+
+/// Advances the shared animation time, called once per frame by the paint thread.
+pub fn tick() {
+  CLOCK.tick()
+}
+
+/// Seconds since the app started, as of the last `tick`.
+pub fn now() -> f64 {
+  CLOCK.now()
+}
+
+/// A color that moves through a list of stops, written in config.yaml as
+/// `{ animate: [{ color: "#f00", duration_ms: 1000 }, ...], mode: loop }`,
+/// or as the `{ pulse: ..., period_ms: 2000 }` shorthand.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "AnimationDef", into = "AnimationDef")]
+pub struct Animation {
+  stops: Vec<Keyframe>,
+  mode: AnimationMode,
+  easing: Easing,
+  space: ColorSpace,
+  // Kept so a pulse is written back the way it was written.
+  pulse: Option<Pulse>,
+}
+
+/// A sine wave between two colors, or between a color and a dimmer
+/// version of it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pulse {
+  pub colors: PulseColors,
+  pub period_ms: f32,
+  /// How much darker a single color gets at the low point.
+  pub amount: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged, expecting = "pulse takes a color or a list of two colors")]
+pub enum PulseColors {
+  One(ColorSpec),
+  Two([ColorSpec; 2]),
+}
+
+const DEFAULT_PULSE_PERIOD_MS: f32 = 2000.0;
+const DEFAULT_PULSE_AMOUNT: f32 = 0.3;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Keyframe {
+  pub color: ColorSpec,
+  /// How long it takes to get from this stop to the next one.
+  pub duration_ms: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnimationMode {
+  /// After the last stop, goes on to the first one again.
+  #[default]
+  Loop,
+  /// Goes back and forth between the first and the last stop.
+  #[serde(alias = "ping-pong")]
+  PingPong,
+}
+
+/// Applied to every segment between two stops.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Easing {
+  #[default]
+  Linear,
+  EaseIn,
+  EaseOut,
+  EaseInOut,
+  /// Half a cosine wave, a ping-pong with it is a sine wave.
+  Sine,
+}
+
+/// Where colors between two stops are interpolated. OKLab looks even across
+/// hues, sRGB between stops 60 degrees apart follows the HSL hue wheel.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorSpace {
+  #[default]
+  Oklab,
+  Srgb,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AnimationDef {
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  animate: Option<Vec<Keyframe>>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pulse: Option<PulseColors>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  period_ms: Option<f32>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  amount: Option<Amount>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  mode: Option<AnimationMode>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  easing: Option<Easing>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  space: Option<ColorSpace>,
+}
+
+impl TryFrom<AnimationDef> for Animation {
+  type Error = String;
+
+  fn try_from(def: AnimationDef) -> Result<Animation, String> {
+    match (def.animate, def.pulse) {
+      (Some(stops), None) => {
+        if def.period_ms.is_some() || def.amount.is_some() {
+          return Err("period_ms and amount only work with pulse".to_string());
+        }
+        Animation::new(
+          stops,
+          def.mode.unwrap_or_default(),
+          def.easing.unwrap_or_default(),
+          def.space.unwrap_or_default(),
+        )
+      }
+      (None, Some(colors)) => {
+        if def.mode.is_some() || def.easing.is_some() {
+          return Err(
+            "a pulse always goes back and forth on a sine curve, it has no mode or easing"
+              .to_string(),
+          );
+        }
+        if let (PulseColors::Two(_), Some(_)) = (&colors, &def.amount) {
+          return Err("amount only works when pulsing a single color".to_string());
+        }
+        let pulse = Pulse {
+          colors,
+          period_ms: def.period_ms.unwrap_or(DEFAULT_PULSE_PERIOD_MS),
+          amount: def.amount.map(|a| a.0),
+        };
+        Animation::pulse(pulse, def.space.unwrap_or_default())
+      }
+      (Some(_), Some(_)) => Err("use either animate or pulse, not both".to_string()),
+      (None, None) => Err("an animation needs animate or pulse".to_string()),
+    }
+  }
+}
+
+impl From<Animation> for AnimationDef {
+  fn from(animation: Animation) -> AnimationDef {
+    let space = Some(animation.space).filter(|s| *s != ColorSpace::default());
+    match animation.pulse {
+      Some(pulse) => AnimationDef {
+        animate: None,
+        pulse: Some(pulse.colors),
+        period_ms: Some(pulse.period_ms),
+        amount: pulse.amount.map(Amount),
+        mode: None,
+        easing: None,
+        space,
+      },
+      None => AnimationDef {
+        animate: Some(animation.stops),
+        pulse: None,
+        period_ms: None,
+        amount: None,
+        mode: Some(animation.mode),
+        easing: Some(animation.easing),
+        space,
+      },
+    }
+  }
+}
+
+/// `30%` or `0.3` in config.yaml.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Amount(f32);
+
+impl Serialize for Amount {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format_amount(self.0))
+  }
+}
+
+impl<'de> Deserialize<'de> for Amount {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    struct AmountVisitor;
+
+    impl<'de> Visitor<'de> for AmountVisitor {
+      type Value = Amount;
+
+      fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an amount like 20% or 0.2")
+      }
+
+      fn visit_str<E: de::Error>(self, s: &str) -> Result<Amount, E> {
+        parse_amount(s).map(Amount).map_err(E::custom)
+      }
+
+      fn visit_f64<E: de::Error>(self, v: f64) -> Result<Amount, E> {
+        Ok(Amount(v as f32))
+      }
+
+      fn visit_u64<E: de::Error>(self, v: u64) -> Result<Amount, E> {
+        Ok(Amount(v as f32))
+      }
+
+      fn visit_i64<E: de::Error>(self, v: i64) -> Result<Amount, E> {
+        Ok(Amount(v as f32))
+      }
+    }
+
+    deserializer.deserialize_any(AmountVisitor)
+  }
+}
+
+impl Animation {
+  pub fn new(
+    stops: Vec<Keyframe>,
+    mode: AnimationMode,
+    easing: Easing,
+    space: ColorSpace,
+  ) -> Result<Animation, String> {
+    if stops.len() < 2 {
+      return Err("an animation needs at least two stops".to_string());
+    }
+    for (index, stop) in stops.iter().enumerate() {
+      if let ColorSpec::Default | ColorSpec::Transparent = stop.color {
+        return Err(format!(
+          "animate[{}]: \"{}\" isn't a color, it can't be animated",
+          index, stop.color
+        ));
+      }
+      if !stop.duration_ms.is_finite() || stop.duration_ms < 0.0 {
+        return Err(format!("animate[{}]: duration_ms can't be negative", index));
+      }
+    }
+    let animation = Animation {
+      stops,
+      mode,
+      easing,
+      space,
+      pulse: None,
+    };
+    if animation.cycle_ms() <= 0.0 {
+      return Err("an animation needs a duration_ms above zero".to_string());
+    }
+    Ok(animation)
+  }
+
+  /// A ping-pong between the two colors (or the color and a darker version
+  /// of it) with sine easing, which makes a full sine wave every `period_ms`.
+  pub fn pulse(pulse: Pulse, space: ColorSpace) -> Result<Animation, String> {
+    let (from, to) = match &pulse.colors {
+      PulseColors::One(color) => {
+        let amount = pulse.amount.unwrap_or(DEFAULT_PULSE_AMOUNT);
+        let dimmed = Derivation::Darken(color.clone(), amount);
+        (color.clone(), ColorSpec::Derived(Box::new(dimmed)))
+      }
+      PulseColors::Two([from, to]) => (from.clone(), to.clone()),
+    };
+    if !pulse.period_ms.is_finite() || pulse.period_ms <= 0.0 {
+      return Err("period_ms has to be above zero".to_string());
+    }
+    let half_period = pulse.period_ms / 2.0;
+    let stops = vec![
+      Keyframe {
+        color: from,
+        duration_ms: half_period,
+      },
+      Keyframe {
+        color: to,
+        duration_ms: half_period,
+      },
+    ];
+    let mut animation = Animation::new(stops, AnimationMode::PingPong, Easing::Sine, space)
+      .map_err(|e| e.replace("animate", "pulse"))?;
+    animation.pulse = Some(pulse);
+    Ok(animation)
+  }
+
+  pub fn stops(&self) -> &[Keyframe] {
+    &self.stops
+  }
+
+  /// Rebuilds the animation with every stop's color replaced. The result is
+  /// only meant for painting, a pulse isn't kept as one.
+  pub fn map_colors(
+    &self,
+    mut f: impl FnMut(&ColorSpec) -> Result<ColorSpec, String>,
+  ) -> Result<Animation, String> {
+    let stops = self
+      .stops
+      .iter()
+      .map(|stop| {
+        Ok(Keyframe {
+          color: f(&stop.color)?,
+          duration_ms: stop.duration_ms,
+        })
+      })
+      .collect::<Result<Vec<_>, String>>()?;
+    Ok(Animation {
+      stops,
+      pulse: None,
+      ..*self
+    })
+  }
+
+  /// Length of one full cycle.
+  pub fn cycle_ms(&self) -> f32 {
+    let forward = |stops: &[Keyframe]| stops.iter().map(|s| s.duration_ms).sum::<f32>();
+    match self.mode {
+      AnimationMode::Loop => forward(&self.stops),
+      // The last stop has nowhere to go, so its duration isn't used.
+      AnimationMode::PingPong => 2.0 * forward(&self.stops[..self.stops.len() - 1]),
+    }
+  }
+
+  /// The animation's color `seconds` after it started. `hue_offset` is
+  /// passed on to `rainbow` stops.
+  pub fn sample(&self, seconds: f64, hue_offset: f32) -> Option<Rgba> {
+    self.sample_at(&Moment::Live, seconds, hue_offset)
+  }
+
+  /// `sample` with `accent` and `rainbow` stops taken from `moment`.
+  pub(crate) fn sample_at(&self, moment: &Moment, seconds: f64, hue_offset: f32) -> Option<Rgba> {
+    let cycle_ms = self.cycle_ms() as f64;
+    let mut t = ((seconds * 1000.0).rem_euclid(cycle_ms)) as f32;
+
+    // Ping-pong plays the stops forward and then the same segments backwards.
+    let half = cycle_ms as f32 / 2.0;
+    let reverse = self.mode == AnimationMode::PingPong && t >= half;
+    if reverse {
+      t = 2.0 * half - t;
+    }
+
+    let last = match self.mode {
+      AnimationMode::Loop => self.stops.len(),
+      AnimationMode::PingPong => self.stops.len() - 1,
+    };
+    for index in 0..last {
+      let duration = self.stops[index].duration_ms;
+      if t < duration || index == last - 1 {
+        let from = &self.stops[index].color;
+        let to = &self.stops[(index + 1) % self.stops.len()].color;
+        let progress = if duration > 0.0 {
+          (t / duration).clamp(0.0, 1.0)
+        } else {
+          1.0
+        };
+        return self.interpolate(
+          from.rgba_at(moment, hue_offset)?,
+          to.rgba_at(moment, hue_offset)?,
+          self.easing.apply(progress),
+        );
+      }
+      t -= duration;
+    }
+    None
+  }
+
+  fn interpolate(&self, from: Rgba, to: Rgba, t: f32) -> Option<Rgba> {
+    let lerp = |x: f32, y: f32| x + (y - x) * t;
+    let alpha = lerp(from.a as f32, to.a as f32).round() as u8;
+    Some(match self.space {
+      ColorSpace::Oklab => {
+        let ((l1, a1, b1), (l2, a2, b2)) = (from.to_oklab(), to.to_oklab());
+        Rgba::from_oklab(lerp(l1, l2), lerp(a1, a2), lerp(b1, b2), alpha)
+      }
+      ColorSpace::Srgb => {
+        let channel = |x: u8, y: u8| lerp(x as f32, y as f32).round() as u8;
+        Rgba {
+          r: channel(from.r, to.r),
+          g: channel(from.g, to.g),
+          b: channel(from.b, to.b),
+          a: alpha,
+        }
+      }
+    })
+  }
+}
+
+impl Easing {
+  pub(crate) fn apply(self, t: f32) -> f32 {
+    match self {
+      Easing::Linear => t,
+      Easing::EaseIn => t * t,
+      Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+      Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+      Easing::Sine => (1.0 - (t * std::f32::consts::PI).cos()) / 2.0,
+    }
+  }
+}
+
+/// Only used in messages, animations are written as YAML maps.
+impl fmt::Display for Animation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if let Some(pulse) = &self.pulse {
+      return match &pulse.colors {
+        PulseColors::One(color) => write!(f, "pulse({}, {}ms)", color, pulse.period_ms),
+        PulseColors::Two([from, to]) => write!(f, "pulse({}, {}, {}ms)", from, to, pulse.period_ms),
+      };
+    }
+    let stops: Vec<String> = self
+      .stops
+      .iter()
+      .map(|s| format!("{} {}ms", s.color, s.duration_ms))
+      .collect();
+    write!(f, "animate({})", stops.join(", "))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const RED: ColorSpec = ColorSpec::Hex(0x0000ff);
+  const BLUE: ColorSpec = ColorSpec::Hex(0xff0000);
+  const LIME: ColorSpec = ColorSpec::Hex(0x00ff00);
+
+  fn stops(colors: &[ColorSpec]) -> Vec<Keyframe> {
+    colors
+      .iter()
+      .map(|color| Keyframe {
+        color: color.clone(),
+        duration_ms: 1000.0,
+      })
+      .collect()
+  }
+
+  fn animation(colors: &[ColorSpec], mode: AnimationMode) -> Animation {
+    Animation::new(stops(colors), mode, Easing::Linear, ColorSpace::Srgb).unwrap()
+  }
+
+  #[test]
+  fn loop_returns_to_the_first_stop() {
+    let animation = animation(&[RED, BLUE], AnimationMode::Loop);
+    assert_eq!(animation.cycle_ms(), 2000.0);
+    let at = |seconds| animation.sample(seconds, 0.0).unwrap();
+    assert_eq!(at(0.0), Rgba::opaque(255, 0, 0));
+    assert_eq!(at(0.5), Rgba::opaque(128, 0, 128));
+    assert_eq!(at(1.0), Rgba::opaque(0, 0, 255));
+    assert_eq!(at(1.5), Rgba::opaque(128, 0, 128));
+    assert_eq!(at(2.0), Rgba::opaque(255, 0, 0));
+  }
+
+  #[test]
+  fn ping_pong_plays_the_stops_backwards() {
+    let animation = animation(&[RED, LIME, BLUE], AnimationMode::PingPong);
+    // The last stop's duration isn't used.
+    assert_eq!(animation.cycle_ms(), 4000.0);
+    let at = |seconds| animation.sample(seconds, 0.0).unwrap();
+    assert_eq!(at(1.0), Rgba::opaque(0, 255, 0));
+    assert_eq!(at(2.0), Rgba::opaque(0, 0, 255));
+    assert_eq!(at(3.0), Rgba::opaque(0, 255, 0));
+    assert_eq!(at(4.0), Rgba::opaque(255, 0, 0));
+  }
+
+  #[test]
+  fn needs_two_stops_and_a_duration() {
+    let one = Animation::new(
+      stops(&[RED]),
+      AnimationMode::Loop,
+      Easing::Linear,
+      ColorSpace::Srgb,
+    );
+    assert_eq!(one.unwrap_err(), "an animation needs at least two stops");
+    let mut zero = stops(&[RED, BLUE]);
+    zero.iter_mut().for_each(|stop| stop.duration_ms = 0.0);
+    let zero = Animation::new(zero, AnimationMode::Loop, Easing::Linear, ColorSpace::Srgb);
+    assert_eq!(
+      zero.unwrap_err(),
+      "an animation needs a duration_ms above zero"
+    );
+  }
+
+  #[test]
+  fn clock_only_moves_on_tick() {
+    let time = ManualClock::default();
+    let clock = AnimationClock::new(Box::new(time.clone()));
+    time.set_ms(1500);
+    assert_eq!(clock.now(), 0.0);
+    clock.tick();
+    assert_eq!(clock.now(), 1.5);
+  }
+
+  fn pulse(colors: PulseColors, amount: Option<f32>) -> Animation {
+    let pulse = Pulse {
+      colors,
+      period_ms: 2000.0,
+      amount,
+    };
+    Animation::pulse(pulse, ColorSpace::Srgb).unwrap()
+  }
+
+  #[test]
+  fn pulse_is_a_sine_wave() {
+    let animation = pulse(PulseColors::Two([RED, BLUE]), None);
+    let time = ManualClock::default();
+    let clock = AnimationClock::new(Box::new(time.clone()));
+    let at = |ms| {
+      time.set_ms(ms);
+      clock.tick();
+      animation.sample(clock.now(), 0.0).unwrap()
+    };
+    assert_eq!(at(0), Rgba::opaque(255, 0, 0));
+    // A quarter period in, the sine is halfway.
+    assert_eq!(at(500), Rgba::opaque(128, 0, 128));
+    assert_eq!(at(1000), Rgba::opaque(0, 0, 255));
+    assert_eq!(at(1500), Rgba::opaque(128, 0, 128));
+    assert_eq!(at(2000), Rgba::opaque(255, 0, 0));
+    // Slow at the ends, fast in the middle.
+    assert!(at(250).r > 200);
+  }
+
+  #[test]
+  fn single_color_pulse_dims_by_amount() {
+    let animation = pulse(PulseColors::One(RED), Some(0.2));
+    let dimmed = ColorSpec::Derived(Box::new(Derivation::Darken(RED, 0.2)));
+    assert_eq!(animation.sample(0.0, 0.0), RED.rgba_at(&Moment::Live, 0.0));
+    assert_eq!(
+      animation.sample(1.0, 0.0),
+      dimmed.rgba_at(&Moment::Live, 0.0)
+    );
+    assert_eq!(animation.sample(2.0, 0.0), RED.rgba_at(&Moment::Live, 0.0));
+  }
+
+  #[test]
+  fn pulse_is_written_back_as_a_pulse() {
+    let yaml = "pulse: '#ff0000'\nperiod_ms: 1000.0\namount: 20%\n";
+    let animation: Animation = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(animation.cycle_ms(), 1000.0);
+    assert_eq!(serde_yaml::to_string(&animation).unwrap(), yaml);
+  }
+
+  #[test]
+  fn misspelled_fields_are_rejected() {
+    let yaml = "animate:\n- { color: red, durtion_ms: 500 }\n- { color: blue, duration_ms: 500 }\n";
+    let e = serde_yaml::from_str::<Animation>(yaml).unwrap_err();
+    assert!(
+      e.to_string().contains("unknown field `durtion_ms`"),
+      "{}",
+      e
+    );
+    let e = serde_yaml::from_str::<Animation>("pulse: red\nperiod: 500\n").unwrap_err();
+    assert!(e.to_string().contains("unknown field `period`"), "{}", e);
+  }
+
+  #[test]
+  fn pulse_has_no_mode() {
+    let e = serde_yaml::from_str::<Animation>("pulse: red\nmode: loop\n").unwrap_err();
+    assert!(e.to_string().contains("it has no mode or easing"), "{}", e);
+  }
+}
@@ -0,0 +1,481 @@
+use once_cell::sync::Lazy;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+#[cfg(unix)]
+use std::io;
+use std::sync::{Arc, Mutex};
+
+static BACKEND: Lazy<Option<Arc<dyn WindowBackend>>> = Lazy::new(system);
+// Stands in when there's no window system to talk to, nothing gets a border.
+static NO_BACKEND: Lazy<Arc<dyn WindowBackend>> = Lazy::new(|| Arc::new(MockBackend::new()));
+
+/// How many owners are followed up from a window. Real owner chains are a
+/// few windows long, a longer one is a cycle (a handle reused while the
+/// chain was read, or a backend reporting nonsense).
+pub const MAX_OWNER_DEPTH: usize = 32;
+
+/// What `WindowBackend::subscribe` calls for every event.
+pub type EventHandler = Box<dyn FnMut(WindowEvent)>;
+
+/// Everything cute-borders needs from the window system. Windows are
+/// identified by a `usize`, on Win32 that's the HWND.
+pub trait WindowBackend: Send + Sync {
+  /// Every top-level window that exists right now, in Z order.
+  fn enumerate(&self) -> Vec<usize>;
+  /// The attributes of a window that should get a border, `None` for
+  /// hidden, tool and child windows and for windows that are gone.
+  fn describe(&self, window: usize) -> Option<WindowAttributes>;
+  /// Whether the window still exists.
+  fn is_alive(&self, window: usize) -> bool;
+  /// The window that has focus, if any.
+  fn foreground(&self) -> Option<usize>;
+  /// The window that owns `window`, e.g. the main window of a dialog.
+  fn owner(&self, window: usize) -> Option<usize>;
+  /// The process a window belongs to, 0 if unknown.
+  fn pid(&self, window: usize) -> u32;
+  /// Where the window is on the desktop.
+  fn bounds(&self, window: usize) -> Option<Rect>;
+  /// The area covered by all monitors.
+  fn desktop(&self) -> Rect;
+  /// Sets the border color as a COLORREF, `DWMWA_COLOR_DEFAULT` resets it.
+  fn set_border_color(&self, window: usize, color: u32);
+  /// Called after the `set_border_color` calls of a frame, for backends
+  /// that can only apply the colors of all windows at once.
+  fn flush(&self) {}
+  /// Whether windows without focus can each have their own color. sway
+  /// and i3 have a single color for all of them.
+  fn has_per_window_colors(&self) -> bool {
+    true
+  }
+  /// Calls `on_event` for window events until the backend stops. Blocks
+  /// the calling thread, which is also the one `on_event` runs on.
+  fn subscribe(&self, on_event: EventHandler);
+
+  /// Whether `window` is `owner` or one of the windows owned by it,
+  /// directly or through other owned windows. Gives up after
+  /// `MAX_OWNER_DEPTH` owners, so a cycle can't hang the paint loop.
+  fn is_owned_by(&self, window: usize, owner: usize) -> bool {
+    let mut current = Some(window);
+    for _ in 0..=MAX_OWNER_DEPTH {
+      let Some(window) = current else {
+        return false;
+      };
+      if window == owner {
+        return true;
+      }
+      current = self.owner(window);
+    }
+    false
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowAttributes {
+  pub title: String,
+  pub class: String,
+  pub pid: u32,
+  /// Context menus get the active color along with the window they belong to.
+  pub is_menu: bool,
+}
+
+/// What happened to a window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WindowEvent {
+  Created(usize),
+  Destroyed(usize),
+  Shown(usize),
+  Hidden(usize),
+  NameChanged(usize),
+  Foreground(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rect {
+  pub left: i32,
+  pub top: i32,
+  pub right: i32,
+  pub bottom: i32,
+}
+
+impl Rect {
+  pub fn width(&self) -> i32 {
+    self.right - self.left
+  }
+
+  pub fn center_x(&self) -> i32 {
+    self.left + self.width() / 2
+  }
+}
+
+/// The backend of the platform we're running on.
+pub fn current() -> Arc<dyn WindowBackend> {
+  BACKEND.as_ref().unwrap_or(&NO_BACKEND).clone()
+}
+
+/// Whether we found a window system to draw borders on.
+pub fn is_available() -> bool {
+  BACKEND.is_some()
+}
+
+#[cfg(windows)]
+fn system() -> Option<Arc<dyn WindowBackend>> {
+  Some(Arc::new(crate::win32::Win32Backend))
+}
+
+// sway, i3 and Hyprland tell their clients where their sockets are.
+#[cfg(unix)]
+fn system() -> Option<Arc<dyn WindowBackend>> {
+  let backend: io::Result<Arc<dyn WindowBackend>> =
+    if let Some(sway) = crate::sway::SwayBackend::from_env() {
+      sway.map(|backend| Arc::new(backend) as _)
+    } else {
+      crate::hyprland::HyprlandBackend::from_env()?.map(|backend| Arc::new(backend) as _)
+    };
+  match backend {
+    Ok(backend) => Some(backend),
+    Err(e) => {
+      crate::logger::Logger::log(&format!(
+        "[ERROR] Failed to connect to the compositor: {}",
+        e
+      ));
+      None
+    }
+  }
+}
+
+#[cfg(not(any(windows, unix)))]
+fn system() -> Option<Arc<dyn WindowBackend>> {
+  None
+}
+
+/// A window of the `MockBackend`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockWindow {
+  pub attributes: WindowAttributes,
+  pub owner: Option<usize>,
+  pub bounds: Rect,
+}
+
+/// A desktop that only exists in memory. Windows are opened, renamed and
+/// focused through its methods, which queue the events Win32 would send,
+/// and the border colors it was told to set can be read back.
+pub struct MockBackend {
+  state: Mutex<MockState>,
+}
+
+#[derive(Default)]
+struct MockState {
+  // Ordered by handle so enumeration is deterministic.
+  windows: BTreeMap<usize, MockWindow>,
+  foreground: Option<usize>,
+  desktop: Rect,
+  borders: HashMap<usize, u32>,
+  border_calls: usize,
+  events: VecDeque<WindowEvent>,
+}
+
+impl MockBackend {
+  pub fn new() -> MockBackend {
+    MockBackend {
+      state: Mutex::new(MockState {
+        desktop: Rect {
+          left: 0,
+          top: 0,
+          right: 1920,
+          bottom: 1080,
+        },
+        ..Default::default()
+      }),
+    }
+  }
+
+  pub fn open(&self, window: usize, mock: MockWindow) {
+    let mut state = self.state.lock().unwrap();
+    state.windows.insert(window, mock);
+    state.events.push_back(WindowEvent::Created(window));
+    state.events.push_back(WindowEvent::Shown(window));
+  }
+
+  pub fn close(&self, window: usize) {
+    let mut state = self.state.lock().unwrap();
+    state.windows.remove(&window);
+    state.borders.remove(&window);
+    if state.foreground == Some(window) {
+      state.foreground = None;
+    }
+    state.events.push_back(WindowEvent::Destroyed(window));
+  }
+
+  pub fn rename(&self, window: usize, title: &str) {
+    let mut state = self.state.lock().unwrap();
+    if let Some(mock) = state.windows.get_mut(&window) {
+      mock.attributes.title = title.to_string();
+      state.events.push_back(WindowEvent::NameChanged(window));
+    }
+  }
+
+  pub fn focus(&self, window: usize) {
+    let mut state = self.state.lock().unwrap();
+    state.foreground = Some(window);
+    state.events.push_back(WindowEvent::Foreground(window));
+  }
+
+  pub fn set_desktop(&self, desktop: Rect) {
+    self.state.lock().unwrap().desktop = desktop;
+  }
+
+  /// The color last set on a window, `None` if it was never set.
+  pub fn border_color(&self, window: usize) -> Option<u32> {
+    self.state.lock().unwrap().borders.get(&window).copied()
+  }
+
+  /// How many times `set_border_color` was called.
+  pub fn border_calls(&self) -> usize {
+    self.state.lock().unwrap().border_calls
+  }
+}
+
+impl Default for MockBackend {
+  fn default() -> MockBackend {
+    MockBackend::new()
+  }
+}
+
+impl WindowBackend for MockBackend {
+  fn enumerate(&self) -> Vec<usize> {
+    self.state.lock().unwrap().windows.keys().copied().collect()
+  }
+
+  fn describe(&self, window: usize) -> Option<WindowAttributes> {
+    let state = self.state.lock().unwrap();
+    state
+      .windows
+      .get(&window)
+      .map(|mock| mock.attributes.clone())
+  }
+
+  fn is_alive(&self, window: usize) -> bool {
+    self.state.lock().unwrap().windows.contains_key(&window)
+  }
+
+  fn foreground(&self) -> Option<usize> {
+    self.state.lock().unwrap().foreground
+  }
+
+  fn owner(&self, window: usize) -> Option<usize> {
+    let state = self.state.lock().unwrap();
+    state.windows.get(&window).and_then(|mock| mock.owner)
+  }
+
+  fn pid(&self, window: usize) -> u32 {
+    let state = self.state.lock().unwrap();
+    state
+      .windows
+      .get(&window)
+      .map_or(0, |mock| mock.attributes.pid)
+  }
+
+  fn bounds(&self, window: usize) -> Option<Rect> {
+    let state = self.state.lock().unwrap();
+    state.windows.get(&window).map(|mock| mock.bounds)
+  }
+
+  fn desktop(&self) -> Rect {
+    self.state.lock().unwrap().desktop
+  }
+
+  fn set_border_color(&self, window: usize, color: u32) {
+    let mut state = self.state.lock().unwrap();
+    state.border_calls += 1;
+    if state.windows.contains_key(&window) {
+      state.borders.insert(window, color);
+    }
+  }
+
+  /// Delivers the queued events and returns once there are none left.
+  fn subscribe(&self, mut on_event: EventHandler) {
+    loop {
+      // Not holding the lock while `on_event` runs, it may call back into us.
+      let Some(event) = self.state.lock().unwrap().events.pop_front() else {
+        return;
+      };
+      on_event(event);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::animation::ManualClock;
+  use crate::border::Borders;
+  use crate::config::Config;
+  use crate::paint::Painter;
+  use crate::registry::WindowRegistry;
+  use crate::rules::RuleSet;
+  use crate::transition::Transitions;
+
+  const EDITOR_ACTIVE: u32 = 0x0000ff;
+  const EDITOR_INACTIVE: u32 = 0x000080;
+  const OTHER_ACTIVE: u32 = 0xff0000;
+  const OTHER_INACTIVE: u32 = 0x800000;
+
+  fn window(title: &str, owner: Option<usize>) -> MockWindow {
+    MockWindow {
+      attributes: WindowAttributes {
+        title: title.to_string(),
+        class: "Class".to_string(),
+        pid: 1,
+        is_menu: false,
+      },
+      owner,
+      bounds: Rect::default(),
+    }
+  }
+
+  /// A mock desktop and what the paint loop keeps for it between frames.
+  struct Desktop {
+    backend: Arc<MockBackend>,
+    registry: WindowRegistry,
+    config: Arc<Config>,
+    rules: Arc<RuleSet>,
+    borders: Borders,
+    transitions: Transitions,
+  }
+
+  impl Desktop {
+    fn new() -> Desktop {
+      let config = Config::from_yaml(
+        "window_rules:
+  - Match: Title
+    contains: Editor
+    active_border_color: \"#ff0000\"
+    inactive_border_color: \"#800000\"
+  - Match: Global
+    active_border_color: \"#0000ff\"
+    inactive_border_color: \"#000080\"
+",
+      )
+      .unwrap();
+      let backend = Arc::new(MockBackend::new());
+      Desktop {
+        registry: WindowRegistry::new(backend.clone()),
+        backend,
+        rules: Arc::new(RuleSet::compile(&config).unwrap()),
+        config: Arc::new(config),
+        borders: Borders::new(),
+        transitions: Transitions::new(Box::new(ManualClock::default())),
+      }
+    }
+
+    fn painter(&mut self) -> Painter<'_> {
+      Painter {
+        registry: &mut self.registry,
+        borders: &mut self.borders,
+        transitions: &mut self.transitions,
+      }
+    }
+
+    /// Delivers the queued events, then paints one frame.
+    fn paint(&mut self) {
+      let events = Arc::new(Mutex::new(Vec::new()));
+      let queue = events.clone();
+      let on_event = move |event| queue.lock().unwrap().push(event);
+      self.backend.subscribe(Box::new(on_event));
+      for event in std::mem::take(&mut *events.lock().unwrap()) {
+        self.registry.handle(event);
+      }
+
+      let (backend, config, rules) = (
+        self.backend.clone(),
+        self.config.clone(),
+        self.rules.clone(),
+      );
+      self
+        .painter()
+        .apply_colors(backend.as_ref(), &config, &rules, backend.foreground());
+    }
+  }
+
+  #[test]
+  fn focus_moves_the_active_color() {
+    let mut desktop = Desktop::new();
+    desktop.backend.open(1, window("notes - Editor", None));
+    desktop.backend.open(2, window("Terminal", None));
+    desktop.backend.focus(1);
+    desktop.paint();
+    assert_eq!(desktop.backend.border_color(1), Some(EDITOR_ACTIVE));
+    assert_eq!(desktop.backend.border_color(2), Some(OTHER_INACTIVE));
+
+    desktop.backend.focus(2);
+    desktop.paint();
+    assert_eq!(desktop.backend.border_color(1), Some(EDITOR_INACTIVE));
+    assert_eq!(desktop.backend.border_color(2), Some(OTHER_ACTIVE));
+    assert_eq!(desktop.backend.border_calls(), 4);
+
+    // Nothing changed, nothing is set again.
+    desktop.paint();
+    assert_eq!(desktop.backend.border_calls(), 4);
+  }
+
+  #[test]
+  fn owned_windows_share_the_active_color() {
+    let mut desktop = Desktop::new();
+    desktop.backend.open(1, window("notes - Editor", None));
+    desktop.backend.open(2, window("Save as", Some(1)));
+    desktop.backend.open(3, window("Replace", Some(2)));
+    desktop.backend.focus(1);
+    desktop.paint();
+    assert_eq!(desktop.backend.border_color(2), Some(OTHER_ACTIVE));
+    assert_eq!(desktop.backend.border_color(3), Some(OTHER_ACTIVE));
+  }
+
+  #[test]
+  fn renamed_and_destroyed_windows() {
+    let mut desktop = Desktop::new();
+    desktop.backend.open(1, window("Terminal", None));
+    desktop.backend.open(2, window("Terminal", None));
+    desktop.paint();
+    assert_eq!(desktop.backend.border_color(1), Some(OTHER_INACTIVE));
+
+    desktop.backend.rename(1, "Editor");
+    desktop.backend.close(2);
+    desktop.paint();
+    assert_eq!(desktop.backend.border_color(1), Some(EDITOR_INACTIVE));
+    assert_eq!(desktop.backend.border_color(2), None);
+    assert_eq!(desktop.registry.windows(), [1]);
+  }
+
+  #[test]
+  fn windows_gone_without_an_event_are_dropped() {
+    let mut desktop = Desktop::new();
+    desktop.backend.open(1, window("Terminal", None));
+    desktop.backend.open(2, window("Terminal", None));
+    desktop.paint();
+    desktop.backend.close(2);
+    // The destroy event got lost.
+    desktop.backend.subscribe(Box::new(|_| {}));
+    desktop.paint();
+    assert_eq!(desktop.registry.windows(), [1]);
+  }
+
+  #[test]
+  fn reset_gives_every_window_its_default_border() {
+    let mut desktop = Desktop::new();
+    desktop.backend.open(1, window("notes - Editor", None));
+    desktop.backend.open(2, window("Terminal", None));
+    desktop.paint();
+    let backend = desktop.backend.clone();
+    desktop.painter().reset_colors(backend.as_ref());
+    assert_eq!(backend.border_color(1), Some(crate::DWMWA_COLOR_DEFAULT));
+    assert_eq!(backend.border_color(2), Some(crate::DWMWA_COLOR_DEFAULT));
+  }
+
+  #[test]
+  fn owner_cycles_end() {
+    let backend = MockBackend::new();
+    backend.open(1, window("a", Some(2)));
+    backend.open(2, window("b", Some(1)));
+    assert!(backend.is_owned_by(1, 2));
+    assert!(!backend.is_owned_by(1, 3));
+  }
+}
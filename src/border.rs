@@ -0,0 +1,132 @@
+use once_cell::sync::Lazy;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, MutexGuard};
+
+static BORDERS: Lazy<Mutex<Borders>> = Lazy::new(|| Mutex::new(Borders::new()));
+
+/// The color last applied to each window, so a border is only set again
+/// when its color actually changes. Without animations that's almost never.
+pub struct Borders {
+  applied: HashMap<usize, u32>,
+  calls: u64,
+  skipped: u64,
+}
+
+impl Borders {
+  pub fn new() -> Borders {
+    Borders {
+      applied: HashMap::new(),
+      calls: 0,
+      skipped: 0,
+    }
+  }
+
+  /// Calls `apply` unless `window` already has `color`.
+  pub fn set(&mut self, window: usize, color: u32, apply: impl FnOnce(usize, u32)) {
+    if self.applied.get(&window) == Some(&color) {
+      self.skipped += 1;
+      return;
+    }
+    apply(window, color);
+    self.calls += 1;
+    self.applied.insert(window, color);
+  }
+
+  /// Forgets windows that were closed, their handles can be reused.
+  pub fn retain(&mut self, windows: &HashSet<usize>) {
+    self.applied.retain(|window, _| windows.contains(window));
+  }
+
+  /// One line for the status report.
+  pub fn report(&self) -> String {
+    let total = self.calls + self.skipped;
+    let skipped_percent = if total == 0 {
+      0.0
+    } else {
+      self.skipped as f64 * 100.0 / total as f64
+    };
+    format!(
+      "{} windows, {} border updates, {} skipped ({:.1}%)",
+      self.applied.len(),
+      self.calls,
+      self.skipped,
+      skipped_percent
+    )
+  }
+}
+
+impl Default for Borders {
+  fn default() -> Borders {
+    Borders::new()
+  }
+}
+
+pub(crate) fn lock() -> MutexGuard<'static, Borders> {
+  BORDERS.lock().unwrap()
+}
+
+pub fn report() -> String {
+  BORDERS.lock().unwrap().report()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const RED: u32 = 0x0000ff;
+  const BLUE: u32 = 0xff0000;
+
+  fn set(borders: &mut Borders, window: usize, color: u32) -> Option<(usize, u32)> {
+    let mut applied = None;
+    borders.set(window, color, |window, color| {
+      applied = Some((window, color))
+    });
+    applied
+  }
+
+  #[test]
+  fn unchanged_colors_are_skipped() {
+    let mut borders = Borders::new();
+    assert_eq!(set(&mut borders, 1, RED), Some((1, RED)));
+    assert_eq!(set(&mut borders, 1, RED), None);
+    assert_eq!(
+      borders.report(),
+      "1 windows, 1 border updates, 1 skipped (50.0%)"
+    );
+  }
+
+  #[test]
+  fn changed_colors_are_applied() {
+    let mut borders = Borders::new();
+    set(&mut borders, 1, RED);
+    assert_eq!(set(&mut borders, 1, BLUE), Some((1, BLUE)));
+    assert_eq!(set(&mut borders, 2, BLUE), Some((2, BLUE)));
+    assert_eq!(
+      borders.report(),
+      "2 windows, 3 border updates, 0 skipped (0.0%)"
+    );
+  }
+
+  #[test]
+  fn closed_windows_are_forgotten() {
+    let mut borders = Borders::new();
+    set(&mut borders, 1, RED);
+    set(&mut borders, 2, RED);
+    borders.retain(&HashSet::from([2]));
+    // A reused handle gets its color set again.
+    assert_eq!(set(&mut borders, 1, RED), Some((1, RED)));
+    assert_eq!(set(&mut borders, 2, RED), None);
+    assert_eq!(
+      borders.report(),
+      "2 windows, 3 border updates, 1 skipped (25.0%)"
+    );
+  }
+
+  #[test]
+  fn an_empty_report_has_no_percentage_to_divide() {
+    assert_eq!(
+      Borders::new().report(),
+      "0 windows, 0 border updates, 0 skipped (0.0%)"
+    );
+  }
+}
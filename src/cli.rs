@@ -0,0 +1,233 @@
+// src/cli.rs
+
+// Subcomandos de linha de comando. Nenhum deles mexe em janelas de verdade,
+// então funcionam em qualquer sistema.
+
+use std::path::{Path, PathBuf};
+
+use cute_borders::color::{ColorSpec, Moment};
+use cute_borders::config::Config;
+use cute_borders::eval::{self, Snapshot};
+use cute_borders::lint;
+use cute_borders::rules::RuleSet;
+use cute_borders::util::get_file_path;
+
+const EVAL_USAGE: &str = "uso: cute-borders eval [--config config.yaml] --windows janelas.json [--focused id] [--at ms] [--accent cor] [--json]";
+const EXPLAIN_USAGE: &str = "uso: cute-borders explain [--config config.yaml] --windows janelas.json [--focused id] [--window id] [--at ms] [--accent cor] [--json]";
+
+// A cor de destaque padrão do Windows, para o eval não depender do sistema em que roda.
+const DEFAULT_ACCENT: &str = "#0078d4";
+const LINT_USAGE: &str = "uso: cute-borders lint [--config config.yaml] [--json]";
+
+// Devolve a saída e o código de saída.
+type Subcommand = fn(&[String]) -> Result<(String, i32), String>;
+
+// Argumentos em comum do eval e do explain.
+struct EvalArgs {
+  config_path: PathBuf,
+  windows_path: PathBuf,
+  focused: Option<usize>,
+  window: Option<usize>,
+  // Quando e com que cor de destaque rainbow, accent e animações são resolvidos.
+  at_ms: f64,
+  accent: u32,
+  json: bool,
+}
+
+/// Roda o subcomando em `args` (sem o nome do programa) e devolve o código de saída,
+/// ou `None` se `args` não é um subcomando.
+pub fn run(args: &[String]) -> Option<i32> {
+  let (command, rest) = args.split_first()?;
+  let command: Subcommand = match command.as_str() {
+    "eval" => eval,
+    "explain" => explain,
+    "lint" => lint,
+    _ => return None,
+  };
+  // Antes de rodar, para os erros que o subcomando mostra no stderr aparecerem.
+  attach_console();
+  Some(match command(rest) {
+    Ok((output, code)) => {
+      print!("{}", output);
+      code
+    }
+    Err(e) => {
+      eprintln!("{}", e);
+      1
+    }
+  })
+}
+
+// Como o lint, sai com 1 se alguma regra foi descartada por não ter condição.
+fn eval(args: &[String]) -> Result<(String, i32), String> {
+  let args = parse_eval_args(args, EVAL_USAGE, false)?;
+  let (config, rules, code) = load_rules(&args.config_path)?;
+  let snapshot = Snapshot::load(&args.windows_path)?;
+  let evaluations = eval::evaluate(&rules, &snapshot, args.focused, &args.moment(&config))?;
+  let output = if args.json {
+    to_json(&evaluations)?
+  } else {
+    eval::format_table(&evaluations)
+  };
+  Ok((output, code))
+}
+
+// Igual ao eval, mas mostra cada regra considerada e por que a janela está ativa ou não.
+fn explain(args: &[String]) -> Result<(String, i32), String> {
+  let args = parse_eval_args(args, EXPLAIN_USAGE, true)?;
+  let (config, rules, code) = load_rules(&args.config_path)?;
+  let snapshot = Snapshot::load(&args.windows_path)?;
+  let mut explanations = eval::explain(&rules, &snapshot, args.focused, &args.moment(&config))?;
+  if let Some(window) = args.window {
+    explanations.retain(|explanation| explanation.evaluation.id == window);
+    if explanations.is_empty() {
+      return Err(format!(
+        "a janela {} não está em {}",
+        window,
+        args.windows_path.display()
+      ));
+    }
+  }
+  let output = if args.json {
+    to_json(&explanations)?
+  } else {
+    eval::format_explanations(&explanations)
+  };
+  Ok((output, code))
+}
+
+// Mostra no stderr os erros das regras, que o app só registra no log, e devolve o código de saída.
+fn load_rules(config_path: &Path) -> Result<(Config, RuleSet, i32), String> {
+  let (config, rules) = Config::load(config_path)?;
+  for error in rules.errors() {
+    eprintln!("{}: {}", config_path.display(), error);
+  }
+  let code = if rules.errors().is_empty() { 0 } else { 1 };
+  Ok((config, rules, code))
+}
+
+impl EvalArgs {
+  fn moment(&self, config: &Config) -> Moment {
+    Moment::Fixed {
+      accent: self.accent,
+      seconds: self.at_ms / 1000.0,
+      rainbow: config.rainbow_settings(),
+    }
+  }
+}
+
+// Sai com 1 se achou algum problema, para o CI falhar.
+fn lint(args: &[String]) -> Result<(String, i32), String> {
+  let mut config_path = default_config_path()?;
+  let mut json = false;
+
+  let mut args = args.iter();
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--config" => {
+        let value = args
+          .next()
+          .ok_or_else(|| format!("{} precisa de um valor\n{}", arg, LINT_USAGE))?;
+        config_path = PathBuf::from(value);
+      }
+      "--json" => json = true,
+      _ => return Err(format!("argumento desconhecido: {}\n{}", arg, LINT_USAGE)),
+    }
+  }
+
+  let findings = lint::lint_file(&config_path)?;
+  let code = if findings.is_empty() { 0 } else { 1 };
+  let output = if json {
+    to_json(&findings)?
+  } else {
+    lint::format_findings(&findings)
+  };
+  Ok((output, code))
+}
+
+// O config.yaml do app, a não ser que --config diga outro.
+fn default_config_path() -> Result<PathBuf, String> {
+  get_file_path("config.yaml")
+    .map(PathBuf::from)
+    .map_err(|e| e.to_string())
+}
+
+fn parse_eval_args(args: &[String], usage: &str, accepts_window: bool) -> Result<EvalArgs, String> {
+  let mut config_path = default_config_path()?;
+  let mut windows_path = None;
+  let mut focused = None;
+  let mut window = None;
+  let mut at_ms = 0.0;
+  let mut accent = parse_accent(DEFAULT_ACCENT)?;
+  let mut json = false;
+
+  let mut args = args.iter();
+  while let Some(arg) = args.next() {
+    let mut value = || {
+      args
+        .next()
+        .ok_or_else(|| format!("{} precisa de um valor\n{}", arg, usage))
+    };
+    match arg.as_str() {
+      "--config" => config_path = PathBuf::from(value()?),
+      "--windows" => windows_path = Some(PathBuf::from(value()?)),
+      "--focused" => focused = Some(parse_id(value()?)?),
+      "--window" if accepts_window => window = Some(parse_id(value()?)?),
+      "--at" => at_ms = parse_ms(value()?)?,
+      "--accent" => accent = parse_accent(value()?)?,
+      "--json" => json = true,
+      _ => return Err(format!("argumento desconhecido: {}\n{}", arg, usage)),
+    }
+  }
+  Ok(EvalArgs {
+    config_path,
+    windows_path: windows_path.ok_or_else(|| usage.to_string())?,
+    focused,
+    window,
+    at_ms,
+    accent,
+    json,
+  })
+}
+
+fn parse_ms(ms: &str) -> Result<f64, String> {
+  ms.parse::<f64>()
+    .ok()
+    .filter(|ms| ms.is_finite() && *ms >= 0.0)
+    .ok_or_else(|| format!("--at espera milissegundos, não {}", ms))
+}
+
+// Aceita qualquer cor opaca do config.yaml que não dependa de outra.
+fn parse_accent(color: &str) -> Result<u32, String> {
+  match color.parse::<ColorSpec>() {
+    Ok(ColorSpec::Hex(colorref)) => Ok(colorref),
+    Ok(_) => Err(format!("--accent espera uma cor fixa, não {}", color)),
+    Err(e) => Err(format!("--accent: {}", e)),
+  }
+}
+
+fn to_json(value: &impl serde::Serialize) -> Result<String, String> {
+  serde_json::to_string_pretty(value)
+    .map(|json| json + "\n")
+    .map_err(|e| e.to_string())
+}
+
+// HWNDs costumam aparecer em hexadecimal, então aceita os dois.
+fn parse_id(id: &str) -> Result<usize, String> {
+  let parsed = match id.strip_prefix("0x") {
+    Some(hex) => usize::from_str_radix(hex, 16),
+    None => id.parse(),
+  };
+  parsed.map_err(|_| format!("id de janela inválido: {}", id))
+}
+
+// O executável usa o subsistema "windows", sem console. Para a saída aparecer
+// no terminal de onde foi chamado, se conecta ao console do processo pai.
+#[cfg(windows)]
+fn attach_console() {
+  use winapi::um::wincon::{AttachConsole, ATTACH_PARENT_PROCESS};
+  unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
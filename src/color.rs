@@ -0,0 +1,749 @@
+use serde::de::{self, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::accent;
+use crate::animation::{self, Animation};
+use crate::css::{self, Rgba};
+use crate::rainbow::{self, Rainbow, RainbowSettings};
+use crate::{DWMWA_COLOR_DEFAULT, DWMWA_COLOR_NONE};
+
+/// A border color as written in config.yaml. Parsed (and rejected) when the
+/// config is loaded, so only the dynamic ones need work on every frame.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum ColorSpec {
+  /// A fixed color, stored as a COLORREF (0x00bbggrr).
+  Hex(u32),
+  /// A color with alpha, only valid in config.yaml. It's blended against
+  /// `alpha_backdrop` when the rules are compiled. Keeps the text it was
+  /// written as, for error messages and for writing the config back.
+  Translucent(Rgba, String),
+  /// The default windows 11 border.
+  #[default]
+  Default,
+  /// No border at all.
+  Transparent,
+  /// The system accent color.
+  Accent,
+  /// The current color of the rainbow cycle.
+  Rainbow,
+  /// `$name`, a reference into the config's `palette`. Replaced by the
+  /// palette color when the rules are compiled.
+  Named(String),
+  /// A color computed from other colors, e.g. `darken(accent, 20%)`.
+  Derived(Box<Derivation>),
+  /// Keyframes written as `{ animate: [...] }`.
+  Animated(Box<Animation>),
+}
+
+/// Color functions. Everything except `over` works in OKLab/OKLCH so the
+/// amounts look the same across hues. Operands with alpha are blended
+/// against `alpha_backdrop` first, like any other color, except the
+/// foreground of `over`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Derivation {
+  /// Adds to the OKLCH lightness, `lighten(accent, 10%)`.
+  Lighten(ColorSpec, f32),
+  /// Subtracts from the OKLCH lightness.
+  Darken(ColorSpec, f32),
+  /// Scales the chroma up, `saturate(accent, 50%)` is 1.5x the chroma.
+  Saturate(ColorSpec, f32),
+  /// Scales the chroma down, `desaturate(accent, 100%)` is gray.
+  Desaturate(ColorSpec, f32),
+  /// Rotates the hue by 180 degrees.
+  Complement(ColorSpec),
+  /// Interpolates in OKLab, the amount is how much of the second color is used.
+  Mix(ColorSpec, ColorSpec, f32),
+  /// Composites the first (translucent) color over the second one.
+  Over(ColorSpec, ColorSpec),
+}
+
+/// `palette` entries resolved down to actual colors.
+pub type Palette = HashMap<String, ColorSpec>;
+
+/// What `accent`, `rainbow` and animations are resolved against.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Moment {
+  /// The app's accent, rainbow and animation clock as they are right now.
+  Live,
+  /// Fixed values, so `eval` gives the same colors on every run.
+  Fixed {
+    /// COLORREF, like `accent::current`.
+    accent: u32,
+    /// Seconds since the app started, for the rainbow and animations.
+    seconds: f64,
+    rainbow: RainbowSettings,
+  },
+}
+
+impl Moment {
+  fn accent(&self) -> u32 {
+    match self {
+      Moment::Live => accent::current(),
+      Moment::Fixed { accent, .. } => *accent,
+    }
+  }
+
+  fn rainbow(&self, hue_offset: f32) -> u32 {
+    match self {
+      Moment::Live => Rainbow::get_color(hue_offset),
+      Moment::Fixed {
+        seconds, rainbow, ..
+      } => {
+        let hue = rainbow::hue_at(*seconds, rainbow) + hue_offset;
+        rainbow::color_at(hue, rainbow.saturation, rainbow.lightness)
+      }
+    }
+  }
+
+  fn seconds(&self) -> f64 {
+    match self {
+      Moment::Live => animation::now(),
+      Moment::Fixed { seconds, .. } => *seconds,
+    }
+  }
+}
+
+impl ColorSpec {
+  /// The COLORREF to paint right now. `hue_offset` shifts `rainbow`, so
+  /// windows can be at different points of the cycle.
+  pub fn resolve(&self, hue_offset: f32) -> u32 {
+    self.resolve_at(&Moment::Live, hue_offset)
+  }
+
+  /// `resolve` with the dynamic colors taken from `moment`.
+  pub fn resolve_at(&self, moment: &Moment, hue_offset: f32) -> u32 {
+    match self {
+      ColorSpec::Hex(colorref) => *colorref,
+      // RuleSet::compile turns these into Hex, DWM ignores alpha anyway.
+      ColorSpec::Translucent(rgba, _) => rgba.to_colorref(),
+      ColorSpec::Default => DWMWA_COLOR_DEFAULT,
+      ColorSpec::Transparent => DWMWA_COLOR_NONE,
+      ColorSpec::Accent => moment.accent(),
+      ColorSpec::Rainbow => moment.rainbow(hue_offset),
+      ColorSpec::Derived(_) | ColorSpec::Animated(_) => self
+        .rgba_at(moment, hue_offset)
+        .map_or(DWMWA_COLOR_DEFAULT, |rgba| rgba.to_colorref()),
+      // Never reached, RuleSet::compile only keeps resolved colors.
+      ColorSpec::Named(_) => DWMWA_COLOR_DEFAULT,
+    }
+  }
+
+  /// A painted COLORREF as a color, `default` and `transparent` included.
+  pub fn from_colorref(colorref: u32) -> ColorSpec {
+    match colorref {
+      DWMWA_COLOR_DEFAULT => ColorSpec::Default,
+      DWMWA_COLOR_NONE => ColorSpec::Transparent,
+      colorref => ColorSpec::Hex(colorref),
+    }
+  }
+
+  /// The color at `moment`. `None` for default and transparent,
+  /// which aren't actual colors.
+  pub(crate) fn rgba_at(&self, moment: &Moment, hue_offset: f32) -> Option<Rgba> {
+    match self {
+      ColorSpec::Hex(colorref) => Some(Rgba::from_colorref(*colorref)),
+      ColorSpec::Translucent(rgba, _) => Some(*rgba),
+      ColorSpec::Accent => Some(Rgba::from_colorref(moment.accent())),
+      ColorSpec::Rainbow => Some(Rgba::from_colorref(moment.rainbow(hue_offset))),
+      ColorSpec::Derived(derivation) => derivation.evaluate(moment, hue_offset),
+      ColorSpec::Animated(animation) => {
+        animation.sample_at(moment, moment.seconds(), hue_offset)
+      }
+      ColorSpec::Default | ColorSpec::Transparent | ColorSpec::Named(_) => None,
+    }
+  }
+
+  /// Whether the color can change while running (accent, rainbow, an
+  /// animation or a function of them).
+  pub fn is_dynamic(&self) -> bool {
+    self.any(&|c| matches!(c, ColorSpec::Accent | ColorSpec::Rainbow | ColorSpec::Animated(_)))
+  }
+
+  /// Whether the color has to be repainted when the system accent changes.
+  pub fn uses_accent(&self) -> bool {
+    self.any(&|c| *c == ColorSpec::Accent)
+  }
+
+  /// Whether the rainbow has to keep ticking for this color.
+  pub fn uses_rainbow(&self) -> bool {
+    self.any(&|c| *c == ColorSpec::Rainbow)
+  }
+
+  /// Whether the color changes on its own and has to be repainted every frame.
+  pub fn is_animated(&self) -> bool {
+    self.any(&|c| matches!(c, ColorSpec::Rainbow | ColorSpec::Animated(_)))
+  }
+
+  /// Whether `f` holds for this color or any color it's made of.
+  fn any(&self, f: &dyn Fn(&ColorSpec) -> bool) -> bool {
+    f(self)
+      || match self {
+        ColorSpec::Derived(derivation) => derivation.operands().iter().any(|c| c.any(f)),
+        ColorSpec::Animated(animation) => animation.stops().iter().any(|s| s.color.any(f)),
+        _ => false,
+      }
+  }
+
+  /// Computes color functions that only depend on fixed colors once, up front.
+  pub fn simplify(self) -> ColorSpec {
+    match self {
+      ColorSpec::Derived(_) if !self.is_dynamic() => ColorSpec::Hex(self.resolve(0.0)),
+      color => color,
+    }
+  }
+
+  /// Replaces every `$name` reference, including the ones inside color functions,
+  /// with the palette color it points to.
+  pub fn lookup(&self, palette: &Palette) -> Result<ColorSpec, String> {
+    self.map_names(&mut |name| {
+      palette
+        .get(name)
+        .cloned()
+        .ok_or_else(|| format!("undefined palette color ${}", name))
+    })
+  }
+
+  fn map_names(
+    &self,
+    f: &mut dyn FnMut(&str) -> Result<ColorSpec, String>,
+  ) -> Result<ColorSpec, String> {
+    match self {
+      ColorSpec::Named(name) => f(name),
+      ColorSpec::Derived(derivation) => Ok(ColorSpec::Derived(Box::new(
+        derivation.map_operands(|c| match c.map_names(f)? {
+          // Caught when parsing, unless it comes from the palette.
+          color @ (ColorSpec::Default | ColorSpec::Transparent) => Err(format!(
+            "{} is {}, it can't be used in a color function",
+            c, color
+          )),
+          color => Ok(color),
+        })?,
+      ))),
+      ColorSpec::Animated(animation) => Ok(ColorSpec::Animated(Box::new(
+        animation.map_colors(|c| c.map_names(f))?,
+      ))),
+      color => Ok(color.clone()),
+    }
+  }
+}
+
+impl Derivation {
+  fn operands(&self) -> Vec<&ColorSpec> {
+    match self {
+      Derivation::Lighten(c, _)
+      | Derivation::Darken(c, _)
+      | Derivation::Saturate(c, _)
+      | Derivation::Desaturate(c, _)
+      | Derivation::Complement(c) => vec![c],
+      Derivation::Mix(a, b, _) | Derivation::Over(a, b) => vec![a, b],
+    }
+  }
+
+  pub(crate) fn map_operands(
+    &self,
+    mut f: impl FnMut(&ColorSpec) -> Result<ColorSpec, String>,
+  ) -> Result<Derivation, String> {
+    Ok(match self {
+      Derivation::Lighten(c, amount) => Derivation::Lighten(f(c)?, *amount),
+      Derivation::Darken(c, amount) => Derivation::Darken(f(c)?, *amount),
+      Derivation::Saturate(c, amount) => Derivation::Saturate(f(c)?, *amount),
+      Derivation::Desaturate(c, amount) => Derivation::Desaturate(f(c)?, *amount),
+      Derivation::Complement(c) => Derivation::Complement(f(c)?),
+      Derivation::Mix(a, b, amount) => Derivation::Mix(f(a)?, f(b)?, *amount),
+      Derivation::Over(a, b) => Derivation::Over(f(a)?, f(b)?),
+    })
+  }
+
+  fn evaluate(&self, moment: &Moment, hue_offset: f32) -> Option<Rgba> {
+    let adjust_lch = |c: &ColorSpec, adjust: &dyn Fn(f32, f32, f32) -> (f32, f32, f32)| {
+      let rgba = c.rgba_at(moment, hue_offset)?;
+      let (l, chroma, h) = rgba.to_oklch();
+      let (l, chroma, h) = adjust(l, chroma, h);
+      Some(Rgba::from_oklch(l.clamp(0.0, 1.0), chroma.max(0.0), h, rgba.a))
+    };
+
+    match self {
+      Derivation::Lighten(c, amount) => adjust_lch(c, &|l, ch, h| (l + amount, ch, h)),
+      Derivation::Darken(c, amount) => adjust_lch(c, &|l, ch, h| (l - amount, ch, h)),
+      Derivation::Saturate(c, amount) => adjust_lch(c, &|l, ch, h| (l, ch * (1.0 + amount), h)),
+      Derivation::Desaturate(c, amount) => adjust_lch(c, &|l, ch, h| (l, ch * (1.0 - amount), h)),
+      Derivation::Complement(c) => adjust_lch(c, &|l, ch, h| (l, ch, (h + 180.0) % 360.0)),
+      Derivation::Mix(a, b, amount) => {
+        let (a, b) = (a.rgba_at(moment, hue_offset)?, b.rgba_at(moment, hue_offset)?);
+        let ((l1, a1, b1), (l2, a2, b2)) = (a.to_oklab(), b.to_oklab());
+        let lerp = |x: f32, y: f32| x + (y - x) * amount.clamp(0.0, 1.0);
+        let alpha = lerp(a.a as f32, b.a as f32).round() as u8;
+        Some(Rgba::from_oklab(lerp(l1, l2), lerp(a1, a2), lerp(b1, b2), alpha))
+      }
+      Derivation::Over(foreground, backdrop) => {
+        let foreground = foreground.rgba_at(moment, hue_offset)?;
+        Some(foreground.over(backdrop.rgba_at(moment, hue_offset)?))
+      }
+    }
+  }
+
+  /// Parses `name(args)` if `name` is one of the color functions above.
+  fn parse(s: &str) -> Option<Result<Derivation, String>> {
+    let (name, args) = s.strip_suffix(')')?.split_once('(')?;
+    let name = name.trim().to_lowercase();
+    let (min_args, max_args) = match name.as_str() {
+      "complement" => (1, 1),
+      "lighten" | "darken" | "saturate" | "desaturate" | "over" => (2, 2),
+      "mix" => (2, 3),
+      _ => return None,
+    };
+
+    let args = split_top_level(args);
+    let derivation = if args.len() < min_args || args.len() > max_args {
+      Err(format!("wrong number of arguments for {}()", name))
+    } else {
+      Derivation::from_args(&name, &args)
+    };
+    Some(derivation.map_err(|e| format!("invalid color \"{}\": {}", s, e)))
+  }
+
+  fn from_args(name: &str, args: &[&str]) -> Result<Derivation, String> {
+    let color = |i: usize| parse_operand(args[i]);
+    let amount = |i: usize| parse_amount(args[i]);
+    Ok(match name {
+      "lighten" => Derivation::Lighten(color(0)?, amount(1)?),
+      "darken" => Derivation::Darken(color(0)?, amount(1)?),
+      "saturate" => Derivation::Saturate(color(0)?, amount(1)?),
+      "desaturate" => Derivation::Desaturate(color(0)?, amount(1)?),
+      "complement" => Derivation::Complement(color(0)?),
+      "mix" => Derivation::Mix(color(0)?, color(1)?, if args.len() == 3 { amount(2)? } else { 0.5 }),
+      _ => Derivation::Over(color(0)?, color(1)?),
+    })
+  }
+}
+
+/// Splits on commas that aren't nested inside parentheses.
+fn split_top_level(args: &str) -> Vec<&str> {
+  let mut parts = Vec::new();
+  let (mut depth, mut start) = (0, 0);
+  for (i, c) in args.char_indices() {
+    match c {
+      '(' => depth += 1,
+      ')' => depth -= 1,
+      ',' if depth == 0 => {
+        parts.push(args[start..i].trim());
+        start = i + 1;
+      }
+      _ => {}
+    }
+  }
+  parts.push(args[start..].trim());
+  parts
+}
+
+fn parse_operand(arg: &str) -> Result<ColorSpec, String> {
+  match arg.parse::<ColorSpec>()? {
+    ColorSpec::Default | ColorSpec::Transparent => Err(format!(
+      "\"{}\" isn't a color, it can't be used in a color function",
+      arg
+    )),
+    color => Ok(color),
+  }
+}
+
+/// `20%` or `0.2`.
+pub(crate) fn parse_amount(arg: &str) -> Result<f32, String> {
+  let (number, scale) = match arg.strip_suffix('%') {
+    Some(percent) => (percent.trim(), 100.0),
+    None => (arg, 1.0),
+  };
+  number
+    .parse::<f32>()
+    .ok()
+    .filter(|v| v.is_finite())
+    .map(|v| v / scale)
+    .ok_or_else(|| format!("\"{}\" is not an amount like 20% or 0.2", arg))
+}
+
+/// Amounts are written back as percentages.
+pub(crate) fn format_amount(amount: f32) -> String {
+  format!("{}%", (amount * 100.0 * 1000.0).round() / 1000.0)
+}
+
+/// Follows `$name` references between palette entries, reporting
+/// undefined names and cycles.
+pub fn resolve_palette(palette: &BTreeMap<String, ColorSpec>) -> Result<Palette, String> {
+  let mut resolved = Palette::new();
+  for name in palette.keys() {
+    let mut chain = Vec::new();
+    resolve_palette_entry(name, palette, &mut resolved, &mut chain)
+      .map_err(|e| format!("palette.{}: {}", name, e))?;
+  }
+  Ok(resolved)
+}
+
+fn resolve_palette_entry(
+  name: &str,
+  palette: &BTreeMap<String, ColorSpec>,
+  resolved: &mut Palette,
+  chain: &mut Vec<String>,
+) -> Result<ColorSpec, String> {
+  if let Some(color) = resolved.get(name) {
+    return Ok(color.clone());
+  }
+  if chain.iter().any(|n| n == name) {
+    chain.push(name.to_string());
+    let cycle: Vec<String> = chain.iter().map(|n| format!("${}", n)).collect();
+    return Err(format!("palette cycle {}", cycle.join(" -> ")));
+  }
+
+  let color = palette
+    .get(name)
+    .ok_or_else(|| format!("undefined palette color ${}", name))?;
+  chain.push(name.to_string());
+  let color = color.map_names(&mut |next| resolve_palette_entry(next, palette, resolved, chain))?;
+  chain.pop();
+
+  resolved.insert(name.to_string(), color.clone());
+  Ok(color)
+}
+
+impl FromStr for ColorSpec {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim().to_lowercase().as_str() {
+      // An empty string used to be the only way to say "default", keep accepting it.
+      "" | "default" => Ok(ColorSpec::Default),
+      "transparent" | "none" => Ok(ColorSpec::Transparent),
+      "accent" => Ok(ColorSpec::Accent),
+      "rainbow" => Ok(ColorSpec::Rainbow),
+      _ if s.trim().starts_with('$') => {
+        let name = &s.trim()[1..];
+        if !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+          Ok(ColorSpec::Named(name.to_string()))
+        } else {
+          Err(format!("invalid palette reference \"{}\"", s.trim()))
+        }
+      }
+      _ => match Derivation::parse(s.trim()) {
+        Some(derivation) => derivation.map(|d| ColorSpec::Derived(Box::new(d))),
+        None => css::parse(s).map(|rgba| match rgba.a {
+          255 => ColorSpec::Hex(rgba.to_colorref()),
+          _ => ColorSpec::Translucent(rgba, s.trim().to_string()),
+        }),
+      },
+    }
+  }
+}
+
+impl fmt::Display for ColorSpec {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ColorSpec::Hex(colorref) => {
+        let r = colorref & 0xFF;
+        let g = (colorref >> 8) & 0xFF;
+        let b = (colorref >> 16) & 0xFF;
+        write!(f, "#{:02x}{:02x}{:02x}", r, g, b)
+      }
+      ColorSpec::Translucent(_, text) => write!(f, "{}", text),
+      ColorSpec::Default => write!(f, "default"),
+      ColorSpec::Transparent => write!(f, "transparent"),
+      ColorSpec::Accent => write!(f, "accent"),
+      ColorSpec::Rainbow => write!(f, "rainbow"),
+      ColorSpec::Named(name) => write!(f, "${}", name),
+      ColorSpec::Derived(derivation) => write!(f, "{}", derivation),
+      ColorSpec::Animated(animation) => write!(f, "{}", animation),
+    }
+  }
+}
+
+impl fmt::Display for Derivation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Derivation::Lighten(c, amount) => write!(f, "lighten({}, {})", c, format_amount(*amount)),
+      Derivation::Darken(c, amount) => write!(f, "darken({}, {})", c, format_amount(*amount)),
+      Derivation::Saturate(c, amount) => write!(f, "saturate({}, {})", c, format_amount(*amount)),
+      Derivation::Desaturate(c, amount) => write!(f, "desaturate({}, {})", c, format_amount(*amount)),
+      Derivation::Complement(c) => write!(f, "complement({})", c),
+      Derivation::Mix(a, b, amount) => write!(f, "mix({}, {}, {})", a, b, format_amount(*amount)),
+      Derivation::Over(a, b) => write!(f, "over({}, {})", a, b),
+    }
+  }
+}
+
+impl Serialize for ColorSpec {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    match self {
+      ColorSpec::Animated(animation) => animation.serialize(serializer),
+      color => serializer.collect_str(color),
+    }
+  }
+}
+
+/// A string, or a map for animations.
+impl<'de> Deserialize<'de> for ColorSpec {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    struct ColorSpecVisitor;
+
+    impl<'de> Visitor<'de> for ColorSpecVisitor {
+      type Value = ColorSpec;
+
+      fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a color or an animation")
+      }
+
+      fn visit_str<E: de::Error>(self, s: &str) -> Result<ColorSpec, E> {
+        s.parse().map_err(E::custom)
+      }
+
+      fn visit_map<M: MapAccess<'de>>(self, map: M) -> Result<ColorSpec, M::Error> {
+        let animation = Animation::deserialize(de::value::MapAccessDeserializer::new(map))?;
+        Ok(ColorSpec::Animated(Box::new(animation)))
+      }
+    }
+
+    deserializer.deserialize_any(ColorSpecVisitor)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::Config;
+  use crate::rules::RuleSet;
+
+  #[test]
+  fn errors_quote_the_color_as_written() {
+    let e = "#FFG".parse::<ColorSpec>().unwrap_err();
+    assert!(e.contains("\"#FFG\""), "{}", e);
+    let e = "Rgb(1, 2)".parse::<ColorSpec>().unwrap_err();
+    assert!(e.contains("\"Rgb(1, 2)\""), "{}", e);
+  }
+
+  #[test]
+  fn translucent_colors_keep_their_text() {
+    let color: ColorSpec = "#ff08".parse().unwrap();
+    assert_eq!(color.to_string(), "#ff08");
+
+    let config = Config::from_yaml("window_rules:\n  - Match: Global\n    active_border_color: \"#ff08\"\n").unwrap();
+    let e = RuleSet::compile(&config).err().unwrap();
+    assert!(e.contains("\"#ff08\" has an alpha channel"), "{}", e);
+  }
+
+  #[test]
+  fn default_is_the_windows_border() {
+    assert_eq!(ColorSpec::default(), ColorSpec::Default);
+  }
+
+  fn compile(yaml: &str) -> Result<RuleSet, String> {
+    RuleSet::compile(&Config::from_yaml(yaml).unwrap())
+  }
+
+  fn palette(yaml: &str) -> Result<Palette, String> {
+    resolve_palette(&Config::from_yaml(yaml).unwrap().palette)
+  }
+
+  #[test]
+  fn palette_names_are_looked_up() {
+    let rules = compile(
+      "palette:
+  mauve: '#c6a0f6'
+window_rules:
+  - Match: Global
+    active_border_color: $mauve
+    inactive_border_color: darken($mauve, 20%)
+",
+    )
+    .unwrap();
+    let (active, inactive) = rules.colors_of(0, 0.0);
+    assert_eq!(active, 0xf6a0c6);
+    assert_eq!(
+      inactive,
+      "darken(#c6a0f6, 20%)".parse::<ColorSpec>().unwrap().resolve(0.0)
+    );
+  }
+
+  #[test]
+  fn palette_entries_can_use_each_other() {
+    let palette = palette(
+      "palette:
+  base: '#1e1e2e'
+  surface: lighten($base, 10%)
+  border: $surface
+window_rules: []
+",
+    )
+    .unwrap();
+    let surface: ColorSpec = "lighten(#1e1e2e, 10%)".parse().unwrap();
+    assert_eq!(palette["base"], ColorSpec::Hex(0x2e1e1e));
+    assert_eq!(palette["surface"], surface);
+    assert_eq!(palette["border"], surface);
+  }
+
+  #[test]
+  fn palette_cycles_are_errors() {
+    let error = palette(
+      "palette:
+  a: $b
+  b: mix($c, red)
+  c: $a
+window_rules: []
+",
+    )
+    .unwrap_err();
+    assert_eq!(error, "palette.a: palette cycle $a -> $b -> $c -> $a");
+
+    let error = palette("palette:\n  self: lighten($self, 5%)\nwindow_rules: []\n").unwrap_err();
+    assert_eq!(error, "palette.self: palette cycle $self -> $self");
+  }
+
+  #[test]
+  fn undefined_names_are_errors() {
+    let error = palette("palette:\n  a: $missing\nwindow_rules: []\n").unwrap_err();
+    assert_eq!(error, "palette.a: undefined palette color $missing");
+
+    let error = compile(
+      "palette:
+  a: red
+window_rules:
+  - Match: Global
+    active_border_color: $a
+    inactive_border_color: mix($a, $b)
+",
+    )
+    .err()
+    .unwrap();
+    assert_eq!(
+      error,
+      "window_rules[0].inactive_border_color: undefined palette color $b"
+    );
+  }
+
+  #[test]
+  fn invalid_references_are_rejected() {
+    assert_eq!(
+      "$".parse::<ColorSpec>(),
+      Err("invalid palette reference \"$\"".to_string())
+    );
+    assert!("$a b".parse::<ColorSpec>().is_err());
+  }
+
+  /// A fixed color function, computed.
+  fn derived(color: &str) -> Rgba {
+    let color: ColorSpec = color.parse().unwrap();
+    assert!(matches!(color, ColorSpec::Derived(_)), "{:?}", color);
+    Rgba::from_colorref(color.resolve(0.0))
+  }
+
+  fn close(a: f32, b: f32, tolerance: f32) -> bool {
+    (a - b).abs() <= tolerance
+  }
+
+  fn is_gray(color: Rgba) -> bool {
+    color.r.abs_diff(color.g) <= 1 && color.g.abs_diff(color.b) <= 1
+  }
+
+  #[test]
+  fn lighten_and_darken_move_the_lightness() {
+    let gray = Rgba::opaque(128, 128, 128).to_oklch().0;
+    let lighter = derived("lighten(#808080, 10%)");
+    assert!(is_gray(lighter));
+    assert!(close(lighter.to_oklch().0, gray + 0.1, 0.005));
+    let darker = derived("darken(#808080, 0.1)");
+    assert!(is_gray(darker));
+    assert!(close(darker.to_oklch().0, gray - 0.1, 0.005));
+
+    assert_eq!(derived("lighten(white, 10%)"), Rgba::opaque(255, 255, 255));
+    assert_eq!(derived("darken(white, 100%)"), Rgba::opaque(0, 0, 0));
+  }
+
+  #[test]
+  fn saturate_and_desaturate_scale_the_chroma() {
+    let (l, chroma, hue) = Rgba::opaque(160, 80, 80).to_oklch();
+    let (l2, chroma2, hue2) = derived("saturate(#a05050, 50%)").to_oklch();
+    assert!(close(chroma2, chroma * 1.5, 0.005));
+    assert!(close(l2, l, 0.005) && close(hue2, hue, 1.0));
+    let (_, chroma2, _) = derived("desaturate(#a05050, 50%)").to_oklch();
+    assert!(close(chroma2, chroma * 0.5, 0.005));
+
+    assert!(is_gray(derived("desaturate(red, 100%)")));
+    assert!(is_gray(derived("saturate(#808080, 100%)")));
+  }
+
+  #[test]
+  fn complement_turns_the_hue_around() {
+    let (l, chroma, hue) = Rgba::opaque(198, 160, 246).to_oklch();
+    let (l2, chroma2, hue2) = derived("complement(#c6a0f6)").to_oklch();
+    assert!(close((hue2 - hue).rem_euclid(360.0), 180.0, 2.0));
+    assert!(close(l2, l, 0.01) && close(chroma2, chroma, 0.01));
+  }
+
+  #[test]
+  fn mix_interpolates_in_oklab() {
+    assert_eq!(derived("mix(red, blue, 0%)"), Rgba::opaque(255, 0, 0));
+    assert_eq!(derived("mix(red, blue, 100%)"), Rgba::opaque(0, 0, 255));
+    let gray = derived("mix(black, white)");
+    assert!(is_gray(gray));
+    assert!(close(gray.to_oklch().0, 0.5, 0.005));
+    // Amounts past either end stop at the color.
+    assert_eq!(derived("mix(red, blue, 150%)"), Rgba::opaque(0, 0, 255));
+  }
+
+  #[test]
+  fn over_composites_the_alpha() {
+    assert_eq!(
+      derived("over(rgb(255 0 0 / 50%), black)"),
+      Rgba::opaque(128, 0, 0)
+    );
+    assert_eq!(derived("over(red, black)"), Rgba::opaque(255, 0, 0));
+  }
+
+  #[test]
+  fn operands_with_alpha_are_blended_first() {
+    let rule = |color: &str, backdrop: &str| {
+      compile(&format!(
+        "{}window_rules:\n  - Match: Global\n    active_border_color: '{}'\n",
+        backdrop, color
+      ))
+      .map(|rules| rules.colors_of(0, 0.0).0)
+    };
+    let error = rule("lighten(#ff000080, 10%)", "").unwrap_err();
+    assert!(error.contains("\"#ff000080\" has an alpha channel"), "{}", error);
+    assert_eq!(
+      rule("lighten(#ff000080, 10%)", "alpha_backdrop: '#000000'\n"),
+      rule("lighten(#800000, 10%)", "")
+    );
+    assert_eq!(rule("mix(#0000ff00, red, 0%)", "alpha_backdrop: '#ffffff'\n"), Ok(0xffffff));
+
+    // The foreground of `over` keeps its alpha, its backdrop doesn't.
+    assert_eq!(rule("over(#ff000080, black)", ""), Ok(0x000080));
+    assert!(rule("over(red, #0000ff80)", "").is_err());
+  }
+
+  #[test]
+  fn default_and_transparent_are_not_operands() {
+    let error = "lighten(default, 10%)".parse::<ColorSpec>().unwrap_err();
+    assert!(error.contains("\"default\" isn't a color"), "{}", error);
+    assert!("mix(red, transparent)".parse::<ColorSpec>().is_err());
+
+    let error = compile(
+      "palette:
+  frame: default
+window_rules:
+  - Match: Global
+    active_border_color: darken($frame, 10%)
+",
+    )
+    .err()
+    .unwrap();
+    assert_eq!(
+      error,
+      "window_rules[0].active_border_color: $frame is default, it can't be used in a color function"
+    );
+    let error = palette("palette:\n  none: transparent\n  dim: mix($none, red)\nwindow_rules: []\n")
+      .unwrap_err();
+    assert_eq!(
+      error,
+      "palette.dim: $none is transparent, it can't be used in a color function"
+    );
+  }
+}
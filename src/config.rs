@@ -1,27 +1,271 @@
 // src/config.rs
 
-use crate::logger::Logger;
+use crate::color::ColorSpec;
+use crate::logger::{LogLevel, Logger};
+use crate::animation::Easing;
+use crate::rainbow::{RainbowDirection, RainbowOffset, RainbowSettings};
+use crate::transition::TransitionSettings;
+use crate::rules::RuleSet;
 use crate::util::get_file_path;
 use once_cell::sync::Lazy;
-use serde::{Deserialize, Serialize};
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_yaml;
+use std::fmt;
+use std::collections::BTreeMap;
 use std::fs;
-use std::sync::Mutex;
-use std::time::SystemTime;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
 struct ConfigState {
-    config: Config,
+    config: Arc<Config>,
+    rules: Arc<RuleSet>,
     last_modified: Option<SystemTime>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Clone)]
 pub struct Rule {
-    #[serde(rename = "Match")]
+    /// Forma curta: `Match` + `contains`/`equals`/`glob`/`regex` direto na regra.
+    #[serde(flatten)]
+    pub matcher: Option<Matcher>,
+    /// Condição composta com `all`/`any`/`not`. Se `Match` também existir, os dois precisam bater.
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "serde_yaml::with::singleton_map_recursive::serialize")]
+    pub when: Option<Condition>,
+    pub active_border_color: ColorSpec,
+    /// Sem valor usa a borda padrão do Windows.
+    pub inactive_border_color: ColorSpec,
+}
+
+// Os campos de um Matcher enquanto são lidos, antes de saber se tem `Match`.
+#[derive(Default)]
+struct MatcherFields {
+    rule_match: Option<RuleMatch>,
+    contains: Option<Needles>,
+    equals: Option<String>,
+    glob: Option<String>,
+    regex: Option<String>,
+}
+
+impl MatcherFields {
+    // Lê o valor de `key` se for um campo do Match. Devolve false para qualquer outro campo.
+    fn read<'de, M: MapAccess<'de>>(&mut self, key: &str, map: &mut M) -> Result<bool, M::Error> {
+        match key {
+            "Match" | "match" => self.rule_match = Some(map.next_value()?),
+            "contains" => self.contains = Some(map.next_value()?),
+            "equals" => self.equals = Some(map.next_value()?),
+            "glob" => self.glob = Some(map.next_value()?),
+            "regex" => self.regex = Some(map.next_value()?),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.rule_match.is_none() && self.contains.is_none() && self.equals.is_none() && self.glob.is_none() && self.regex.is_none()
+    }
+
+    fn into_matcher(self) -> Result<Option<Matcher>, String> {
+        match self.rule_match {
+            Some(rule_match) => Ok(Some(Matcher {
+                rule_match,
+                contains: self.contains,
+                equals: self.equals,
+                glob: self.glob,
+                regex: self.regex,
+            })),
+            None if self.is_empty() => Ok(None),
+            None => Err("contains, equals, glob and regex need a Match".to_string()),
+        }
+    }
+}
+
+const RULE_FIELDS: &[&str] = &[
+    "Match", "contains", "equals", "glob", "regex", "when", "active_border_color", "inactive_border_color",
+];
+
+// Os campos do Match ficam soltos na regra. Lidos um a um, um `Match: Titel`
+// ou um campo com erro de digitação vira erro de leitura em vez de uma regra que some.
+impl<'de> Deserialize<'de> for Rule {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct RuleVisitor;
+
+        impl<'de> Visitor<'de> for RuleVisitor {
+            type Value = Rule;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a window rule")
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<Rule, M::Error> {
+                let mut fields = MatcherFields::default();
+                let mut when = None;
+                let mut active_border_color = None;
+                let mut inactive_border_color = None;
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "when" => when = map.next_value()?,
+                        "active_border_color" => active_border_color = Some(map.next_value()?),
+                        "inactive_border_color" => inactive_border_color = Some(map.next_value()?),
+                        _ if fields.read(&key, &mut map)? => {}
+                        _ => return Err(de::Error::unknown_field(&key, RULE_FIELDS)),
+                    }
+                }
+                Ok(Rule {
+                    matcher: fields.into_matcher().map_err(de::Error::custom)?,
+                    when,
+                    active_border_color: active_border_color.ok_or_else(|| de::Error::missing_field("active_border_color"))?,
+                    inactive_border_color: inactive_border_color.unwrap_or_default(),
+                })
+            }
+        }
+
+        deserializer.deserialize_map(RuleVisitor)
+    }
+}
+
+impl Rule {
+    pub fn global(active_border_color: ColorSpec, inactive_border_color: ColorSpec) -> Rule {
+        Rule {
+            matcher: Some(Matcher::new(RuleMatch::Global)),
+            when: None,
+            active_border_color,
+            inactive_border_color,
+        }
+    }
+
+    /// Regras Global vão substituindo umas às outras, qualquer outra regra encerra a busca.
+    pub fn is_global(&self) -> bool {
+        self.when.is_none() && self.matcher.as_ref().is_some_and(|m| m.rule_match == RuleMatch::Global)
+    }
+}
+
+/// Um nó da árvore `when` de uma regra. Além dos combinadores, qualquer folha
+/// pode ser escrita igual à forma curta com `Match` da regra.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum Condition {
+    All(Vec<Condition>),
+    Any(Vec<Condition>),
+    Not(Box<Condition>),
+    #[serde(untagged)]
+    Leaf(Matcher),
+}
+
+const CONDITION_FIELDS: &[&str] = &["all", "any", "not", "Match", "contains", "equals", "glob", "regex"];
+
+// Lido à mão para um erro dentro do `when` apontar o campo exato, em vez de
+// "data did not match any variant of untagged enum Condition".
+impl<'de> Deserialize<'de> for Condition {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ConditionVisitor;
+
+        impl<'de> Visitor<'de> for ConditionVisitor {
+            type Value = Condition;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "all, any, not or a Match condition")
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<Condition, M::Error> {
+                let mut combinator: Option<Condition> = None;
+                let mut fields = MatcherFields::default();
+                while let Some(key) = map.next_key::<String>()? {
+                    let condition = match key.as_str() {
+                        "all" => Condition::All(map.next_value()?),
+                        "any" => Condition::Any(map.next_value()?),
+                        "not" => Condition::Not(Box::new(map.next_value()?)),
+                        _ if fields.read(&key, &mut map)? => continue,
+                        _ => return Err(de::Error::unknown_field(&key, CONDITION_FIELDS)),
+                    };
+                    if combinator.is_some() {
+                        return Err(de::Error::custom("only one of all, any and not per condition, nest them to combine"));
+                    }
+                    combinator = Some(condition);
+                }
+
+                match combinator {
+                    Some(_) if !fields.is_empty() => Err(de::Error::custom(
+                        "a condition is either all/any/not or a Match, put the Match inside all to combine them",
+                    )),
+                    Some(condition) => Ok(condition),
+                    None => match fields.into_matcher().map_err(de::Error::custom)? {
+                        Some(matcher) => Ok(Condition::Leaf(matcher)),
+                        None => Err(de::Error::custom("empty condition, expected all, any, not or Match")),
+                    },
+                }
+            }
+        }
+
+        deserializer.deserialize_map(ConditionVisitor)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Matcher {
+    #[serde(rename = "Match", alias = "match")]
     pub rule_match: RuleMatch,
-    #[serde(default)]
-    pub contains: Option<String>,
-    pub active_border_color: String,
-    pub inactive_border_color: String,
+    /// Um valor ou uma lista; com lista basta um deles aparecer no texto.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contains: Option<Needles>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub equals: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glob: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+}
+
+impl Matcher {
+    pub fn new(rule_match: RuleMatch) -> Matcher {
+        Matcher { rule_match, contains: None, equals: None, glob: None, regex: None }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(untagged)]
+pub enum Needles {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// Um texto ou uma lista de textos.
+impl<'de> Deserialize<'de> for Needles {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NeedlesVisitor;
+
+        impl<'de> Visitor<'de> for NeedlesVisitor {
+            type Value = Needles;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a string or a list of strings")
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<Needles, E> {
+                Ok(Needles::One(s.to_string()))
+            }
+
+            fn visit_seq<S: SeqAccess<'de>>(self, mut seq: S) -> Result<Needles, S::Error> {
+                let mut needles = Vec::new();
+                while let Some(needle) = seq.next_element()? {
+                    needles.push(needle);
+                }
+                Ok(Needles::Many(needles))
+            }
+        }
+
+        deserializer.deserialize_any(NeedlesVisitor)
+    }
+}
+
+impl Needles {
+    pub fn iter(&self) -> std::slice::Iter<'_, String> {
+        match self {
+            Needles::One(needle) => std::slice::from_ref(needle).iter(),
+            Needles::Many(needles) => needles.iter(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
@@ -29,86 +273,308 @@ pub enum RuleMatch {
     Global,
     Title,
     Class,
+    Process,
+    /// Nome do workspace no gerenciador de janelas lado a lado; sem nome, o índice a partir de 0.
+    Workspace,
+    /// Layout do workspace, ex. `BSP` no komorebi ou `horizontal` no GlazeWM.
+    Layout,
+    /// Estado da janela: `tiled`, `stacked`, `monocle`, `floating`, `maximized`, `fullscreen` ou `minimized`.
+    State,
+}
+
+/// Gerenciador de janelas lado a lado de onde vêm os workspaces, layouts e estados.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum TilingWm {
+    Komorebi,
+    GlazeWm,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
+    /// Graus de matiz por segundo.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rainbow_degrees_per_second: Option<f32>,
+    /// Formato antigo, graus de matiz por quadro a uns 30 quadros por segundo.
+    /// Só é lido, ao salvar vira `rainbow_degrees_per_second`.
+    #[serde(default, skip_serializing)]
     pub rainbow_speed: Option<f32>,
+    /// Saturação e luminosidade HSL do rainbow, de 0.0 a 1.0.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rainbow_saturation: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rainbow_lightness: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rainbow_direction: Option<RainbowDirection>,
+    /// Dá a cada janela (ou processo, ou posição na tela) um ponto diferente do rainbow.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rainbow_offset: Option<RainbowOffset>,
+    /// Graus de matiz entre as bordas esquerda e direita da tela com `rainbow_offset: position`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rainbow_spread: Option<f32>,
+    /// Duração do fade entre a cor inativa e a ativa quando o foco muda. Sem valor troca na hora.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transition_ms: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transition_easing: Option<Easing>,
+    /// Quadros por segundo enquanto alguma cor anima. Parado, nada é repintado até algo mudar.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame_rate: Option<u32>,
     pub hide_tray_icon: Option<bool>,
+    /// `explain` também registra no log por que cada janela ficou com a sua cor.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_level: Option<LogLevel>,
+    /// Segue os eventos do komorebi ou do GlazeWM para as regras `Workspace`, `Layout` e `State`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tiling_wm: Option<TilingWm>,
+    /// Cor opaca usada para misturar cores com alpha (`#rrggbbaa`, `rgb(... / 0.5)`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alpha_backdrop: Option<String>,
+    /// Cores nomeadas, usadas nas regras como `$nome`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub palette: BTreeMap<String, ColorSpec>,
     pub window_rules: Vec<Rule>,
 }
 
+// O rainbow antigo avançava `rainbow_speed` graus a cada 33 ms.
+const LEGACY_TICKS_PER_SECOND: f32 = 30.0;
+
 static CONFIG: Lazy<Mutex<ConfigState>> = Lazy::new(|| Mutex::new(load_or_create_config()));
 
 impl Config {
-    pub fn get() -> Config {
+    /// Lê um config no formato do config.yaml. As regras só são conferidas por `RuleSet::compile`.
+    pub fn from_yaml(yaml: &str) -> Result<Config, String> {
+        serde_yaml::from_str(yaml).map_err(|e| e.to_string())
+    }
+
+    /// Lê e compila um arquivo como o config.yaml, mas falha em vez de voltar para o padrão.
+    pub fn load(path: &Path) -> Result<(Config, RuleSet), String> {
+        let yaml = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let config = Config::from_yaml(&yaml)?;
+        let rules = RuleSet::compile(&config)?;
+        Ok((config, rules))
+    }
+
+    /// O config já lido. Não olha o arquivo, quem relê é `reload_if_changed`.
+    pub fn get() -> Arc<Config> {
+        CONFIG.lock().unwrap().config.clone()
+    }
+
+    /// As regras compiladas do config atual, recompiladas só quando ele é relido.
+    pub fn rules() -> Arc<RuleSet> {
+        CONFIG.lock().unwrap().rules.clone()
+    }
+
+    /// Relê o config.yaml se ele mudou desde a última leitura e devolve se releu.
+    /// Chamado por quem observa a pasta do config, não a cada quadro.
+    pub fn reload_if_changed() -> bool {
         let mut state_guard = CONFIG.lock().unwrap();
-        let config_path = get_file_path("config.yaml");
-        if let Ok(metadata) = fs::metadata(&config_path) {
-            if let Ok(modified_time) = metadata.modified() {
-                if state_guard.last_modified.map_or(true, |last| modified_time > last) {
-                    Logger::log("[CONFIG] config.yaml changed, reloading.");
-                    *state_guard = load_or_create_config();
-                }
-            }
+        let Ok(config_path) = get_file_path("config.yaml") else {
+            return false;
+        };
+        let Ok(modified_time) = fs::metadata(&config_path).and_then(|m| m.modified()) else {
+            return false;
+        };
+        if state_guard.last_modified.is_some_and(|last| modified_time <= last) {
+            return false;
         }
-        state_guard.config.clone()
+        Logger::log("[CONFIG] config.yaml changed, reloading.");
+        *state_guard = load_or_create_config();
+        true
+    }
+
+    pub fn rainbow_settings(&self) -> RainbowSettings {
+        let defaults = RainbowSettings::default();
+        RainbowSettings {
+            speed: self
+                .rainbow_degrees_per_second
+                .filter(|speed| speed.is_finite())
+                .or_else(|| {
+                    self.rainbow_speed
+                        .filter(|speed| speed.is_finite())
+                        .map(|speed| speed * LEGACY_TICKS_PER_SECOND)
+                })
+                .unwrap_or(defaults.speed),
+            saturation: self.rainbow_saturation.filter(|s| s.is_finite()).unwrap_or(defaults.saturation).clamp(0.0, 1.0),
+            lightness: self.rainbow_lightness.filter(|l| l.is_finite()).unwrap_or(defaults.lightness).clamp(0.0, 1.0),
+            direction: self.rainbow_direction.unwrap_or(defaults.direction),
+            offset: self.rainbow_offset.unwrap_or(defaults.offset),
+            spread: self.rainbow_spread.filter(|s| s.is_finite()).unwrap_or(defaults.spread),
+        }
+    }
+
+    pub fn transition_settings(&self) -> Option<TransitionSettings> {
+        let duration_ms = self.transition_ms.filter(|ms| ms.is_finite() && *ms > 0.0)?;
+        Some(TransitionSettings {
+            duration_ms,
+            easing: self.transition_easing.unwrap_or(Easing::EaseInOut),
+        })
+    }
+
+    pub fn frame_interval(&self) -> Duration {
+        Duration::from_secs(1) / self.frame_rate.unwrap_or(30).clamp(1, 240)
     }
 
     pub fn read_for_gui() -> Config {
         let mut state_guard = CONFIG.lock().unwrap();
         *state_guard = load_or_create_config(); // Força a leitura
-        state_guard.config.clone()
+        (*state_guard.config).clone()
     }
 
     pub fn write_config(config_to_write: &Config) -> Result<(), Box<dyn std::error::Error>> {
-        let config_path = get_file_path("config.yaml");
+        let rules = RuleSet::compile(config_to_write)?;
+        let config_path = get_file_path("config.yaml")?;
         let yaml_string = serde_yaml::to_string(config_to_write)?;
         fs::write(config_path, yaml_string)?;
         
+        Logger::set_level(config_to_write.log_level.unwrap_or_default());
         let mut state_guard = CONFIG.lock().unwrap();
-        state_guard.config = config_to_write.clone();
+        state_guard.config = Arc::new(config_to_write.clone());
+        state_guard.rules = Arc::new(rules);
         state_guard.last_modified = Some(SystemTime::now());
         Ok(())
     }
 }
 
 fn load_or_create_config() -> ConfigState {
-    let config_path = get_file_path("config.yaml");
-    let config_to_return = if let Ok(config_str) = fs::read_to_string(&config_path) {
-        match serde_yaml::from_str(&config_str) {
+    // Sem a pasta ~/.cuteborders não há o que ler nem onde escrever: fica o padrão.
+    let config_path = match get_file_path("config.yaml") {
+        Ok(config_path) => Some(config_path),
+        Err(e) => {
+            Logger::log(&format!("[ERROR] {}. Loading default.", e));
+            None
+        }
+    };
+    let config_to_return = if let Some(config_str) = config_path.as_ref().and_then(|path| fs::read_to_string(path).ok()) {
+        match Config::from_yaml(&config_str) {
             Ok(config) => config,
             Err(e) => {
-                Logger::log(&format!("[ERROR] Failed to parse config file: {:?}. Loading default.", e));
+                Logger::log(&format!("[ERROR] Failed to parse config file: {}. Loading default.", e));
                 create_default_config()
             }
         }
     } else {
         Logger::log("[INFO] Config file not found. Creating a default one.");
         let default_config = create_default_config();
-        if let Ok(yaml_string) = serde_yaml::to_string(&default_config) {
-            if let Err(e) = fs::write(&config_path, yaml_string) {
+        if let (Some(config_path), Ok(yaml_string)) = (&config_path, serde_yaml::to_string(&default_config)) {
+            if let Err(e) = fs::write(config_path, yaml_string) {
                 Logger::log(&format!("[ERROR] Failed to write default config file: {:?}", e));
             }
         }
         default_config
     };
-    let last_modified = fs::metadata(config_path).and_then(|m| m.modified()).ok();
+    let (config_to_return, rules) = match RuleSet::compile(&config_to_return) {
+        Ok(rules) => (config_to_return, rules),
+        Err(e) => {
+            Logger::log(&format!("[ERROR] Invalid config file: {}. Loading default.", e));
+            let default_config = create_default_config();
+            let rules = RuleSet::compile(&default_config).expect("default config is valid");
+            (default_config, rules)
+        }
+    };
+    let last_modified = config_path.and_then(|path| fs::metadata(path).and_then(|m| m.modified()).ok());
+    Logger::set_level(config_to_return.log_level.unwrap_or_default());
     ConfigState {
-        config: config_to_return,
+        config: Arc::new(config_to_return),
+        rules: Arc::new(rules),
         last_modified,
     }
 }
 
 fn create_default_config() -> Config {
     Config {
-        rainbow_speed: Some(1.0),
+        rainbow_degrees_per_second: Some(30.0),
+        rainbow_speed: None,
+        rainbow_saturation: None,
+        rainbow_lightness: None,
+        rainbow_direction: None,
+        rainbow_offset: None,
+        rainbow_spread: None,
+        transition_ms: None,
+        transition_easing: None,
+        frame_rate: None,
         hide_tray_icon: Some(false),
-        window_rules: vec![Rule {
-            rule_match: RuleMatch::Global,
-            contains: None,
-            active_border_color: "rainbow".to_string(),
-            inactive_border_color: "#444444".to_string(),
-        }],
+        log_level: None,
+        tiling_wm: None,
+        alpha_backdrop: None,
+        palette: BTreeMap::new(),
+        window_rules: vec![Rule::global(ColorSpec::Rainbow, ColorSpec::Hex(0x444444))],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(yaml: &str) -> String {
+        Config::from_yaml(yaml).unwrap_err()
+    }
+
+    #[test]
+    fn bundled_config_parses() {
+        let config = Config::from_yaml(include_str!("data/config.yaml")).unwrap();
+        assert!(config.window_rules.iter().all(|rule| rule.matcher.is_some() || rule.when.is_some()));
+    }
+
+    #[test]
+    fn typo_in_match_is_an_error() {
+        let e = error("window_rules:\n  - Match: Titel\n    contains: x\n    active_border_color: red\n");
+        assert!(e.starts_with("window_rules[0].Match: unknown variant `Titel`"), "{}", e);
+    }
+
+    #[test]
+    fn contains_must_be_text() {
+        let e = error("window_rules:\n  - Match: Title\n    contains: 123\n    active_border_color: red\n");
+        assert!(e.starts_with("window_rules[0].contains: invalid type: integer `123`"), "{}", e);
+    }
+
+    #[test]
+    fn unknown_rule_field_is_an_error() {
+        let e = error("window_rules:\n  - Match: Title\n    contans: x\n    active_border_color: red\n");
+        assert!(e.starts_with("window_rules[0]: unknown field `contans`"), "{}", e);
+    }
+
+    #[test]
+    fn matcher_fields_need_match() {
+        let e = error("window_rules:\n  - contains: x\n    active_border_color: red\n");
+        assert!(e.starts_with("window_rules[0]: contains, equals, glob and regex need a Match"), "{}", e);
+    }
+
+    #[test]
+    fn errors_in_when_have_a_path() {
+        let yaml = "window_rules:\n  - when:\n      all:\n        - Match: Title\n          contains: a\n        - not:\n            Match: Clas\n    active_border_color: red\n";
+        assert!(error(yaml).starts_with("window_rules[0].when.all[1].not.Match: unknown variant `Clas`"));
+
+        let yaml = "window_rules:\n  - when:\n      all: []\n      any: []\n    active_border_color: red\n";
+        assert!(error(yaml).starts_with("window_rules[0].when: only one of all, any and not"));
+    }
+
+    #[test]
+    fn rules_round_trip() {
+        let yaml = "window_rules:\n  - when:\n      all:\n        - Match: Title\n          contains: [a, b]\n        - not:\n            Match: Class\n            equals: c\n    active_border_color: red\n";
+        let config = Config::from_yaml(yaml).unwrap();
+        let written = serde_yaml::to_string(&config).unwrap();
+        let reread = Config::from_yaml(&written).unwrap();
+        assert_eq!(serde_yaml::to_string(&reread).unwrap(), written);
+        assert!(written.contains("all:"), "{}", written);
+    }
+
+    #[test]
+    fn old_rainbow_speed_is_per_frame() {
+        let config = Config::from_yaml("rainbow_speed: 2.0\nwindow_rules: []\n").unwrap();
+        assert_eq!(config.rainbow_settings().speed, 60.0);
+        let config = Config::from_yaml("rainbow_speed: 2.0\nrainbow_degrees_per_second: 45.0\nwindow_rules: []\n").unwrap();
+        assert_eq!(config.rainbow_settings().speed, 45.0);
+        let saved = serde_yaml::to_string(&config).unwrap();
+        assert!(!saved.contains("rainbow_speed"), "{}", saved);
+    }
+
+    #[test]
+    fn non_finite_rainbow_settings_fall_back() {
+        let config = Config::from_yaml(
+            "rainbow_degrees_per_second: .nan\nrainbow_saturation: .inf\nrainbow_lightness: .nan\nwindow_rules: []\n",
+        )
+        .unwrap();
+        assert_eq!(config.rainbow_settings(), RainbowSettings::default());
     }
 }
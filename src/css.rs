@@ -0,0 +1,556 @@
+/// A parsed CSS color, 8 bits per channel plus alpha.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rgba {
+  pub r: u8,
+  pub g: u8,
+  pub b: u8,
+  pub a: u8,
+}
+
+impl Rgba {
+  pub fn opaque(r: u8, g: u8, b: u8) -> Rgba {
+    Rgba { r, g, b, a: 255 }
+  }
+
+  pub fn from_colorref(colorref: u32) -> Rgba {
+    Rgba::opaque(
+      (colorref & 0xFF) as u8,
+      ((colorref >> 8) & 0xFF) as u8,
+      ((colorref >> 16) & 0xFF) as u8,
+    )
+  }
+
+  /// COLORREF layout (0x00bbggrr), alpha is dropped.
+  pub fn to_colorref(self) -> u32 {
+    (self.b as u32) << 16 | (self.g as u32) << 8 | self.r as u32
+  }
+
+  pub fn to_oklab(self) -> (f32, f32, f32) {
+    srgb_to_oklab(self.r as f32 / 255.0, self.g as f32 / 255.0, self.b as f32 / 255.0)
+  }
+
+  /// Lightness, chroma and hue in degrees.
+  pub fn to_oklch(self) -> (f32, f32, f32) {
+    let (l, a, b) = self.to_oklab();
+    (l, a.hypot(b), b.atan2(a).to_degrees().rem_euclid(360.0))
+  }
+
+  pub fn from_oklab(l: f32, a: f32, b: f32, alpha: u8) -> Rgba {
+    let (r, g, b) = oklab_to_srgb(l, a, b);
+    Rgba { r: to_channel(r), g: to_channel(g), b: to_channel(b), a: alpha }
+  }
+
+  pub fn from_oklch(l: f32, c: f32, h: f32, alpha: u8) -> Rgba {
+    let (r, g, b) = oklch_to_srgb(l, c, h);
+    Rgba { r: to_channel(r), g: to_channel(g), b: to_channel(b), a: alpha }
+  }
+
+  /// h in degrees, s and l in 0.0..=1.0.
+  pub fn from_hsl(h: f32, s: f32, l: f32, alpha: u8) -> Rgba {
+    let (r, g, b) = hsl_to_rgb(h, s, l);
+    Rgba { r: to_channel(r), g: to_channel(g), b: to_channel(b), a: alpha }
+  }
+
+  /// Blends this color over an opaque `backdrop`.
+  pub fn over(self, backdrop: Rgba) -> Rgba {
+    let alpha = self.a as f32 / 255.0;
+    let blend = |fg: u8, bg: u8| (fg as f32 * alpha + bg as f32 * (1.0 - alpha)).round() as u8;
+    Rgba::opaque(
+      blend(self.r, backdrop.r),
+      blend(self.g, backdrop.g),
+      blend(self.b, backdrop.b),
+    )
+  }
+}
+
+/// Parses any CSS color: `#rgb`, `#rgba`, `#rrggbb`, `#rrggbbaa`, `rgb()`/`rgba()`,
+/// `hsl()`/`hsla()`, `oklch()` and the CSS named colors. Case-insensitive.
+pub fn parse(color: &str) -> Result<Rgba, String> {
+  // Errors quote the color as it was written.
+  let original = color.trim();
+  let color = original.to_lowercase();
+
+  if let Some(hex) = color.strip_prefix('#') {
+    return parse_hex(hex).ok_or_else(|| {
+      format!(
+        "invalid hex color \"{}\", expected #rgb, #rgba, #rrggbb or #rrggbbaa",
+        original
+      )
+    });
+  }
+
+  if let Some((name, args)) = color.strip_suffix(')').and_then(|c| c.split_once('(')) {
+    let result = match name.trim() {
+      "rgb" | "rgba" => parse_rgb(args),
+      "hsl" | "hsla" => parse_hsl(args),
+      "oklch" => parse_oklch(args),
+      other => Err(format!("unknown color function \"{}()\"", other)),
+    };
+    return result.map_err(|e| format!("invalid color \"{}\": {}", original, e));
+  }
+
+  named_color(&color).ok_or_else(|| format!("unknown color \"{}\"", original))
+}
+
+fn parse_hex(hex: &str) -> Option<Rgba> {
+  if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+    return None;
+  }
+  let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok().map(|d| d * 17);
+  let pair = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+
+  match hex.len() {
+    3 => Some(Rgba::opaque(digit(0)?, digit(1)?, digit(2)?)),
+    4 => Some(Rgba { r: digit(0)?, g: digit(1)?, b: digit(2)?, a: digit(3)? }),
+    6 => Some(Rgba::opaque(pair(0)?, pair(2)?, pair(4)?)),
+    8 => Some(Rgba { r: pair(0)?, g: pair(2)?, b: pair(4)?, a: pair(6)? }),
+    _ => None,
+  }
+}
+
+/// Splits both the modern `a b c / d` and the legacy `a, b, c, d` syntax.
+fn split_args(args: &str) -> Result<(Vec<&str>, Option<&str>), String> {
+  let (channels, alpha) = match args.split_once('/') {
+    Some((channels, alpha)) => (channels, Some(alpha.trim())),
+    None => (args, None),
+  };
+
+  let mut parts: Vec<&str> = if channels.contains(',') {
+    channels.split(',').map(str::trim).collect()
+  } else {
+    channels.split_whitespace().collect()
+  };
+
+  let alpha = match (alpha, parts.len()) {
+    (Some(alpha), 3) => Some(alpha),
+    (None, 4) => parts.pop(),
+    (None, 3) => None,
+    _ => return Err("expected 3 components and an optional alpha".to_string()),
+  };
+  Ok((parts, alpha))
+}
+
+fn parse_number(value: &str) -> Result<f32, String> {
+  value
+    .parse::<f32>()
+    .ok()
+    .filter(|v| v.is_finite())
+    .ok_or_else(|| format!("\"{}\" is not a number", value))
+}
+
+/// A number, or a percentage of `percent_of`.
+fn parse_number_or_percent(value: &str, percent_of: f32) -> Result<f32, String> {
+  match value.strip_suffix('%') {
+    Some(percent) => Ok(parse_number(percent)? / 100.0 * percent_of),
+    None => parse_number(value),
+  }
+}
+
+fn parse_percent(value: &str) -> Result<f32, String> {
+  match value.strip_suffix('%') {
+    Some(percent) => Ok(parse_number(percent)? / 100.0),
+    None => Err(format!("\"{}\" should be a percentage", value)),
+  }
+}
+
+fn parse_hue(value: &str) -> Result<f32, String> {
+  let degrees = if let Some(turns) = value.strip_suffix("turn") {
+    parse_number(turns)? * 360.0
+  } else if let Some(radians) = value.strip_suffix("rad") {
+    parse_number(radians)?.to_degrees()
+  } else {
+    parse_number(value.strip_suffix("deg").unwrap_or(value))?
+  };
+  Ok(degrees.rem_euclid(360.0))
+}
+
+fn parse_alpha(alpha: Option<&str>) -> Result<u8, String> {
+  match alpha {
+    Some(alpha) => Ok(to_channel(parse_number_or_percent(alpha, 1.0)?)),
+    None => Ok(255),
+  }
+}
+
+/// 0.0..=1.0 to 0..=255, clamping out-of-gamut values.
+fn to_channel(value: f32) -> u8 {
+  (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn parse_rgb(args: &str) -> Result<Rgba, String> {
+  let (parts, alpha) = split_args(args)?;
+  let channel = |value: &str| parse_number_or_percent(value, 255.0).map(|v| to_channel(v / 255.0));
+  Ok(Rgba {
+    r: channel(parts[0])?,
+    g: channel(parts[1])?,
+    b: channel(parts[2])?,
+    a: parse_alpha(alpha)?,
+  })
+}
+
+fn parse_hsl(args: &str) -> Result<Rgba, String> {
+  let (parts, alpha) = split_args(args)?;
+  let (r, g, b) = hsl_to_rgb(
+    parse_hue(parts[0])?,
+    parse_percent(parts[1])?,
+    parse_percent(parts[2])?,
+  );
+  Ok(Rgba { r: to_channel(r), g: to_channel(g), b: to_channel(b), a: parse_alpha(alpha)? })
+}
+
+fn parse_oklch(args: &str) -> Result<Rgba, String> {
+  let (parts, alpha) = split_args(args)?;
+  // 100% lightness is 1.0 and 100% chroma is 0.4, as in CSS Color 4.
+  let (r, g, b) = oklch_to_srgb(
+    parse_number_or_percent(parts[0], 1.0)?,
+    parse_number_or_percent(parts[1], 0.4)?,
+    parse_hue(parts[2])?,
+  );
+  Ok(Rgba { r: to_channel(r), g: to_channel(g), b: to_channel(b), a: parse_alpha(alpha)? })
+}
+
+/// h in degrees, s and l in 0.0..=1.0, returns channels in 0.0..=1.0.
+pub fn hsl_to_rgb(h: f32, s: f32, l: f32) -> (f32, f32, f32) {
+  let s = s.clamp(0.0, 1.0);
+  let l = l.clamp(0.0, 1.0);
+  let f = |n: f32| {
+    let k = (n + h / 30.0) % 12.0;
+    let a = s * l.min(1.0 - l);
+    l - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+  };
+  (f(0.0), f(8.0), f(4.0))
+}
+
+/// l in 0.0..=1.0, c is the chroma, h in degrees. Returns gamma-encoded sRGB,
+/// which may be out of gamut.
+pub fn oklch_to_srgb(l: f32, c: f32, h: f32) -> (f32, f32, f32) {
+  oklab_to_srgb(l, c * h.to_radians().cos(), c * h.to_radians().sin())
+}
+
+// The OKLab matrices are evaluated in f64, their published constants have
+// more digits than an f32 holds.
+pub fn oklab_to_srgb(l: f32, a: f32, b: f32) -> (f32, f32, f32) {
+  let (l, a, b) = (l as f64, a as f64, b as f64);
+  let l_ = l + 0.3963377774 * a + 0.2158037573 * b;
+  let m_ = l - 0.1055613458 * a - 0.0638541728 * b;
+  let s_ = l - 0.0894841775 * a - 1.2914855480 * b;
+  let (l3, m3, s3) = (l_ * l_ * l_, m_ * m_ * m_, s_ * s_ * s_);
+
+  let r = 4.0767416621 * l3 - 3.3077115913 * m3 + 0.2309699292 * s3;
+  let g = -1.2684380046 * l3 + 2.6097574011 * m3 - 0.3413193965 * s3;
+  let b = -0.0041960863 * l3 - 0.7034186147 * m3 + 1.7076147010 * s3;
+  (
+    linear_to_srgb(r as f32),
+    linear_to_srgb(g as f32),
+    linear_to_srgb(b as f32),
+  )
+}
+
+/// Gamma-encoded sRGB channels in 0.0..=1.0 to OKLab.
+pub fn srgb_to_oklab(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+  let (r, g, b) = (
+    srgb_to_linear(r) as f64,
+    srgb_to_linear(g) as f64,
+    srgb_to_linear(b) as f64,
+  );
+
+  let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+  let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+  let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+
+  (
+    (0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s) as f32,
+    (1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s) as f32,
+    (0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s) as f32,
+  )
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+  if value.abs() <= 0.0031308 {
+    value * 12.92
+  } else {
+    value.signum() * (1.055 * value.abs().powf(1.0 / 2.4) - 0.055)
+  }
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+  if value.abs() <= 0.04045 {
+    value / 12.92
+  } else {
+    value.signum() * ((value.abs() + 0.055) / 1.055).powf(2.4)
+  }
+}
+
+fn named_color(name: &str) -> Option<Rgba> {
+  NAMED_COLORS
+    .binary_search_by_key(&name, |&(n, _)| n)
+    .ok()
+    .map(|i| {
+      let rgb = NAMED_COLORS[i].1;
+      Rgba::opaque((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+    })
+}
+
+// Sorted for binary search. `transparent` is left out on purpose,
+// it means "no border" in config.yaml.
+const NAMED_COLORS: [(&str, u32); 148] = [
+  ("aliceblue", 0xf0f8ff),
+  ("antiquewhite", 0xfaebd7),
+  ("aqua", 0x00ffff),
+  ("aquamarine", 0x7fffd4),
+  ("azure", 0xf0ffff),
+  ("beige", 0xf5f5dc),
+  ("bisque", 0xffe4c4),
+  ("black", 0x000000),
+  ("blanchedalmond", 0xffebcd),
+  ("blue", 0x0000ff),
+  ("blueviolet", 0x8a2be2),
+  ("brown", 0xa52a2a),
+  ("burlywood", 0xdeb887),
+  ("cadetblue", 0x5f9ea0),
+  ("chartreuse", 0x7fff00),
+  ("chocolate", 0xd2691e),
+  ("coral", 0xff7f50),
+  ("cornflowerblue", 0x6495ed),
+  ("cornsilk", 0xfff8dc),
+  ("crimson", 0xdc143c),
+  ("cyan", 0x00ffff),
+  ("darkblue", 0x00008b),
+  ("darkcyan", 0x008b8b),
+  ("darkgoldenrod", 0xb8860b),
+  ("darkgray", 0xa9a9a9),
+  ("darkgreen", 0x006400),
+  ("darkgrey", 0xa9a9a9),
+  ("darkkhaki", 0xbdb76b),
+  ("darkmagenta", 0x8b008b),
+  ("darkolivegreen", 0x556b2f),
+  ("darkorange", 0xff8c00),
+  ("darkorchid", 0x9932cc),
+  ("darkred", 0x8b0000),
+  ("darksalmon", 0xe9967a),
+  ("darkseagreen", 0x8fbc8f),
+  ("darkslateblue", 0x483d8b),
+  ("darkslategray", 0x2f4f4f),
+  ("darkslategrey", 0x2f4f4f),
+  ("darkturquoise", 0x00ced1),
+  ("darkviolet", 0x9400d3),
+  ("deeppink", 0xff1493),
+  ("deepskyblue", 0x00bfff),
+  ("dimgray", 0x696969),
+  ("dimgrey", 0x696969),
+  ("dodgerblue", 0x1e90ff),
+  ("firebrick", 0xb22222),
+  ("floralwhite", 0xfffaf0),
+  ("forestgreen", 0x228b22),
+  ("fuchsia", 0xff00ff),
+  ("gainsboro", 0xdcdcdc),
+  ("ghostwhite", 0xf8f8ff),
+  ("gold", 0xffd700),
+  ("goldenrod", 0xdaa520),
+  ("gray", 0x808080),
+  ("green", 0x008000),
+  ("greenyellow", 0xadff2f),
+  ("grey", 0x808080),
+  ("honeydew", 0xf0fff0),
+  ("hotpink", 0xff69b4),
+  ("indianred", 0xcd5c5c),
+  ("indigo", 0x4b0082),
+  ("ivory", 0xfffff0),
+  ("khaki", 0xf0e68c),
+  ("lavender", 0xe6e6fa),
+  ("lavenderblush", 0xfff0f5),
+  ("lawngreen", 0x7cfc00),
+  ("lemonchiffon", 0xfffacd),
+  ("lightblue", 0xadd8e6),
+  ("lightcoral", 0xf08080),
+  ("lightcyan", 0xe0ffff),
+  ("lightgoldenrodyellow", 0xfafad2),
+  ("lightgray", 0xd3d3d3),
+  ("lightgreen", 0x90ee90),
+  ("lightgrey", 0xd3d3d3),
+  ("lightpink", 0xffb6c1),
+  ("lightsalmon", 0xffa07a),
+  ("lightseagreen", 0x20b2aa),
+  ("lightskyblue", 0x87cefa),
+  ("lightslategray", 0x778899),
+  ("lightslategrey", 0x778899),
+  ("lightsteelblue", 0xb0c4de),
+  ("lightyellow", 0xffffe0),
+  ("lime", 0x00ff00),
+  ("limegreen", 0x32cd32),
+  ("linen", 0xfaf0e6),
+  ("magenta", 0xff00ff),
+  ("maroon", 0x800000),
+  ("mediumaquamarine", 0x66cdaa),
+  ("mediumblue", 0x0000cd),
+  ("mediumorchid", 0xba55d3),
+  ("mediumpurple", 0x9370db),
+  ("mediumseagreen", 0x3cb371),
+  ("mediumslateblue", 0x7b68ee),
+  ("mediumspringgreen", 0x00fa9a),
+  ("mediumturquoise", 0x48d1cc),
+  ("mediumvioletred", 0xc71585),
+  ("midnightblue", 0x191970),
+  ("mintcream", 0xf5fffa),
+  ("mistyrose", 0xffe4e1),
+  ("moccasin", 0xffe4b5),
+  ("navajowhite", 0xffdead),
+  ("navy", 0x000080),
+  ("oldlace", 0xfdf5e6),
+  ("olive", 0x808000),
+  ("olivedrab", 0x6b8e23),
+  ("orange", 0xffa500),
+  ("orangered", 0xff4500),
+  ("orchid", 0xda70d6),
+  ("palegoldenrod", 0xeee8aa),
+  ("palegreen", 0x98fb98),
+  ("paleturquoise", 0xafeeee),
+  ("palevioletred", 0xdb7093),
+  ("papayawhip", 0xffefd5),
+  ("peachpuff", 0xffdab9),
+  ("peru", 0xcd853f),
+  ("pink", 0xffc0cb),
+  ("plum", 0xdda0dd),
+  ("powderblue", 0xb0e0e6),
+  ("purple", 0x800080),
+  ("rebeccapurple", 0x663399),
+  ("red", 0xff0000),
+  ("rosybrown", 0xbc8f8f),
+  ("royalblue", 0x4169e1),
+  ("saddlebrown", 0x8b4513),
+  ("salmon", 0xfa8072),
+  ("sandybrown", 0xf4a460),
+  ("seagreen", 0x2e8b57),
+  ("seashell", 0xfff5ee),
+  ("sienna", 0xa0522d),
+  ("silver", 0xc0c0c0),
+  ("skyblue", 0x87ceeb),
+  ("slateblue", 0x6a5acd),
+  ("slategray", 0x708090),
+  ("slategrey", 0x708090),
+  ("snow", 0xfffafa),
+  ("springgreen", 0x00ff7f),
+  ("steelblue", 0x4682b4),
+  ("tan", 0xd2b48c),
+  ("teal", 0x008080),
+  ("thistle", 0xd8bfd8),
+  ("tomato", 0xff6347),
+  ("turquoise", 0x40e0d0),
+  ("violet", 0xee82ee),
+  ("wheat", 0xf5deb3),
+  ("white", 0xffffff),
+  ("whitesmoke", 0xf5f5f5),
+  ("yellow", 0xffff00),
+  ("yellowgreen", 0x9acd32),
+];
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn oklab_round_trips_srgb() {
+    for color in [
+      Rgba::opaque(0, 0, 0),
+      Rgba::opaque(255, 255, 255),
+      Rgba::opaque(198, 160, 246),
+      Rgba::opaque(255, 0, 0),
+      Rgba::opaque(12, 200, 99),
+    ] {
+      let (l, a, b) = color.to_oklab();
+      assert_eq!(Rgba::from_oklab(l, a, b, 255), color);
+    }
+  }
+
+  #[test]
+  fn oklab_of_white_is_one() {
+    let (l, a, b) = srgb_to_oklab(1.0, 1.0, 1.0);
+    assert!((l - 1.0).abs() < 1e-4 && a.abs() < 1e-4 && b.abs() < 1e-4);
+  }
+
+  #[test]
+  fn hsl_primaries() {
+    assert_eq!(Rgba::from_hsl(0.0, 1.0, 0.5, 255), Rgba::opaque(255, 0, 0));
+    assert_eq!(Rgba::from_hsl(120.0, 1.0, 0.5, 255), Rgba::opaque(0, 255, 0));
+    assert_eq!(Rgba::from_hsl(240.0, 1.0, 0.5, 255), Rgba::opaque(0, 0, 255));
+  }
+
+  fn rgba(r: u8, g: u8, b: u8, a: u8) -> Rgba {
+    Rgba { r, g, b, a }
+  }
+
+  #[test]
+  fn parses_hex() {
+    assert_eq!(parse("#abc"), Ok(rgba(0xaa, 0xbb, 0xcc, 255)));
+    assert_eq!(parse("#abcd"), Ok(rgba(0xaa, 0xbb, 0xcc, 0xdd)));
+    assert_eq!(parse("#C6A0F6"), Ok(rgba(198, 160, 246, 255)));
+    assert_eq!(parse("#c6a0f680"), Ok(rgba(198, 160, 246, 0x80)));
+  }
+
+  #[test]
+  fn parses_rgb_with_and_without_commas() {
+    let purple = Ok(rgba(198, 160, 246, 255));
+    assert_eq!(parse("rgb(198 160 246)"), purple);
+    assert_eq!(parse("rgb(198, 160, 246)"), purple);
+    assert_eq!(parse("RGB( 198 160 246 )"), purple);
+    assert_eq!(parse("rgba(198, 160, 246, 0.5)"), Ok(rgba(198, 160, 246, 128)));
+    assert_eq!(parse("rgb(198 160 246 / 50%)"), Ok(rgba(198, 160, 246, 128)));
+    assert_eq!(parse("rgb(100% 0% 50%)"), Ok(rgba(255, 0, 128, 255)));
+    // Out of range channels are clamped, like in CSS.
+    assert_eq!(parse("rgb(300 -5 0)"), Ok(rgba(255, 0, 0, 255)));
+  }
+
+  #[test]
+  fn parses_hsl_with_units() {
+    let cyan = Ok(rgba(0, 255, 255, 255));
+    assert_eq!(parse("hsl(120deg 100% 50%)"), Ok(rgba(0, 255, 0, 255)));
+    assert_eq!(parse("hsl(180, 100%, 50%)"), cyan);
+    assert_eq!(parse("hsl(0.5turn 100% 50%)"), cyan);
+    assert_eq!(parse("hsl(3.14159265rad 100% 50%)"), cyan);
+    assert_eq!(parse("hsla(-120 100% 50% / 0.2)"), Ok(rgba(0, 0, 255, 51)));
+    assert_eq!(parse("hsl(0 0% 100%)"), Ok(rgba(255, 255, 255, 255)));
+  }
+
+  #[test]
+  fn parses_named_colors() {
+    assert_eq!(parse("RebeccaPurple"), Ok(rgba(0x66, 0x33, 0x99, 255)));
+    assert_eq!(parse("  red "), Ok(rgba(255, 0, 0, 255)));
+    assert_eq!(parse("yellowgreen"), Ok(rgba(0x9a, 0xcd, 0x32, 255)));
+  }
+
+  #[test]
+  fn rejects_malformed_colors() {
+    for color in [
+      "#",
+      "#12",
+      "#12345",
+      "#1234567",
+      "#ggg",
+      "rgb(1 2)",
+      "rgb(1 2 3 4 5)",
+      "rgb(a b c)",
+      "rgb(nan 0 0)",
+      "rgb(1 2 3",
+      "hsl(0 50 50)",
+      "hsl(0deg 50% 50)",
+      "hsl(90grad 50% 50%)",
+      "cmyk(0 0 0 0)",
+      "notacolor",
+      "",
+    ] {
+      assert!(parse(color).is_err(), "{:?} parsed", color);
+    }
+  }
+
+  #[test]
+  fn errors_quote_the_color() {
+    assert_eq!(
+      parse(" #GGG "),
+      Err("invalid hex color \"#GGG\", expected #rgb, #rgba, #rrggbb or #rrggbbaa".to_string())
+    );
+    assert_eq!(
+      parse("Rgb(1 2)"),
+      Err("invalid color \"Rgb(1 2)\": expected 3 components and an optional alpha".to_string())
+    );
+    assert_eq!(parse("Blurple"), Err("unknown color \"Blurple\"".to_string()));
+  }
+}
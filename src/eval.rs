@@ -0,0 +1,575 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::backend::{MockBackend, MockWindow, Rect, WindowAttributes};
+use crate::color::{ColorSpec, Moment};
+use crate::focus::{ActiveReason, Focus};
+use crate::process::ProcessInfo;
+use crate::rules::{RuleCheck, RuleSet, WindowInfo};
+use crate::tiling::WindowTiling;
+
+/// Windows as described in a snapshot file, e.g.
+/// `{"focused": 1, "windows": [{"id": 1, "title": "...", "class": "..."}]}`.
+#[derive(Debug, Deserialize)]
+pub struct Snapshot {
+  pub windows: Vec<SnapshotWindow>,
+  #[serde(default)]
+  pub focused: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SnapshotWindow {
+  pub id: usize,
+  #[serde(default)]
+  pub title: String,
+  #[serde(default)]
+  pub class: String,
+  #[serde(default)]
+  pub pid: u32,
+  /// Executable name or full path, for `Process` rules.
+  #[serde(default)]
+  pub process: Option<String>,
+  /// The window that owns this one, e.g. the main window of a dialog.
+  #[serde(default)]
+  pub owner: Option<usize>,
+  /// Where a tiling window manager put the window, for `Workspace`,
+  /// `Layout` and `State` rules.
+  #[serde(default)]
+  pub workspace: Option<String>,
+  #[serde(default)]
+  pub layout: Option<String>,
+  #[serde(default)]
+  pub states: Vec<String>,
+}
+
+/// What the paint loop would do with one window.
+#[derive(Debug, Serialize)]
+pub struct Evaluation {
+  pub id: usize,
+  pub title: String,
+  pub class: String,
+  /// Index into `window_rules`, `None` if no rule applies and the border is left alone.
+  pub rule: Option<usize>,
+  /// The rule's colors as written, e.g. `rainbow` or `darken(accent, 20%)`,
+  /// with palette names looked up.
+  pub active_color: Option<String>,
+  pub inactive_color: Option<String>,
+  /// Whether the window gets its active color: it has focus, is owned by
+  /// the focused window or is a menu of the focused process.
+  pub is_active: bool,
+  /// The color the border ends up with, `accent`, `rainbow` and
+  /// animations resolved at the evaluated moment.
+  pub color: Option<String>,
+}
+
+/// An `Evaluation` along with why: what every rule found, and why the
+/// window is active or not.
+#[derive(Debug, Serialize)]
+pub struct Explanation {
+  #[serde(flatten)]
+  pub evaluation: Evaluation,
+  pub rules: Vec<RuleCheck>,
+  /// `None` for inactive windows.
+  pub active_reason: Option<ActiveReason>,
+  /// Only for inactive windows.
+  pub inactive_reason: Option<String>,
+}
+
+// `WindowTiling` only holds the states the window managers report.
+const STATES: [&str; 7] = [
+  "tiled",
+  "stacked",
+  "monocle",
+  "floating",
+  "maximized",
+  "fullscreen",
+  "minimized",
+];
+
+impl Snapshot {
+  /// Fails on ids used twice and on owners that aren't in the snapshot
+  /// or that own themselves through other windows.
+  pub fn from_json(json: &str) -> Result<Snapshot, String> {
+    let snapshot: Snapshot = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    snapshot.check_owners()?;
+    Ok(snapshot)
+  }
+
+  pub fn load(path: &Path) -> Result<Snapshot, String> {
+    let json = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Snapshot::from_json(&json).map_err(|e| format!("{}: {}", path.display(), e))
+  }
+
+  fn check_owners(&self) -> Result<(), String> {
+    let mut owners = HashMap::new();
+    for window in &self.windows {
+      if owners.insert(window.id, window.owner).is_some() {
+        return Err(format!("window {} is in the snapshot twice", window.id));
+      }
+    }
+    for window in &self.windows {
+      let mut chain = vec![window.id];
+      let mut current = window.owner;
+      while let Some(owner) = current {
+        let Some(&next) = owners.get(&owner) else {
+          return Err(format!(
+            "window {}: owner {} is not in the snapshot",
+            chain[chain.len() - 1],
+            owner
+          ));
+        };
+        chain.push(owner);
+        if owner == window.id {
+          let chain: Vec<String> = chain.iter().map(usize::to_string).collect();
+          return Err(format!(
+            "window {} owns itself: {}",
+            window.id,
+            chain.join(" -> ")
+          ));
+        }
+        // A cycle that doesn't go through this window is reported for
+        // the windows in it.
+        if chain.len() > self.windows.len() {
+          break;
+        }
+        current = next;
+      }
+    }
+    Ok(())
+  }
+}
+
+impl SnapshotWindow {
+  fn attributes(&self) -> WindowAttributes {
+    WindowAttributes {
+      title: self.title.clone(),
+      class: self.class.clone(),
+      pid: self.pid,
+      // Same check as the Win32 backend.
+      is_menu: self.class.contains("#32768"),
+    }
+  }
+
+  fn process_info(&self) -> Option<ProcessInfo> {
+    let path = self.process.as_ref()?;
+    let name = path.rsplit(['\\', '/']).next().unwrap_or(path);
+    Some(ProcessInfo {
+      name: name.to_string(),
+      path: path.clone(),
+    })
+  }
+
+  fn tiling(&self) -> Result<Option<WindowTiling>, String> {
+    if self.workspace.is_none() && self.layout.is_none() && self.states.is_empty() {
+      return Ok(None);
+    }
+    let states = self
+      .states
+      .iter()
+      .map(|state| {
+        STATES
+          .iter()
+          .find(|known| known.eq_ignore_ascii_case(state))
+          .copied()
+          .ok_or_else(|| format!("window {}: unknown state \"{}\"", self.id, state))
+      })
+      .collect::<Result<_, _>>()?;
+    Ok(Some(WindowTiling {
+      workspace_index: 0,
+      workspace_name: self.workspace.clone().unwrap_or_default(),
+      layout: self.layout.clone().unwrap_or_default(),
+      states,
+    }))
+  }
+}
+
+/// Evaluates `rules` against every window of the snapshot with `focused`
+/// (or the snapshot's own `focused`) having focus, without touching any
+/// real window. Dynamic colors are resolved at `moment`, which should be
+/// a `Moment::Fixed` for output that doesn't depend on the app's state.
+pub fn evaluate(
+  rules: &RuleSet,
+  snapshot: &Snapshot,
+  focused: Option<usize>,
+  moment: &Moment,
+) -> Result<Vec<Evaluation>, String> {
+  let explanations = run(rules, snapshot, focused, moment, false)?;
+  Ok(explanations.into_iter().map(|e| e.evaluation).collect())
+}
+
+/// `evaluate`, recording every rule considered and the focus decision.
+pub fn explain(
+  rules: &RuleSet,
+  snapshot: &Snapshot,
+  focused: Option<usize>,
+  moment: &Moment,
+) -> Result<Vec<Explanation>, String> {
+  run(rules, snapshot, focused, moment, true)
+}
+
+fn run(
+  rules: &RuleSet,
+  snapshot: &Snapshot,
+  focused: Option<usize>,
+  moment: &Moment,
+  explain: bool,
+) -> Result<Vec<Explanation>, String> {
+  let backend = MockBackend::new();
+  for window in &snapshot.windows {
+    backend.open(
+      window.id,
+      MockWindow {
+        attributes: window.attributes(),
+        owner: window.owner,
+        bounds: Rect::default(),
+      },
+    );
+  }
+  let focus = Focus::new(&backend, focused.or(snapshot.focused));
+
+  let mut explanations = Vec::with_capacity(snapshot.windows.len());
+  for window in &snapshot.windows {
+    let attributes = window.attributes();
+    let tiling = window.tiling()?;
+    let info = WindowInfo::new(&window.title, &window.class, window.pid)
+      .with_process(window.process_info())
+      .with_tiling(tiling.as_ref());
+    let rule = rules.rule_for(&info);
+    let specs = rule.map(|rule| rules.color_specs_of(rule));
+    let is_active = focus.is_active(&backend, window.id, window.pid, attributes.is_menu);
+    let color = rule.map(|rule| {
+      let (active, inactive) = rules.colors_at(rule, moment, 0.0);
+      ColorSpec::from_colorref(if is_active { active } else { inactive })
+    });
+
+    let evaluation = Evaluation {
+      id: window.id,
+      title: window.title.clone(),
+      class: window.class.clone(),
+      rule: rule.map(|rule| rules.config_index(rule)),
+      active_color: specs.map(|(active, _)| active.to_string()),
+      inactive_color: specs.map(|(_, inactive)| inactive.to_string()),
+      is_active,
+      color: color.map(|color| color.to_string()),
+    };
+    explanations.push(if explain {
+      let active_reason = focus.active_reason(&backend, window.id, window.pid, attributes.is_menu);
+      Explanation {
+        evaluation,
+        rules: rules.explain(&info).checks,
+        inactive_reason: active_reason.is_none().then(|| focus.inactive_reason()),
+        active_reason,
+      }
+    } else {
+      Explanation {
+        evaluation,
+        rules: Vec::new(),
+        active_reason: None,
+        inactive_reason: None,
+      }
+    });
+  }
+  Ok(explanations)
+}
+
+/// A block per window: every rule in config order, then the rule that
+/// applies, whether the window is active and the color it ends up with.
+pub fn format_explanations(explanations: &[Explanation]) -> String {
+  let mut text = String::new();
+  for explanation in explanations {
+    let evaluation = &explanation.evaluation;
+    text.push_str(&format!(
+      "window {} {:?} ({})\n",
+      evaluation.id, evaluation.title, evaluation.class
+    ));
+    for check in &explanation.rules {
+      text.push_str(&format!("  {}\n", check));
+    }
+    match evaluation.rule {
+      Some(rule) => text.push_str(&format!("  rule: window_rules[{}]\n", rule)),
+      None => text.push_str("  rule: none, the border is left alone\n"),
+    }
+    match (&explanation.active_reason, &explanation.inactive_reason) {
+      (Some(reason), _) => text.push_str(&format!("  active: {}\n", reason)),
+      (None, Some(reason)) => text.push_str(&format!("  inactive: {}\n", reason)),
+      (None, None) => {}
+    }
+    if let Some(color) = &evaluation.color {
+      let (which, spec) = if evaluation.is_active {
+        ("active", &evaluation.active_color)
+      } else {
+        ("inactive", &evaluation.inactive_color)
+      };
+      match spec {
+        // Dynamic colors say what they were resolved from.
+        Some(spec) if spec != color => text.push_str(&format!(
+          "  border: {} ({} color, {})\n",
+          color, which, spec
+        )),
+        _ => text.push_str(&format!("  border: {} ({} color)\n", color, which)),
+      }
+    }
+    text.push('\n');
+  }
+  text
+}
+
+/// One row per window, columns padded to line up.
+pub fn format_table(evaluations: &[Evaluation]) -> String {
+  let mut rows = vec![[
+    "ID".to_string(),
+    "TITLE".to_string(),
+    "CLASS".to_string(),
+    "RULE".to_string(),
+    "ACTIVE".to_string(),
+    "INACTIVE".to_string(),
+    "FOCUS".to_string(),
+    "BORDER".to_string(),
+  ]];
+  let none = || "-".to_string();
+  for evaluation in evaluations {
+    rows.push([
+      evaluation.id.to_string(),
+      truncate(&evaluation.title, 40),
+      truncate(&evaluation.class, 30),
+      evaluation
+        .rule
+        .map_or_else(none, |rule| format!("window_rules[{}]", rule)),
+      evaluation
+        .active_color
+        .as_ref()
+        .map_or_else(none, |color| truncate(color, 30)),
+      evaluation
+        .inactive_color
+        .as_ref()
+        .map_or_else(none, |color| truncate(color, 30)),
+      if evaluation.is_active {
+        "active"
+      } else {
+        "inactive"
+      }
+      .to_string(),
+      evaluation.color.clone().unwrap_or_else(none),
+    ]);
+  }
+
+  let mut widths = [0; 8];
+  for row in &rows {
+    for (width, cell) in widths.iter_mut().zip(row) {
+      *width = (*width).max(cell.chars().count());
+    }
+  }
+  let mut table = String::new();
+  for row in &rows {
+    let cells: Vec<String> = row
+      .iter()
+      .zip(widths)
+      .map(|(cell, width)| format!("{:width$}", cell, width = width))
+      .collect();
+    table.push_str(cells.join("  ").trim_end());
+    table.push('\n');
+  }
+  table
+}
+
+fn truncate(text: &str, max: usize) -> String {
+  if text.chars().count() <= max {
+    return text.to_string();
+  }
+  let mut truncated: String = text.chars().take(max - 1).collect();
+  truncated.push('…');
+  truncated
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::Config;
+  use crate::rainbow::RainbowSettings;
+
+  const RULES: &str = "window_rules:
+  - Match: Title
+    contains: firefox
+    active_border_color: red
+    inactive_border_color: gray
+  - Match: Global
+    active_border_color: blue
+";
+
+  fn rules() -> RuleSet {
+    RuleSet::compile(&Config::from_yaml(RULES).unwrap()).unwrap()
+  }
+
+  fn at(seconds: f64) -> Moment {
+    Moment::Fixed {
+      accent: 0xd47800,
+      seconds,
+      rainbow: RainbowSettings::default(),
+    }
+  }
+
+  #[test]
+  fn owned_windows_and_menus_are_active() {
+    let snapshot = Snapshot::from_json(
+      r##"{
+        "focused": 1,
+        "windows": [
+          { "id": 1, "title": "Mozilla Firefox", "pid": 10 },
+          { "id": 2, "title": "Save As", "class": "#32770", "pid": 10, "owner": 1 },
+          { "id": 3, "title": "", "class": "#32768", "pid": 10 },
+          { "id": 4, "title": "Firefox help", "pid": 20 }
+        ]
+      }"##,
+    )
+    .unwrap();
+    let evaluations = evaluate(&rules(), &snapshot, None, &at(0.0)).unwrap();
+    let summary: Vec<_> = evaluations
+      .iter()
+      .map(|e| (e.id, e.rule, e.is_active))
+      .collect();
+    assert_eq!(
+      summary,
+      [
+        (1, Some(0), true),
+        (2, Some(1), true),
+        (3, Some(1), true),
+        (4, Some(0), false)
+      ]
+    );
+
+    let explanations = explain(&rules(), &snapshot, Some(4), &at(0.0)).unwrap();
+    assert_eq!(explanations[3].active_reason, Some(ActiveReason::Focused));
+    assert!(explanations[1].inactive_reason.is_some());
+  }
+
+  #[test]
+  fn snapshot_processes_match_by_name_and_path() {
+    let rules = RuleSet::compile(
+      &Config::from_yaml(
+        "window_rules:
+  - Match: Process
+    equals: code.exe
+    active_border_color: red
+  - Match: Process
+    glob: /opt/*
+    active_border_color: blue
+",
+      )
+      .unwrap(),
+    )
+    .unwrap();
+    let snapshot = Snapshot::from_json(
+      r#"{
+        "windows": [
+          { "id": 1, "process": "C:\\Program Files\\Code\\Code.exe" },
+          { "id": 2, "process": "/opt/app/bin/app" },
+          { "id": 3 }
+        ]
+      }"#,
+    )
+    .unwrap();
+    let rules: Vec<_> = evaluate(&rules, &snapshot, None, &at(0.0))
+      .unwrap()
+      .iter()
+      .map(|evaluation| evaluation.rule)
+      .collect();
+    assert_eq!(rules, [Some(0), Some(1), None]);
+  }
+
+  #[test]
+  fn owners_have_to_be_in_the_snapshot() {
+    let error = Snapshot::from_json(r#"{"windows": [{"id": 1, "owner": 9}]}"#).unwrap_err();
+    assert_eq!(error, "window 1: owner 9 is not in the snapshot");
+
+    let error = Snapshot::from_json(r#"{"windows": [{"id": 1}, {"id": 1}]}"#).unwrap_err();
+    assert_eq!(error, "window 1 is in the snapshot twice");
+  }
+
+  #[test]
+  fn owner_cycles_are_rejected() {
+    let error = Snapshot::from_json(
+      r#"{"windows": [{"id": 1, "owner": 2}, {"id": 2, "owner": 3}, {"id": 3, "owner": 2}]}"#,
+    )
+    .unwrap_err();
+    assert_eq!(error, "window 2 owns itself: 2 -> 3 -> 2");
+
+    let error = Snapshot::from_json(r#"{"windows": [{"id": 1, "owner": 1}]}"#).unwrap_err();
+    assert_eq!(error, "window 1 owns itself: 1 -> 1");
+  }
+
+  #[test]
+  fn unknown_states_are_errors() {
+    let snapshot =
+      Snapshot::from_json(r#"{"windows": [{"id": 1, "states": ["sideways"]}]}"#).unwrap();
+    assert_eq!(
+      evaluate(&rules(), &snapshot, None, &at(0.0)).unwrap_err(),
+      "window 1: unknown state \"sideways\""
+    );
+  }
+
+  #[test]
+  fn dynamic_colors_are_written_as_is_and_resolved_at_the_moment() {
+    let rules = RuleSet::compile(
+      &Config::from_yaml(
+        "window_rules:
+  - Match: Title
+    contains: rainbow
+    active_border_color: rainbow
+    inactive_border_color: rainbow
+  - Match: Title
+    contains: accent
+    active_border_color: accent
+    inactive_border_color: darken(accent, 0%)
+  - Match: Title
+    contains: animated
+    active_border_color: default
+    inactive_border_color: { animate: [{ color: red, duration_ms: 1000 }, { color: blue, duration_ms: 1000 }], space: srgb }
+",
+      )
+      .unwrap(),
+    )
+    .unwrap();
+    let snapshot = Snapshot::from_json(
+      r#"{ "windows": [
+        { "id": 1, "title": "rainbow" },
+        { "id": 2, "title": "accent" },
+        { "id": 3, "title": "animated" }
+      ] }"#,
+    )
+    .unwrap();
+    let colors = |seconds| {
+      evaluate(&rules, &snapshot, None, &at(seconds))
+        .unwrap()
+        .into_iter()
+        .map(|e| (e.inactive_color.unwrap(), e.color.unwrap()))
+        .collect::<Vec<_>>()
+    };
+    let animation = "animate(#ff0000 1000ms, #0000ff 1000ms)".to_string();
+    assert_eq!(
+      colors(0.0),
+      [
+        ("rainbow".to_string(), "#ff0000".to_string()),
+        ("darken(accent, 0%)".to_string(), "#0078d4".to_string()),
+        (animation.clone(), "#ff0000".to_string()),
+      ]
+    );
+    // 30 degrees a second, and halfway around the animation.
+    assert_eq!(
+      colors(1.0),
+      [
+        ("rainbow".to_string(), "#ff8000".to_string()),
+        ("darken(accent, 0%)".to_string(), "#0078d4".to_string()),
+        (animation, "#0000ff".to_string()),
+      ]
+    );
+
+    let explanations = explain(&rules, &snapshot, None, &at(1.0)).unwrap();
+    let text = format_explanations(&explanations);
+    assert!(
+      text.contains("  border: #ff8000 (inactive color, rainbow)\n"),
+      "{}",
+      text
+    );
+  }
+}
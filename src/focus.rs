@@ -0,0 +1,165 @@
+use serde::Serialize;
+use std::fmt;
+
+use crate::backend::{WindowBackend, MAX_OWNER_DEPTH};
+
+/// The focused window and its process, looked up once per frame.
+pub struct Focus {
+  pub window: Option<usize>,
+  pub pid: u32,
+}
+
+/// Why a window gets its active color.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActiveReason {
+  Focused,
+  /// Owned by the focused window, through these owners (the focused window last).
+  OwnedByFocused(Vec<usize>),
+  /// A `#32768` context menu of the focused process, with its pid.
+  MenuOfFocusedProcess(u32),
+}
+
+impl Focus {
+  pub fn new(backend: &dyn WindowBackend, window: Option<usize>) -> Focus {
+    Focus {
+      window,
+      pid: window.map_or(0, |window| backend.pid(window)),
+    }
+  }
+
+  /// Whether a window gets its active color: the focused window, the
+  /// windows it owns (dialogs, tool windows) and the context menus
+  /// (`#32768`) of the focused process, which aren't owned by anything.
+  pub fn is_active(
+    &self,
+    backend: &dyn WindowBackend,
+    window: usize,
+    pid: u32,
+    is_menu: bool,
+  ) -> bool {
+    let is_in_owner_chain = self
+      .window
+      .is_some_and(|active| backend.is_owned_by(window, active));
+    let is_menu_of_active_process = self.pid != 0 && pid == self.pid && is_menu;
+    is_in_owner_chain || is_menu_of_active_process
+  }
+
+  /// `is_active`, along with why. `None` for inactive windows. Like
+  /// `is_owned_by`, follows at most `MAX_OWNER_DEPTH` owners.
+  pub fn active_reason(
+    &self,
+    backend: &dyn WindowBackend,
+    window: usize,
+    pid: u32,
+    is_menu: bool,
+  ) -> Option<ActiveReason> {
+    let active = self.window?;
+    if window == active {
+      return Some(ActiveReason::Focused);
+    }
+    let mut owners = Vec::new();
+    let mut current = backend.owner(window);
+    while let Some(owner) = current {
+      if owners.len() == MAX_OWNER_DEPTH {
+        break;
+      }
+      owners.push(owner);
+      if owner == active {
+        return Some(ActiveReason::OwnedByFocused(owners));
+      }
+      current = backend.owner(owner);
+    }
+    if self.pid != 0 && pid == self.pid && is_menu {
+      return Some(ActiveReason::MenuOfFocusedProcess(pid));
+    }
+    None
+  }
+
+  /// Why `window` doesn't get its active color, for when `active_reason` is `None`.
+  pub fn inactive_reason(&self) -> String {
+    match self.window {
+      Some(active) => format!(
+        "not focused, not owned by the focused window 0x{:x} and not a menu of its process",
+        active
+      ),
+      None => "no window has focus".to_string(),
+    }
+  }
+}
+
+impl fmt::Display for ActiveReason {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ActiveReason::Focused => write!(f, "has focus"),
+      ActiveReason::OwnedByFocused(owners) => {
+        let owners: Vec<String> = owners
+          .iter()
+          .map(|owner| format!("0x{:x}", owner))
+          .collect();
+        write!(f, "owned by the focused window ({})", owners.join(" -> "))
+      }
+      ActiveReason::MenuOfFocusedProcess(pid) => {
+        write!(f, "a #32768 menu of the focused process ({})", pid)
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::backend::{MockBackend, MockWindow, Rect, WindowAttributes};
+
+  fn open(backend: &MockBackend, window: usize, owner: Option<usize>, is_menu: bool) {
+    backend.open(
+      window,
+      MockWindow {
+        attributes: WindowAttributes {
+          title: String::new(),
+          class: String::new(),
+          pid: 7,
+          is_menu,
+        },
+        owner,
+        bounds: Rect::default(),
+      },
+    );
+  }
+
+  #[test]
+  fn reasons_follow_the_owner_chain() {
+    let backend = MockBackend::new();
+    open(&backend, 1, None, false);
+    open(&backend, 2, Some(1), false);
+    open(&backend, 3, Some(2), false);
+    open(&backend, 4, None, true);
+    let focus = Focus::new(&backend, Some(1));
+
+    assert_eq!(
+      focus.active_reason(&backend, 1, 7, false),
+      Some(ActiveReason::Focused)
+    );
+    assert_eq!(
+      focus.active_reason(&backend, 3, 7, false),
+      Some(ActiveReason::OwnedByFocused(vec![2, 1]))
+    );
+    assert_eq!(
+      focus.active_reason(&backend, 4, 7, true),
+      Some(ActiveReason::MenuOfFocusedProcess(7))
+    );
+    assert!(focus.is_active(&backend, 3, 7, false));
+  }
+
+  #[test]
+  fn owner_cycles_are_inactive() {
+    let backend = MockBackend::new();
+    open(&backend, 1, None, false);
+    open(&backend, 2, Some(3), false);
+    open(&backend, 3, Some(2), false);
+    let focus = Focus::new(&backend, Some(1));
+
+    assert_eq!(focus.active_reason(&backend, 2, 7, false), None);
+    assert!(!focus.is_active(&backend, 2, 7, false));
+  }
+}
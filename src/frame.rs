@@ -0,0 +1,120 @@
+use once_cell::sync::Lazy;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+static FRAMES: Lazy<FrameSignal> = Lazy::new(FrameSignal::new);
+
+/// Wakes the paint thread. Requests made while it's busy painting are
+/// merged into a single repaint.
+pub struct FrameSignal {
+  requested: Mutex<bool>,
+  condvar: Condvar,
+}
+
+impl FrameSignal {
+  pub fn new() -> FrameSignal {
+    FrameSignal {
+      requested: Mutex::new(false),
+      condvar: Condvar::new(),
+    }
+  }
+
+  pub fn request(&self) {
+    *self.requested.lock().unwrap() = true;
+    self.condvar.notify_one();
+  }
+
+  /// Blocks until a repaint is requested or `timeout` passes, forever
+  /// without one. Returns whether a repaint was requested.
+  pub fn wait(&self, timeout: Option<Duration>) -> bool {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut requested = self.requested.lock().unwrap();
+    while !*requested {
+      match deadline {
+        None => requested = self.condvar.wait(requested).unwrap(),
+        Some(deadline) => {
+          let now = Instant::now();
+          if now >= deadline {
+            break;
+          }
+          requested = self
+            .condvar
+            .wait_timeout(requested, deadline - now)
+            .unwrap()
+            .0;
+        }
+      }
+    }
+    std::mem::replace(&mut *requested, false)
+  }
+}
+
+impl Default for FrameSignal {
+  fn default() -> FrameSignal {
+    FrameSignal::new()
+  }
+}
+
+/// Asks the paint thread to repaint every window as soon as possible.
+pub fn request_repaint() {
+  FRAMES.request()
+}
+
+/// Called by the paint thread between frames.
+pub fn wait_for_repaint(timeout: Option<Duration>) -> bool {
+  FRAMES.wait(timeout)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+  #[test]
+  fn requests_made_while_busy_are_merged() {
+    let signal = FrameSignal::new();
+    signal.request();
+    signal.request();
+    assert!(signal.wait(Some(Duration::ZERO)));
+    assert!(!signal.wait(Some(Duration::ZERO)));
+  }
+
+  #[test]
+  fn timeouts_return_without_a_request() {
+    let signal = FrameSignal::new();
+    let start = Instant::now();
+    assert!(!signal.wait(Some(Duration::from_millis(20))));
+    assert!(start.elapsed() >= Duration::from_millis(20));
+  }
+
+  // A paint loop like main's: idle it must sleep instead of spinning, and
+  // one request has to wake it for exactly one frame.
+  #[test]
+  fn idle_loops_sleep_until_a_request() {
+    let signal = FrameSignal::new();
+    let frames = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    std::thread::scope(|scope| {
+      scope.spawn(|| {
+        while !stop.load(Ordering::SeqCst) {
+          if signal.wait(None) {
+            frames.fetch_add(1, Ordering::SeqCst);
+          }
+        }
+      });
+      std::thread::sleep(Duration::from_millis(50));
+      assert_eq!(frames.load(Ordering::SeqCst), 0);
+
+      signal.request();
+      let deadline = Instant::now() + Duration::from_secs(5);
+      while frames.load(Ordering::SeqCst) == 0 && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(1));
+      }
+      std::thread::sleep(Duration::from_millis(50));
+      assert_eq!(frames.load(Ordering::SeqCst), 1);
+
+      stop.store(true, Ordering::SeqCst);
+      signal.request();
+    });
+  }
+}
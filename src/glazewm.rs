@@ -0,0 +1,301 @@
+use serde_json::Value;
+use tungstenite::Message;
+
+use crate::tiling::{TilingSnapshot, WindowTiling};
+
+/// Where GlazeWM's IPC server listens.
+pub const DEFAULT_ADDRESS: &str = "ws://127.0.0.1:6123";
+
+const QUERY_WORKSPACES: &str = "query workspaces";
+
+/// What GlazeWM sent, as far as we care.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GlazeMessage {
+  /// Something happened, the workspaces need to be queried again.
+  Event,
+  Workspaces(TilingSnapshot),
+  Other,
+}
+
+/// Subscribes to all events and queries the workspaces again after each
+/// one, calling `on_snapshot` with the result until GlazeWM goes away or
+/// `on_snapshot` returns false.
+pub fn subscribe(
+  address: &str,
+  on_snapshot: &mut dyn FnMut(TilingSnapshot) -> bool,
+) -> Result<(), String> {
+  let (mut socket, _) = tungstenite::connect(address)
+    .map_err(|e| format!("failed to connect to {}: {}", address, e))?;
+  let send = |socket: &mut tungstenite::WebSocket<_>, command: &str| {
+    socket
+      .send(Message::Text(command.to_string()))
+      .map_err(|e| format!("failed to send \"{}\": {}", command, e))
+  };
+  send(&mut socket, "sub -e all")?;
+  send(&mut socket, QUERY_WORKSPACES)?;
+
+  loop {
+    let text = match socket.read() {
+      Ok(Message::Text(text)) => text,
+      Ok(Message::Close(_)) => return Ok(()),
+      Ok(_) => continue,
+      Err(e) => return Err(format!("lost the connection: {}", e)),
+    };
+    match parse_message(&text)? {
+      GlazeMessage::Event => send(&mut socket, QUERY_WORKSPACES)?,
+      GlazeMessage::Workspaces(snapshot) => {
+        if !on_snapshot(snapshot) {
+          let _ = socket.close(None);
+          return Ok(());
+        }
+      }
+      GlazeMessage::Other => {}
+    }
+  }
+}
+
+pub fn parse_message(text: &str) -> Result<GlazeMessage, String> {
+  let message: Value = serde_json::from_str(text).map_err(|e| format!("invalid message: {}", e))?;
+  match message["messageType"].as_str() {
+    Some("event_subscription") => Ok(GlazeMessage::Event),
+    Some("client_response") if message["clientMessage"] == QUERY_WORKSPACES => {
+      if message["success"] == false {
+        return Err(format!(
+          "\"{}\" failed: {}",
+          QUERY_WORKSPACES, message["error"]
+        ));
+      }
+      parse_workspaces(&message["data"]["workspaces"]).map(GlazeMessage::Workspaces)
+    }
+    _ => Ok(GlazeMessage::Other),
+  }
+}
+
+/// Reads the windows out of the workspace trees of `query workspaces`.
+pub fn parse_workspaces(workspaces: &Value) -> Result<TilingSnapshot, String> {
+  let workspaces = workspaces
+    .as_array()
+    .ok_or("the response has no workspaces")?;
+  let mut snapshot = TilingSnapshot::default();
+  for (index, workspace) in workspaces.iter().enumerate() {
+    let tiling = WindowTiling {
+      workspace_index: index,
+      workspace_name: workspace["name"]
+        .as_str()
+        .map_or_else(|| index.to_string(), String::from),
+      layout: workspace["tilingDirection"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string(),
+      states: Vec::new(),
+    };
+    collect_windows(workspace, &tiling, &mut snapshot);
+  }
+  Ok(snapshot)
+}
+
+/// Windows are leaves of the tree, below the workspace or nested splits.
+fn collect_windows(container: &Value, tiling: &WindowTiling, snapshot: &mut TilingSnapshot) {
+  for child in container["children"]
+    .as_array()
+    .map_or(&[][..], Vec::as_slice)
+  {
+    if child["type"] != "window" {
+      collect_windows(child, tiling, snapshot);
+      continue;
+    }
+    let Some(window) = child["handle"].as_u64().map(|handle| handle as usize) else {
+      continue;
+    };
+    let state = &child["state"];
+    let state = match state["type"].as_str() {
+      Some("tiling") => "tiled",
+      Some("floating") => "floating",
+      Some("fullscreen") if state["maximized"] == true => "maximized",
+      Some("fullscreen") => "fullscreen",
+      Some("minimized") => "minimized",
+      _ => continue,
+    };
+    if child["hasFocus"] == true {
+      snapshot.focused = Some(window);
+    }
+    snapshot.windows.insert(
+      window,
+      WindowTiling {
+        states: vec![state],
+        ..tiling.clone()
+      },
+    );
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::Config;
+  use crate::rules::{RuleSet, WindowInfo};
+  use serde_json::json;
+
+  fn window(handle: u64, state: Value, has_focus: bool) -> Value {
+    json!({
+      "type": "window",
+      "handle": handle,
+      "title": "title",
+      "className": "AppWindow",
+      "processName": "app",
+      "state": state,
+      "hasFocus": has_focus
+    })
+  }
+
+  /// Trimmed down from the response of GlazeWM 3.
+  fn workspaces() -> Value {
+    json!([
+      {
+        "type": "workspace",
+        "name": "1",
+        "tilingDirection": "horizontal",
+        "hasFocus": false,
+        "children": [
+          window(1, json!({ "type": "tiling" }), false),
+          {
+            "type": "split",
+            "tilingDirection": "vertical",
+            "children": [
+              window(2, json!({ "type": "tiling" }), false),
+              window(3, json!({ "type": "tiling" }), true)
+            ]
+          },
+          window(4, json!({ "type": "floating", "centered": true, "shownOnTop": false }), false)
+        ]
+      },
+      {
+        "type": "workspace",
+        "name": "web",
+        "tilingDirection": "vertical",
+        "hasFocus": false,
+        "children": [
+          window(5, json!({ "type": "fullscreen", "maximized": true, "shownOnTop": false }), false),
+          window(6, json!({ "type": "fullscreen", "maximized": false, "shownOnTop": false }), false),
+          window(7, json!({ "type": "minimized" }), false),
+          window(8, json!({ "type": "something_new" }), false)
+        ]
+      }
+    ])
+  }
+
+  fn response(data: Value) -> String {
+    json!({
+      "messageType": "client_response",
+      "clientMessage": "query workspaces",
+      "data": data,
+      "error": null,
+      "success": true
+    })
+    .to_string()
+  }
+
+  #[test]
+  fn reads_windows_from_nested_splits() {
+    let snapshot = parse_workspaces(&workspaces()).unwrap();
+    let tiling = |index, name: &str, layout: &str, state| WindowTiling {
+      workspace_index: index,
+      workspace_name: name.to_string(),
+      layout: layout.to_string(),
+      states: vec![state],
+    };
+    assert_eq!(snapshot.windows[&1], tiling(0, "1", "horizontal", "tiled"));
+    assert_eq!(snapshot.windows[&3], tiling(0, "1", "horizontal", "tiled"));
+    assert_eq!(
+      snapshot.windows[&4],
+      tiling(0, "1", "horizontal", "floating")
+    );
+    assert_eq!(
+      snapshot.windows[&5],
+      tiling(1, "web", "vertical", "maximized")
+    );
+    assert_eq!(
+      snapshot.windows[&6],
+      tiling(1, "web", "vertical", "fullscreen")
+    );
+    assert_eq!(
+      snapshot.windows[&7],
+      tiling(1, "web", "vertical", "minimized")
+    );
+    // Unknown states are left out.
+    assert_eq!(snapshot.windows.len(), 7);
+    assert_eq!(snapshot.focused, Some(3));
+  }
+
+  #[test]
+  fn parses_messages() {
+    let event = json!({
+      "messageType": "event_subscription",
+      "data": { "eventType": "focus_changed" },
+      "subscriptionId": "7b4c",
+      "success": true
+    });
+    assert_eq!(parse_message(&event.to_string()), Ok(GlazeMessage::Event));
+
+    match parse_message(&response(json!({ "workspaces": workspaces() }))) {
+      Ok(GlazeMessage::Workspaces(snapshot)) => assert_eq!(snapshot.focused, Some(3)),
+      other => panic!("expected workspaces, got {:?}", other),
+    }
+
+    let subscribed = json!({
+      "messageType": "client_response",
+      "clientMessage": "sub -e all",
+      "data": { "subscriptionId": "7b4c" },
+      "success": true
+    });
+    assert_eq!(
+      parse_message(&subscribed.to_string()),
+      Ok(GlazeMessage::Other)
+    );
+  }
+
+  #[test]
+  fn failed_queries_are_errors() {
+    let failed = json!({
+      "messageType": "client_response",
+      "clientMessage": "query workspaces",
+      "data": null,
+      "error": "unknown command",
+      "success": false
+    });
+    assert!(parse_message(&failed.to_string()).is_err());
+    assert!(parse_message(&response(json!({}))).is_err());
+    assert!(parse_message("{").is_err());
+  }
+
+  #[test]
+  fn tiling_rules_match_the_snapshot() {
+    let rules = RuleSet::compile(
+      &Config::from_yaml(
+        "window_rules:
+  - Match: State
+    contains: [floating, maximized]
+    active_border_color: red
+  - Match: Workspace
+    equals: web
+    active_border_color: green
+  - Match: Layout
+    equals: horizontal
+    active_border_color: blue
+",
+      )
+      .unwrap(),
+    )
+    .unwrap();
+    let snapshot = parse_workspaces(&workspaces()).unwrap();
+    let rule_for = |handle: usize| {
+      let window = WindowInfo::new("", "", 0).with_tiling(snapshot.windows.get(&handle));
+      rules.rule_for(&window).map(|rule| rules.config_index(rule))
+    };
+    assert_eq!(rule_for(2), Some(2));
+    assert_eq!(rule_for(4), Some(0));
+    assert_eq!(rule_for(5), Some(0));
+    assert_eq!(rule_for(6), Some(1));
+    assert_eq!(rule_for(8), None);
+  }
+}
@@ -1,8 +1,10 @@
 // src/gui.rs
 
 use eframe::egui;
-use crate::config::{Config, Rule, RuleMatch};
-use crate::logger::Logger;
+use cute_borders::color::ColorSpec;
+use cute_borders::config::{Config, Rule};
+use cute_borders::css;
+use cute_borders::logger::Logger;
 
 struct ConfigApp {
     is_rainbow_active: bool,
@@ -12,11 +14,16 @@ struct ConfigApp {
     active_color_picker: egui::Color32,
     inactive_color_picker: egui::Color32,
     rainbow_speed: f32,
+    // Cores que não cabem num campo de texto (animações) são mantidas se o texto não mudar.
+    loaded_active_color: ColorSpec,
+    loaded_inactive_color: ColorSpec,
 }
 
 impl ConfigApp {
     fn hex_to_color32(hex: &str) -> egui::Color32 {
-        egui::Color32::from_hex(hex).unwrap_or(egui::Color32::BLACK)
+        css::parse(hex)
+            .map(|c| egui::Color32::from_rgba_unmultiplied(c.r, c.g, c.b, c.a))
+            .unwrap_or(egui::Color32::BLACK)
     }
 
     fn color32_to_hex(color: egui::Color32) -> String {
@@ -29,15 +36,19 @@ impl ConfigApp {
         let mut inactive_hex = "#444444".to_string();
         let mut is_rainbow = false;
         let mut is_inactive_disabled = false;
+        let mut loaded_active_color = ColorSpec::Default;
+        let mut loaded_inactive_color = ColorSpec::Default;
 
-        if let Some(global_rule) = config.window_rules.iter().find(|r| r.rule_match == RuleMatch::Global) {
-            is_inactive_disabled = global_rule.inactive_border_color.is_empty();
+        if let Some(global_rule) = config.window_rules.iter().find(|r| r.is_global()) {
+            loaded_active_color = global_rule.active_border_color.clone();
+            loaded_inactive_color = global_rule.inactive_border_color.clone();
+            is_inactive_disabled = global_rule.inactive_border_color == ColorSpec::Default;
             if !is_inactive_disabled {
-                inactive_hex = global_rule.inactive_border_color.clone();
+                inactive_hex = global_rule.inactive_border_color.to_string();
             }
-            is_rainbow = global_rule.active_border_color.to_lowercase() == "rainbow";
+            is_rainbow = global_rule.active_border_color == ColorSpec::Rainbow;
             if !is_rainbow {
-                active_hex = global_rule.active_border_color.clone();
+                active_hex = global_rule.active_border_color.to_string();
             }
         }
 
@@ -48,26 +59,35 @@ impl ConfigApp {
             inactive_color_hex: inactive_hex.clone(),
             active_color_picker: Self::hex_to_color32(&active_hex),
             inactive_color_picker: Self::hex_to_color32(&inactive_hex),
-            rainbow_speed: config.rainbow_speed.unwrap_or(1.0),
+            rainbow_speed: config.rainbow_settings().speed,
+            loaded_active_color,
+            loaded_inactive_color,
         }
     }
 
     fn save_to_config(&self) {
         let mut config = Config::read_for_gui();
-        config.rainbow_speed = Some(self.rainbow_speed);
-
-        let active_color = if self.is_rainbow_active { "rainbow".to_string() } else { self.active_color_hex.clone() };
-        let inactive_color = if self.is_inactive_disabled { "".to_string() } else { self.inactive_color_hex.clone() };
+        config.rainbow_degrees_per_second = Some(self.rainbow_speed);
+        config.rainbow_speed = None;
+
+        let parse = |text: &str, loaded: &ColorSpec| {
+            if text == loaded.to_string() { Ok(loaded.clone()) } else { text.parse::<ColorSpec>() }
+        };
+        let active_color = if self.is_rainbow_active { Ok(ColorSpec::Rainbow) } else { parse(&self.active_color_hex, &self.loaded_active_color) };
+        let inactive_color = if self.is_inactive_disabled { Ok(ColorSpec::Default) } else { parse(&self.inactive_color_hex, &self.loaded_inactive_color) };
+        let (active_color, inactive_color) = match (active_color, inactive_color) {
+            (Ok(active), Ok(inactive)) => (active, inactive),
+            (Err(e), _) | (_, Err(e)) => {
+                Logger::log(&format!("[GUI ERROR] Cor inválida, configuração não salva: {}", e));
+                return;
+            }
+        };
 
-        if let Some(global_rule) = config.window_rules.iter_mut().find(|r| r.rule_match == RuleMatch::Global) {
+        if let Some(global_rule) = config.window_rules.iter_mut().find(|r| r.is_global()) {
             global_rule.active_border_color = active_color;
             global_rule.inactive_border_color = inactive_color;
         } else {
-            config.window_rules.insert(0, Rule {
-                rule_match: RuleMatch::Global, contains: None,
-                active_border_color: active_color,
-                inactive_border_color: inactive_color,
-            });
+            config.window_rules.insert(0, Rule::global(active_color, inactive_color));
         }
         if let Err(e) = Config::write_config(&config) {
             Logger::log(&format!("[GUI ERROR] Falha ao salvar configuração: {:?}", e));
@@ -105,7 +125,7 @@ impl eframe::App for ConfigApp {
 
                     if self.is_rainbow_active {
                         ui.label("Velocidade do Rainbow:");
-                        ui.add(egui::Slider::new(&mut self.rainbow_speed, 0.1..=10.0));
+                        ui.add(egui::Slider::new(&mut self.rainbow_speed, 3.0..=300.0).logarithmic(true).suffix("°/s"));
                         ui.end_row();
                     }
                     
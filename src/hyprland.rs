@@ -0,0 +1,681 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::backend::{EventHandler, Rect, WindowAttributes, WindowBackend, WindowEvent};
+use crate::logger::Logger;
+use crate::{DWMWA_COLOR_DEFAULT, DWMWA_COLOR_NONE};
+
+/// One line of Hyprland's event socket, `EVENT>>DATA`. Only the events
+/// that change which windows exist, their titles or the focus. Where
+/// Hyprland sends a v2 with the address, the v1 without it is ignored.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HyprlandEvent {
+  /// `activewindowv2>>address`, without an address on an empty workspace.
+  ActiveWindow(Option<usize>),
+  /// `openwindow>>address,workspace,class,title`
+  OpenWindow {
+    address: usize,
+    class: String,
+    title: String,
+  },
+  /// `closewindow>>address`
+  CloseWindow(usize),
+  /// `windowtitle>>address`, sent by every version but without the title.
+  WindowTitle(usize),
+  /// `windowtitlev2>>address,title`, since Hyprland 0.42.
+  WindowTitleV2 { address: usize, title: String },
+  /// `movewindow` and `changefloatingmode`, the window moved somewhere else.
+  MoveWindow(usize),
+}
+
+impl HyprlandEvent {
+  pub fn parse(line: &str) -> Option<HyprlandEvent> {
+    let (name, data) = line.split_once(">>")?;
+    let first = data.split(',').next().unwrap_or_default();
+    match name {
+      "activewindowv2" => Some(HyprlandEvent::ActiveWindow(parse_address(first))),
+      "openwindow" => {
+        // The title comes last and can contain commas itself.
+        let mut fields = data.splitn(4, ',');
+        let address = parse_address(fields.next()?)?;
+        let _workspace = fields.next()?;
+        Some(HyprlandEvent::OpenWindow {
+          address,
+          class: fields.next()?.to_string(),
+          title: fields.next().unwrap_or_default().to_string(),
+        })
+      }
+      "closewindow" => parse_address(first).map(HyprlandEvent::CloseWindow),
+      "windowtitle" => parse_address(first).map(HyprlandEvent::WindowTitle),
+      "windowtitlev2" => {
+        let (address, title) = data.split_once(',')?;
+        Some(HyprlandEvent::WindowTitleV2 {
+          address: parse_address(address)?,
+          title: title.to_string(),
+        })
+      }
+      "movewindow" | "changefloatingmode" => parse_address(first).map(HyprlandEvent::MoveWindow),
+      _ => None,
+    }
+  }
+}
+
+/// Window addresses are hex, with `0x` in JSON and without in events.
+fn parse_address(address: &str) -> Option<usize> {
+  let address = address.trim();
+  let address = address.strip_prefix("0x").unwrap_or(address);
+  usize::from_str_radix(address, 16).ok()
+}
+
+/// A window of `j/clients`, only the fields we use.
+#[derive(Debug, Deserialize)]
+struct Client {
+  address: String,
+  #[serde(default)]
+  mapped: Option<bool>,
+  at: [i32; 2],
+  size: [i32; 2],
+  class: String,
+  title: String,
+  #[serde(default)]
+  pid: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ActiveWindow {
+  #[serde(default)]
+  address: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Monitor {
+  x: i32,
+  y: i32,
+  width: i32,
+  height: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConfigOption {
+  #[serde(default)]
+  custom: Option<String>,
+}
+
+impl Client {
+  fn window(&self) -> HyprlandWindow {
+    HyprlandWindow {
+      attributes: WindowAttributes {
+        title: self.title.clone(),
+        class: self.class.clone(),
+        pid: u32::try_from(self.pid).unwrap_or(0),
+        is_menu: false,
+      },
+      bounds: Rect {
+        left: self.at[0],
+        top: self.at[1],
+        right: self.at[0] + self.size[0],
+        bottom: self.at[1] + self.size[1],
+      },
+    }
+  }
+}
+
+/// Borders on Hyprland. Events come from `.socket2.sock`, queries and
+/// `setprop` go to `.socket.sock`, one connection per request. Windows
+/// are identified by their address.
+///
+/// The paint loop already picks the active or inactive color of each
+/// window, so both of Hyprland's border colors of a window are set to it.
+pub struct HyprlandBackend {
+  requests: PathBuf,
+  events: PathBuf,
+  state: Mutex<HyprlandState>,
+  // `general:col.active_border` and `general:col.inactive_border` as
+  // `setprop` values, what `DWMWA_COLOR_DEFAULT` puts back.
+  default_colors: Option<(String, String)>,
+  // Set on the first `windowtitlev2`, from then on `windowtitle` is a duplicate.
+  has_title_v2: AtomicBool,
+}
+
+#[derive(Default)]
+struct HyprlandState {
+  windows: HashMap<usize, HyprlandWindow>,
+  active: Option<usize>,
+  desktop: Rect,
+}
+
+struct HyprlandWindow {
+  attributes: WindowAttributes,
+  bounds: Rect,
+}
+
+impl HyprlandBackend {
+  /// Finds the sockets of the instance in `$HYPRLAND_INSTANCE_SIGNATURE`,
+  /// `None` when we're not running under Hyprland.
+  pub fn from_env() -> Option<io::Result<HyprlandBackend>> {
+    let signature = std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE")?;
+    // Since 0.40 the sockets live in $XDG_RUNTIME_DIR, before that in /tmp.
+    let directory = std::env::var_os("XDG_RUNTIME_DIR")
+      .map(|runtime| Path::new(&runtime).join("hypr").join(&signature))
+      .filter(|directory| directory.exists())
+      .unwrap_or_else(|| Path::new("/tmp/hypr").join(&signature));
+    Some(HyprlandBackend::connect(
+      &directory.join(".socket.sock"),
+      &directory.join(".socket2.sock"),
+    ))
+  }
+
+  pub fn connect(requests: &Path, events: &Path) -> io::Result<HyprlandBackend> {
+    let mut backend = HyprlandBackend {
+      requests: requests.to_path_buf(),
+      events: events.to_path_buf(),
+      state: Mutex::new(HyprlandState::default()),
+      default_colors: None,
+      has_title_v2: AtomicBool::new(false),
+    };
+    // Also tells us early if nothing is listening.
+    backend.default_colors = backend.query_default_colors()?;
+    // Focus is only known from queries and events, read it before the first frame asks.
+    backend.enumerate();
+    Ok(backend)
+  }
+
+  /// Sends a request and reads the reply, Hyprland closes the connection after it.
+  fn request(&self, request: &str) -> io::Result<String> {
+    let mut stream = UnixStream::connect(&self.requests)?;
+    stream.write_all(request.as_bytes())?;
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    Ok(reply)
+  }
+
+  fn query<T: for<'de> Deserialize<'de>>(&self, request: &str) -> Result<T, String> {
+    let reply = self.request(request).map_err(|e| e.to_string())?;
+    serde_json::from_str(&reply).map_err(|e| e.to_string())
+  }
+
+  fn query_default_colors(&self) -> io::Result<Option<(String, String)>> {
+    let option = |name: &str| -> io::Result<Option<String>> {
+      let reply = self.request(&format!("j/getoption {}", name))?;
+      Ok(
+        serde_json::from_str::<ConfigOption>(&reply)
+          .ok()
+          .and_then(|option| option.custom)
+          .map(|custom| gradient(&custom)),
+      )
+    };
+    let active = option("general:col.active_border")?;
+    let inactive = option("general:col.inactive_border")?;
+    Ok(active.zip(inactive))
+  }
+
+  fn active_window(&self) -> Option<usize> {
+    match self.query::<ActiveWindow>("j/activewindow") {
+      Ok(active) => active.address.as_deref().and_then(parse_address),
+      Err(e) => {
+        Logger::log(&format!("[ERROR] Failed to get the active window: {}", e));
+        None
+      }
+    }
+  }
+
+  /// Reads one window again with a single `j/clients`, leaving the others
+  /// as they are. Hyprland has no query for a single window.
+  fn refresh_client(&self, address: usize) -> Result<(), String> {
+    let clients = self.query::<Vec<Client>>("j/clients")?;
+    let client = clients.iter().find(|client| {
+      client.mapped != Some(false) && parse_address(&client.address) == Some(address)
+    });
+    let mut state = self.state.lock().unwrap();
+    match client {
+      Some(client) => state.windows.insert(address, client.window()),
+      None => state.windows.remove(&address),
+    };
+    Ok(())
+  }
+
+  /// Updates the cached windows and focus for an event, returns what it
+  /// means for the registry.
+  fn apply_event(&self, event: HyprlandEvent) -> Option<WindowEvent> {
+    match event {
+      HyprlandEvent::ActiveWindow(active) => {
+        self.state.lock().unwrap().active = active;
+        active.map(WindowEvent::Foreground)
+      }
+      HyprlandEvent::OpenWindow {
+        address,
+        class,
+        title,
+      } => {
+        // The event has no pid or position, those need a query.
+        if let Err(e) = self.refresh_client(address) {
+          Logger::log(&format!(
+            "[ERROR] Failed to read window 0x{:x}: {}",
+            address, e
+          ));
+          let window = HyprlandWindow {
+            attributes: WindowAttributes {
+              title,
+              class,
+              pid: 0,
+              is_menu: false,
+            },
+            bounds: Rect::default(),
+          };
+          self.state.lock().unwrap().windows.insert(address, window);
+        }
+        Some(WindowEvent::Created(address))
+      }
+      HyprlandEvent::CloseWindow(address) => {
+        let mut state = self.state.lock().unwrap();
+        state.windows.remove(&address);
+        if state.active == Some(address) {
+          state.active = None;
+        }
+        Some(WindowEvent::Destroyed(address))
+      }
+      HyprlandEvent::WindowTitle(address) => {
+        if self.has_title_v2.load(Ordering::Relaxed) {
+          return None;
+        }
+        if let Err(e) = self.refresh_client(address) {
+          Logger::log(&format!(
+            "[ERROR] Failed to read window 0x{:x}: {}",
+            address, e
+          ));
+        }
+        Some(WindowEvent::NameChanged(address))
+      }
+      HyprlandEvent::WindowTitleV2 { address, title } => {
+        self.has_title_v2.store(true, Ordering::Relaxed);
+        let mut state = self.state.lock().unwrap();
+        let window = state.windows.get_mut(&address)?;
+        window.attributes.title = title;
+        Some(WindowEvent::NameChanged(address))
+      }
+      HyprlandEvent::MoveWindow(address) => {
+        if let Err(e) = self.refresh_client(address) {
+          Logger::log(&format!(
+            "[ERROR] Failed to read window 0x{:x}: {}",
+            address, e
+          ));
+        }
+        Some(WindowEvent::Shown(address))
+      }
+    }
+  }
+}
+
+impl WindowBackend for HyprlandBackend {
+  fn enumerate(&self) -> Vec<usize> {
+    let clients = match self.query::<Vec<Client>>("j/clients") {
+      Ok(clients) => clients,
+      Err(e) => {
+        Logger::log(&format!("[ERROR] Failed to get the window list: {}", e));
+        return Vec::new();
+      }
+    };
+    let monitors = self.query::<Vec<Monitor>>("j/monitors").unwrap_or_default();
+    let active = self.active_window();
+
+    let windows: HashMap<usize, HyprlandWindow> = clients
+      .iter()
+      .filter(|client| client.mapped != Some(false))
+      .filter_map(|client| Some((parse_address(&client.address)?, client.window())))
+      .collect();
+    let addresses = windows.keys().copied().collect();
+
+    let mut state = self.state.lock().unwrap();
+    state.windows = windows;
+    state.active = active;
+    if let Some(desktop) = monitors
+      .iter()
+      .map(|monitor| Rect {
+        left: monitor.x,
+        top: monitor.y,
+        right: monitor.x + monitor.width,
+        bottom: monitor.y + monitor.height,
+      })
+      .reduce(|a, b| Rect {
+        left: a.left.min(b.left),
+        top: a.top.min(b.top),
+        right: a.right.max(b.right),
+        bottom: a.bottom.max(b.bottom),
+      })
+    {
+      state.desktop = desktop;
+    }
+    addresses
+  }
+
+  fn describe(&self, window: usize) -> Option<WindowAttributes> {
+    let state = self.state.lock().unwrap();
+    state
+      .windows
+      .get(&window)
+      .map(|window| window.attributes.clone())
+  }
+
+  fn is_alive(&self, window: usize) -> bool {
+    self.state.lock().unwrap().windows.contains_key(&window)
+  }
+
+  fn foreground(&self) -> Option<usize> {
+    self.state.lock().unwrap().active
+  }
+
+  // Dialogs are windows of their own on Hyprland.
+  fn owner(&self, _window: usize) -> Option<usize> {
+    None
+  }
+
+  fn pid(&self, window: usize) -> u32 {
+    let state = self.state.lock().unwrap();
+    state
+      .windows
+      .get(&window)
+      .map_or(0, |window| window.attributes.pid)
+  }
+
+  fn bounds(&self, window: usize) -> Option<Rect> {
+    let state = self.state.lock().unwrap();
+    state.windows.get(&window).map(|window| window.bounds)
+  }
+
+  fn desktop(&self) -> Rect {
+    self.state.lock().unwrap().desktop
+  }
+
+  fn set_border_color(&self, window: usize, color: u32) {
+    let (active, inactive) = match color {
+      DWMWA_COLOR_DEFAULT => match &self.default_colors {
+        Some(defaults) => defaults.clone(),
+        None => return,
+      },
+      DWMWA_COLOR_NONE => ("rgba(00000000)".to_string(), "rgba(00000000)".to_string()),
+      _ => (rgba(color), rgba(color)),
+    };
+    let request = format!(
+      "[[BATCH]]dispatch setprop address:0x{:x} activebordercolor {};dispatch setprop address:0x{:x} inactivebordercolor {}",
+      window, active, window, inactive
+    );
+    match self.request(&request) {
+      Ok(reply) if reply.split_whitespace().all(|result| result == "ok") => {}
+      Ok(reply) => Logger::log(&format!(
+        "[ERROR] Hyprland refused the border color of 0x{:x}: {}",
+        window,
+        reply.trim()
+      )),
+      Err(e) => Logger::log(&format!(
+        "[ERROR] Failed to set the border color of 0x{:x}: {}",
+        window, e
+      )),
+    }
+  }
+
+  fn subscribe(&self, mut on_event: EventHandler) {
+    let stream = match UnixStream::connect(&self.events) {
+      Ok(stream) => stream,
+      Err(e) => {
+        Logger::log(&format!(
+          "[ERROR] Failed to connect to {:?}: {}",
+          self.events, e
+        ));
+        return;
+      }
+    };
+    for line in BufReader::new(stream).lines() {
+      let line = match line {
+        Ok(line) => line,
+        Err(e) => {
+          Logger::log(&format!("[ERROR] Lost the window event connection: {}", e));
+          return;
+        }
+      };
+      if let Some(window_event) =
+        HyprlandEvent::parse(&line).and_then(|event| self.apply_event(event))
+      {
+        on_event(window_event);
+      }
+    }
+  }
+}
+
+/// A COLORREF as a Hyprland color, `rgba(rrggbbaa)`.
+fn rgba(color: u32) -> String {
+  let (r, g, b) = (color & 0xFF, (color >> 8) & 0xFF, (color >> 16) & 0xFF);
+  format!("rgba({:02x}{:02x}{:02x}ff)", r, g, b)
+}
+
+/// `getoption` shows gradients as `aarrggbb aarrggbb 45deg`, without
+/// leading zeros, `setprop` wants `rgba(rrggbbaa) rgba(rrggbbaa) 45deg`.
+fn gradient(custom: &str) -> String {
+  custom
+    .split_whitespace()
+    .map(|part| match u32::from_str_radix(part, 16) {
+      Ok(argb) => format!("rgba({:06x}{:02x})", argb & 0xFFFFFF, argb >> 24),
+      Err(_) => part.to_string(),
+    })
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::os::unix::net::UnixListener;
+  use std::sync::Arc;
+
+  /// Hyprland's two sockets in a temporary directory. `.socket.sock`
+  /// answers queries from `clients` and records every request,
+  /// `.socket2.sock` sends `events` to whoever connects and hangs up.
+  struct FakeHyprland {
+    requests: PathBuf,
+    events: PathBuf,
+    clients: Arc<Mutex<serde_json::Value>>,
+    log: Arc<Mutex<Vec<String>>>,
+  }
+
+  impl FakeHyprland {
+    fn start(name: &str, clients: serde_json::Value, events: &[&str]) -> FakeHyprland {
+      let directory =
+        std::env::temp_dir().join(format!("cute-borders-hypr-{}-{}", std::process::id(), name));
+      let _ = std::fs::remove_dir_all(&directory);
+      std::fs::create_dir_all(&directory).unwrap();
+      let fake = FakeHyprland {
+        requests: directory.join(".socket.sock"),
+        events: directory.join(".socket2.sock"),
+        clients: Arc::new(Mutex::new(clients)),
+        log: Arc::default(),
+      };
+
+      let listener = UnixListener::bind(&fake.requests).unwrap();
+      let (clients, log) = (fake.clients.clone(), fake.log.clone());
+      std::thread::spawn(move || {
+        for stream in listener.incoming() {
+          let mut stream = stream.unwrap();
+          let mut buffer = [0u8; 4096];
+          let length = stream.read(&mut buffer).unwrap();
+          let request = String::from_utf8_lossy(&buffer[..length]).into_owned();
+          let reply = match request.as_str() {
+            "j/clients" => clients.lock().unwrap().to_string(),
+            "j/monitors" => r#"[{"x":0,"y":0,"width":2560,"height":1440}]"#.to_string(),
+            "j/activewindow" => r#"{"address":"0xa1"}"#.to_string(),
+            "j/getoption general:col.active_border" => {
+              r#"{"custom":"ff33ccff ee00ff99 45deg"}"#.to_string()
+            }
+            "j/getoption general:col.inactive_border" => r#"{"custom":"aa595959"}"#.to_string(),
+            batch if batch.starts_with("[[BATCH]]") => "ok\n\nok".to_string(),
+            other => panic!("unexpected request {:?}", other),
+          };
+          log.lock().unwrap().push(request);
+          stream.write_all(reply.as_bytes()).unwrap();
+        }
+      });
+
+      let listener = UnixListener::bind(&fake.events).unwrap();
+      let events: Vec<String> = events.iter().map(|line| format!("{}\n", line)).collect();
+      std::thread::spawn(move || {
+        for stream in listener.incoming() {
+          let mut stream = stream.unwrap();
+          for line in &events {
+            stream.write_all(line.as_bytes()).unwrap();
+          }
+        }
+      });
+      fake
+    }
+
+    fn connect(&self) -> HyprlandBackend {
+      HyprlandBackend::connect(&self.requests, &self.events).unwrap()
+    }
+
+    fn requests(&self, request: &str) -> usize {
+      self
+        .log
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|r| *r == request)
+        .count()
+    }
+  }
+
+  fn client(address: &str, class: &str, title: &str, x: i32) -> serde_json::Value {
+    serde_json::json!({
+      "address": address,
+      "mapped": true,
+      "at": [x, 0],
+      "size": [800, 600],
+      "class": class,
+      "title": title,
+      "pid": 4242,
+    })
+  }
+
+  fn subscribe(backend: &HyprlandBackend) -> Vec<WindowEvent> {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let record = received.clone();
+    // Returns once the fake hangs up.
+    backend.subscribe(Box::new(move |event| record.lock().unwrap().push(event)));
+    let events = received.lock().unwrap().clone();
+    events
+  }
+
+  #[test]
+  fn parses_events() {
+    assert_eq!(
+      HyprlandEvent::parse("openwindow>>a2,1,kitty,vim: a, b"),
+      Some(HyprlandEvent::OpenWindow {
+        address: 0xa2,
+        class: "kitty".to_string(),
+        title: "vim: a, b".to_string(),
+      })
+    );
+    assert_eq!(
+      HyprlandEvent::parse("windowtitlev2>>a2,vim, again"),
+      Some(HyprlandEvent::WindowTitleV2 {
+        address: 0xa2,
+        title: "vim, again".to_string(),
+      })
+    );
+    assert_eq!(
+      HyprlandEvent::parse("activewindowv2>>a2"),
+      Some(HyprlandEvent::ActiveWindow(Some(0xa2)))
+    );
+    assert_eq!(
+      HyprlandEvent::parse("activewindowv2>>,"),
+      Some(HyprlandEvent::ActiveWindow(None))
+    );
+    assert_eq!(HyprlandEvent::parse("activewindow>>kitty,vim"), None);
+    assert_eq!(HyprlandEvent::parse("workspace>>2"), None);
+  }
+
+  #[test]
+  fn connect_reads_windows_focus_and_defaults() {
+    let fake = FakeHyprland::start(
+      "connect",
+      serde_json::json!([
+        client("0xa1", "firefox", "Mozilla Firefox", 0),
+        { "address": "0xa3", "mapped": false, "at": [0, 0], "size": [0, 0], "class": "", "title": "" },
+      ]),
+      &[],
+    );
+    let hyprland = fake.connect();
+    assert_eq!(hyprland.foreground(), Some(0xa1));
+    assert_eq!(hyprland.describe(0xa1).unwrap().class, "firefox");
+    assert!(!hyprland.is_alive(0xa3));
+    assert_eq!(hyprland.desktop().right, 2560);
+    assert_eq!(
+      hyprland.default_colors,
+      Some((
+        "rgba(33ccffff) rgba(00ff99ee) 45deg".to_string(),
+        "rgba(595959aa)".to_string()
+      ))
+    );
+
+    hyprland.set_border_color(0xa1, 0x0000ff);
+    hyprland.set_border_color(0xa1, DWMWA_COLOR_DEFAULT);
+    let log = fake.log.lock().unwrap();
+    assert_eq!(
+      log[log.len() - 2..],
+      [
+        "[[BATCH]]dispatch setprop address:0xa1 activebordercolor rgba(ff0000ff);dispatch setprop address:0xa1 inactivebordercolor rgba(ff0000ff)",
+        "[[BATCH]]dispatch setprop address:0xa1 activebordercolor rgba(33ccffff) rgba(00ff99ee) 45deg;dispatch setprop address:0xa1 inactivebordercolor rgba(595959aa)",
+      ]
+    );
+  }
+
+  #[test]
+  fn events_only_query_what_they_need() {
+    let fake = FakeHyprland::start(
+      "events",
+      serde_json::json!([client("0xa1", "firefox", "Mozilla Firefox", 0)]),
+      &[
+        "openwindow>>a2,1,kitty,fish",
+        "activewindow>>kitty,fish",
+        "activewindowv2>>a2",
+        "movewindow>>a2,2",
+        "windowtitle>>a2",
+        "windowtitlev2>>a2,vim",
+        "windowtitle>>a2",
+        "windowtitlev2>>a2,vim README.md",
+        "closewindow>>a1",
+        "activewindowv2>>,",
+      ],
+    );
+    let hyprland = fake.connect();
+    assert_eq!(fake.requests("j/clients"), 1);
+    *fake.clients.lock().unwrap() = serde_json::json!([
+      client("0xa1", "firefox", "Mozilla Firefox", 0),
+      client("0xa2", "kitty", "fish", 800),
+    ]);
+
+    assert_eq!(
+      subscribe(&hyprland),
+      [
+        WindowEvent::Created(0xa2),
+        WindowEvent::Foreground(0xa2),
+        WindowEvent::Shown(0xa2),
+        WindowEvent::NameChanged(0xa2),
+        WindowEvent::NameChanged(0xa2),
+        WindowEvent::NameChanged(0xa2),
+        WindowEvent::Destroyed(0xa1),
+      ]
+    );
+    // The title comes from the event, the rest from the one query per
+    // openwindow, movewindow and windowtitle (before a v2 was seen).
+    let kitty = hyprland.describe(0xa2).unwrap();
+    assert_eq!((kitty.title.as_str(), kitty.pid), ("vim README.md", 4242));
+    assert_eq!(hyprland.bounds(0xa2).unwrap().left, 800);
+    assert_eq!(fake.requests("j/clients"), 4);
+    assert_eq!(fake.requests("j/activewindow"), 1);
+    assert!(!hyprland.is_alive(0xa1));
+    assert_eq!(hyprland.foreground(), None);
+  }
+}
@@ -0,0 +1,426 @@
+use serde_json::Value;
+use std::io::Read;
+
+use crate::tiling::{TilingSnapshot, WindowTiling};
+
+/// The named pipe komorebi sends its notifications to, `\\.\pipe\cute-borders`.
+pub const PIPE_NAME: &str = "cute-borders";
+
+/// Creates the pipe, asks komorebi to subscribe it and calls `on_snapshot`
+/// for every notification until komorebi goes away or `on_snapshot`
+/// returns false.
+#[cfg(windows)]
+pub fn subscribe(on_snapshot: &mut dyn FnMut(TilingSnapshot) -> bool) -> Result<(), String> {
+  use std::ffi::OsStr;
+  use std::fs::File;
+  use std::io::{self, BufReader};
+  use std::os::windows::ffi::OsStrExt;
+  use std::os::windows::io::FromRawHandle;
+  use std::os::windows::process::CommandExt;
+  use std::process::Command;
+  use std::ptr;
+  use winapi::shared::winerror::ERROR_PIPE_CONNECTED;
+  use winapi::um::handleapi::INVALID_HANDLE_VALUE;
+  use winapi::um::namedpipeapi::{ConnectNamedPipe, CreateNamedPipeW};
+  use winapi::um::winbase::{
+    CREATE_NO_WINDOW, PIPE_ACCESS_INBOUND, PIPE_READMODE_BYTE, PIPE_TYPE_BYTE, PIPE_WAIT,
+  };
+
+  // A notification carries the whole komorebi state, which can be large.
+  const BUFFER_SIZE: u32 = 64 * 1024;
+
+  let name: Vec<u16> = OsStr::new(&format!(r"\\.\pipe\{}", PIPE_NAME))
+    .encode_wide()
+    .chain(std::iter::once(0))
+    .collect();
+  let pipe = unsafe {
+    CreateNamedPipeW(
+      name.as_ptr(),
+      PIPE_ACCESS_INBOUND,
+      PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT,
+      1,
+      0,
+      BUFFER_SIZE,
+      0,
+      ptr::null_mut(),
+    )
+  };
+  if pipe == INVALID_HANDLE_VALUE {
+    return Err(format!(
+      "failed to create the pipe: {}",
+      io::Error::last_os_error()
+    ));
+  }
+  // Closes the pipe when dropped.
+  let file = unsafe { File::from_raw_handle(pipe as _) };
+
+  // komorebi connects to the pipes of its subscribers itself.
+  let status = Command::new("komorebic")
+    .args(["subscribe-pipe", PIPE_NAME])
+    .creation_flags(CREATE_NO_WINDOW)
+    .status()
+    .map_err(|e| format!("failed to run komorebic: {}", e))?;
+  if !status.success() {
+    return Err(format!(
+      "komorebic subscribe-pipe failed ({}), is komorebi running?",
+      status
+    ));
+  }
+  if unsafe { ConnectNamedPipe(pipe, ptr::null_mut()) } == 0 {
+    let e = io::Error::last_os_error();
+    // komorebi was faster than us, which is fine.
+    if e.raw_os_error() != Some(ERROR_PIPE_CONNECTED as i32) {
+      return Err(format!("komorebi didn't connect to the pipe: {}", e));
+    }
+  }
+
+  read_notifications(BufReader::new(file), on_snapshot)
+}
+
+#[cfg(not(windows))]
+pub fn subscribe(_on_snapshot: &mut dyn FnMut(TilingSnapshot) -> bool) -> Result<(), String> {
+  Err("komorebi only runs on Windows".to_string())
+}
+
+/// Reads notifications, JSON objects one after the other, from a pipe or
+/// anything else and calls `on_snapshot` for each until the stream ends
+/// or `on_snapshot` returns false.
+pub fn read_notifications(
+  reader: impl Read,
+  on_snapshot: &mut dyn FnMut(TilingSnapshot) -> bool,
+) -> Result<(), String> {
+  for notification in serde_json::Deserializer::from_reader(reader).into_iter::<Value>() {
+    let notification = notification.map_err(|e| format!("invalid notification: {}", e))?;
+    if !on_snapshot(parse_notification(&notification)?) {
+      break;
+    }
+  }
+  Ok(())
+}
+
+/// A notification is `{"event": ..., "state": ...}`, only the state matters.
+pub fn parse_notification(notification: &Value) -> Result<TilingSnapshot, String> {
+  parse_state(&notification["state"])
+}
+
+/// Reads workspaces, layouts and window states out of komorebi's state.
+pub fn parse_state(state: &Value) -> Result<TilingSnapshot, String> {
+  let (monitors, focused_monitor) = ring(&state["monitors"]).ok_or("the state has no monitors")?;
+  let mut snapshot = TilingSnapshot::default();
+
+  for (monitor_index, monitor) in monitors.iter().enumerate() {
+    let Some((workspaces, focused_workspace)) = ring(&monitor["workspaces"]) else {
+      continue;
+    };
+    for (index, workspace) in workspaces.iter().enumerate() {
+      let tiling = |states: Vec<&'static str>| WindowTiling {
+        workspace_index: index,
+        workspace_name: workspace["name"]
+          .as_str()
+          .map_or_else(|| index.to_string(), String::from),
+        layout: layout_name(&workspace["layout"]),
+        states,
+      };
+
+      for container in ring(&workspace["containers"]).map_or(&[][..], |(containers, _)| containers)
+      {
+        let windows = windows_of(container);
+        let state = if windows.len() > 1 {
+          "stacked"
+        } else {
+          "tiled"
+        };
+        for window in windows {
+          snapshot.windows.insert(window, tiling(vec![state]));
+        }
+      }
+      let monocle = windows_of(&workspace["monocle_container"]);
+      let monocle_states = if monocle.len() > 1 {
+        vec!["monocle", "stacked"]
+      } else {
+        vec!["monocle"]
+      };
+      for &window in &monocle {
+        snapshot
+          .windows
+          .insert(window, tiling(monocle_states.clone()));
+      }
+      if let Some(window) = hwnd(&workspace["maximized_window"]) {
+        snapshot.windows.insert(window, tiling(vec!["maximized"]));
+      }
+      for window in ring(&workspace["floating_windows"]).map_or(&[][..], |(windows, _)| windows) {
+        if let Some(window) = hwnd(window) {
+          snapshot.windows.insert(window, tiling(vec!["floating"]));
+        }
+      }
+
+      if Some(monitor_index) == focused_monitor && Some(index) == focused_workspace {
+        snapshot.focused = focused_window(workspace);
+      }
+    }
+  }
+  Ok(snapshot)
+}
+
+/// The window komorebi considers focused on a workspace.
+fn focused_window(workspace: &Value) -> Option<usize> {
+  if workspace["layer"] == "Floating" {
+    let (windows, focused) = ring(&workspace["floating_windows"])?;
+    return hwnd(windows.get(focused?)?);
+  }
+  if !workspace["monocle_container"].is_null() {
+    return focused_window_of(&workspace["monocle_container"]);
+  }
+  if let Some(window) = hwnd(&workspace["maximized_window"]) {
+    return Some(window);
+  }
+  let (containers, focused) = ring(&workspace["containers"])?;
+  focused_window_of(containers.get(focused?)?)
+}
+
+fn focused_window_of(container: &Value) -> Option<usize> {
+  let (windows, focused) = ring(&container["windows"])?;
+  hwnd(windows.get(focused.unwrap_or(0))?)
+}
+
+fn windows_of(container: &Value) -> Vec<usize> {
+  ring(&container["windows"]).map_or_else(Vec::new, |(windows, _)| {
+    windows.iter().filter_map(hwnd).collect()
+  })
+}
+
+/// komorebi keeps most lists in a `Ring`, `{"elements": [...], "focused": n}`.
+/// Older versions send some of them as plain arrays, without focus.
+fn ring(value: &Value) -> Option<(&[Value], Option<usize>)> {
+  match value {
+    Value::Array(elements) => Some((elements, None)),
+    Value::Object(ring) => {
+      let elements = ring.get("elements")?.as_array()?;
+      let focused = ring
+        .get("focused")
+        .and_then(Value::as_u64)
+        .map(|focused| focused as usize);
+      Some((elements, focused))
+    }
+    _ => None,
+  }
+}
+
+fn hwnd(window: &Value) -> Option<usize> {
+  window["hwnd"].as_u64().map(|hwnd| hwnd as usize)
+}
+
+/// `{"Default": "BSP"}` for the built-in layouts, `{"Custom": ...}` otherwise.
+fn layout_name(layout: &Value) -> String {
+  match layout {
+    Value::String(name) => name.clone(),
+    Value::Object(layout) => match layout.get("Default") {
+      Some(Value::String(name)) => name.clone(),
+      _ => layout.keys().next().cloned().unwrap_or_default(),
+    },
+    _ => String::new(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::Config;
+  use crate::rules::{RuleSet, WindowInfo};
+  use serde_json::json;
+
+  fn window(hwnd: u64, title: &str) -> Value {
+    json!({ "hwnd": hwnd, "title": title, "exe": "app.exe", "class": "AppWindow" })
+  }
+
+  /// Trimmed down from what komorebi 0.1.30 sends, with a workspace on
+  /// the focused monitor focused.
+  fn state(focused_workspace: usize) -> Value {
+    json!({
+      "monitors": {
+        "elements": [
+          {
+            "workspaces": {
+              "elements": [
+                {
+                  "name": "code",
+                  "layout": { "Default": "BSP" },
+                  "layer": "Tiling",
+                  "containers": {
+                    "elements": [
+                      { "windows": { "elements": [window(1, "editor")], "focused": 0 } },
+                      {
+                        "windows": {
+                          "elements": [window(2, "terminal"), window(3, "logs")],
+                          "focused": 1
+                        }
+                      }
+                    ],
+                    "focused": 1
+                  },
+                  "monocle_container": null,
+                  "maximized_window": null,
+                  "floating_windows": [window(4, "picker")]
+                },
+                {
+                  "name": null,
+                  "layout": { "Custom": { "columns": [] } },
+                  "layer": "Tiling",
+                  "containers": [],
+                  "monocle_container": {
+                    "windows": { "elements": [window(5, "browser"), window(6, "docs")], "focused": 1 }
+                  },
+                  "maximized_window": window(7, "video"),
+                  "floating_windows": []
+                },
+                {
+                  "name": "chat",
+                  "layout": { "Default": "Columns" },
+                  "layer": "Floating",
+                  "containers": { "elements": [{ "windows": [window(8, "mail")] }], "focused": 0 },
+                  "monocle_container": null,
+                  "maximized_window": null,
+                  "floating_windows": {
+                    "elements": [window(9, "chat"), window(10, "call")],
+                    "focused": 1
+                  }
+                }
+              ],
+              "focused": focused_workspace
+            }
+          },
+          { "workspaces": { "elements": [], "focused": 0 } }
+        ],
+        "focused": 0
+      }
+    })
+  }
+
+  fn tiling(index: usize, name: &str, layout: &str, states: Vec<&'static str>) -> WindowTiling {
+    WindowTiling {
+      workspace_index: index,
+      workspace_name: name.to_string(),
+      layout: layout.to_string(),
+      states,
+    }
+  }
+
+  #[test]
+  fn reads_rings_and_plain_arrays() {
+    let snapshot = parse_state(&state(0)).unwrap();
+    assert_eq!(snapshot.windows.len(), 10);
+    assert_eq!(
+      snapshot.windows[&1],
+      tiling(0, "code", "BSP", vec!["tiled"])
+    );
+    assert_eq!(
+      snapshot.windows[&2],
+      tiling(0, "code", "BSP", vec!["stacked"])
+    );
+    assert_eq!(
+      snapshot.windows[&4],
+      tiling(0, "code", "BSP", vec!["floating"])
+    );
+    // A plain array of windows in a ring of containers.
+    assert_eq!(
+      snapshot.windows[&8],
+      tiling(2, "chat", "Columns", vec!["tiled"])
+    );
+    assert_eq!(
+      snapshot.windows[&9],
+      tiling(2, "chat", "Columns", vec!["floating"])
+    );
+    // The focused window of the focused container.
+    assert_eq!(snapshot.focused, Some(3));
+  }
+
+  #[test]
+  fn monocle_and_maximized_windows() {
+    let snapshot = parse_state(&state(1)).unwrap();
+    // Unnamed workspaces go by their index, custom layouts by their kind.
+    let monocle = tiling(1, "1", "Custom", vec!["monocle", "stacked"]);
+    assert_eq!(snapshot.windows[&5], monocle);
+    assert_eq!(snapshot.windows[&6], monocle);
+    assert_eq!(
+      snapshot.windows[&7],
+      tiling(1, "1", "Custom", vec!["maximized"])
+    );
+    // The monocle container wins over the maximized window.
+    assert_eq!(snapshot.focused, Some(6));
+  }
+
+  #[test]
+  fn floating_layer_focuses_a_floating_window() {
+    let snapshot = parse_state(&state(2)).unwrap();
+    assert_eq!(snapshot.focused, Some(10));
+  }
+
+  #[test]
+  fn a_state_without_monitors_is_an_error() {
+    assert!(parse_state(&json!({})).is_err());
+  }
+
+  #[test]
+  fn reads_notifications_until_told_to_stop() {
+    let notification = |workspace| {
+      json!({ "event": { "type": "FocusChange" }, "state": state(workspace) }).to_string()
+    };
+    let stream = format!(
+      "{}\n{}{}",
+      notification(0),
+      notification(1),
+      notification(2)
+    );
+
+    let mut focused = Vec::new();
+    read_notifications(stream.as_bytes(), &mut |snapshot| {
+      focused.push(snapshot.focused);
+      true
+    })
+    .unwrap();
+    assert_eq!(focused, [Some(3), Some(6), Some(10)]);
+
+    let mut count = 0;
+    read_notifications(stream.as_bytes(), &mut |_| {
+      count += 1;
+      count < 2
+    })
+    .unwrap();
+    assert_eq!(count, 2);
+
+    let truncated = &stream[..stream.len() - 10];
+    assert!(read_notifications(truncated.as_bytes(), &mut |_| true).is_err());
+  }
+
+  #[test]
+  fn tiling_rules_match_the_snapshot() {
+    let rules = RuleSet::compile(
+      &Config::from_yaml(
+        "window_rules:
+  - Match: State
+    equals: monocle
+    active_border_color: red
+  - Match: Workspace
+    equals: code
+    active_border_color: green
+  - Match: Layout
+    contains: custom
+    active_border_color: blue
+",
+      )
+      .unwrap(),
+    )
+    .unwrap();
+    let snapshot = parse_state(&state(0)).unwrap();
+    let rule_for = |hwnd: usize| {
+      let window = WindowInfo::new("", "", 0).with_tiling(snapshot.windows.get(&hwnd));
+      rules.rule_for(&window).map(|rule| rules.config_index(rule))
+    };
+    assert_eq!(rule_for(1), Some(1));
+    assert_eq!(rule_for(5), Some(0));
+    assert_eq!(rule_for(7), Some(2));
+    assert_eq!(rule_for(9), None);
+    // Unmanaged windows.
+    assert_eq!(rule_for(42), None);
+  }
+}
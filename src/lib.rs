@@ -0,0 +1,79 @@
+//! The core of cute-borders: the config model, rule evaluation, color
+//! parsing and animations, without the tray icon, settings window and
+//! startup task of the app.
+//!
+//! Evaluating rules against a window:
+//!
+//! ```
+//! use cute_borders::config::Config;
+//! use cute_borders::rules::{RuleSet, WindowInfo};
+//!
+//! let config = Config::from_yaml(
+//!   r##"
+//! window_rules:
+//!   - match: Global
+//!     active_border_color: "#c6a0f6"
+//!   - match: Title
+//!     contains: Firefox
+//!     active_border_color: "#ff0000"
+//!     inactive_border_color: transparent
+//! "##,
+//! )
+//! .unwrap();
+//! let rules = RuleSet::compile(&config).unwrap();
+//!
+//! let window = WindowInfo::new("Mozilla Firefox", "MozillaWindowClass", 0);
+//! let (active, inactive) = rules.colors_for(&window).unwrap();
+//! assert_eq!(active, 0x0000ff); // COLORREF, 0x00bbggrr
+//! assert_eq!(inactive, cute_borders::DWMWA_COLOR_NONE);
+//! ```
+//!
+//! Colors can be parsed on their own with [`color::ColorSpec`] and
+//! [`css::parse`]. Windows and focus come from a [`backend::WindowBackend`];
+//! [`backend::MockBackend`] is an in-memory one for scripts and tests.
+//!
+//! Rules that can never match are listed by [`rules::RuleSet::errors`].
+//! Nothing is written to `~/.cuteborders` unless asked to:
+//! [`logger::Logger::log_to_file`] is what the app calls to get its
+//! log.txt, and a library user can call it too.
+//!
+//! The `app` feature is only used by the app (its settings window, and
+//! restoring the border colors on a signal off Windows), and
+//! `glazewm` adds the GlazeWM client (and tungstenite for its WebSocket).
+//! Both are on by default, a library user can turn them off with
+//! `default-features = false`.
+
+pub mod accent;
+pub mod animation;
+pub mod backend;
+pub mod border;
+pub mod color;
+pub mod config;
+pub mod css;
+pub mod eval;
+pub mod focus;
+pub mod frame;
+#[cfg(feature = "glazewm")]
+pub mod glazewm;
+#[cfg(unix)]
+pub mod hyprland;
+pub mod komorebi;
+pub mod lint;
+pub mod logger;
+pub mod paint;
+pub mod process;
+pub mod rainbow;
+pub mod registry;
+pub mod rules;
+#[cfg(unix)]
+pub mod sway;
+pub mod tiling;
+pub mod transition;
+pub mod util;
+#[cfg(windows)]
+mod win32;
+
+/// The border color that tells DWM to draw its default border.
+pub const DWMWA_COLOR_DEFAULT: u32 = 0xFFFFFFFF;
+/// The border color that tells DWM to draw no border at all.
+pub const DWMWA_COLOR_NONE: u32 = 0xFFFFFFFE;
@@ -0,0 +1,472 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::fmt;
+use std::path::Path;
+
+use crate::color::{resolve_palette, ColorSpec};
+use crate::config::{Condition, Config, Matcher, Rule, RuleMatch};
+use crate::rules::{alpha_backdrop, finish_color};
+
+const COLOR_FIELDS: [&str; 2] = ["active_border_color", "inactive_border_color"];
+
+/// A problem in `window_rules` found without looking at any window.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+  /// Index into `window_rules`.
+  pub rule: usize,
+  pub kind: FindingKind,
+  pub message: String,
+  /// What to change to get rid of it.
+  pub fix: String,
+  /// The earlier rule that causes it, for `unreachable`, `duplicate` and
+  /// `overridden_global`.
+  pub related: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+  /// An earlier rule with a broader `contains` matches every window this one would.
+  Unreachable,
+  /// The same rule as an earlier one.
+  Duplicate,
+  /// A Global rule before the last one, which replaces it for every window.
+  OverriddenGlobal,
+  /// `contains: ""` matches everything, `contains: []` nothing.
+  EmptyContains,
+  /// Neither `Match` nor `when`, only colors.
+  NoCondition,
+  InvalidColor,
+  /// A regex that doesn't compile, which keeps the config from loading.
+  InvalidRegex,
+  /// A rule that can't be read, e.g. a misspelled field or `contains` without `Match`.
+  InvalidRule,
+}
+
+/// Lints a config in the format of config.yaml. Errors are for configs
+/// that can't be read at all, problems in single rules are findings.
+pub fn lint(yaml: &str) -> Result<Vec<Finding>, String> {
+  let mut value: Value = serde_yaml::from_str(yaml).map_err(|e| e.to_string())?;
+
+  // Invalid colors and rules would fail the whole config, so they are
+  // checked on their own first. Colors are replaced with `default`, rules
+  // with one that only has colors, whose own findings are dropped.
+  let mut findings = Vec::new();
+  let mut unreadable = Vec::new();
+  if let Some(Value::Sequence(rules)) = value.get_mut("window_rules") {
+    for (index, rule) in rules.iter_mut().enumerate() {
+      for field in COLOR_FIELDS {
+        let Some(color) = rule.get_mut(field) else {
+          continue;
+        };
+        if let Err(e) = ColorSpec::deserialize(color.clone()) {
+          findings.push(invalid_color(index, field, e.to_string(), COLOR_FIX));
+          *color = Value::String("default".to_string());
+        }
+      }
+      if let Err(e) = Rule::deserialize(rule.clone()) {
+        findings.push(Finding {
+          rule: index,
+          kind: FindingKind::InvalidRule,
+          message: e.to_string(),
+          fix: RULE_FIX.to_string(),
+          related: None,
+        });
+        let mut placeholder = Mapping::new();
+        placeholder.insert("active_border_color".into(), "default".into());
+        *rule = Value::Mapping(placeholder);
+        unreadable.push(index);
+      }
+    }
+  }
+  let config: Config = serde_yaml::from_value(value).map_err(|e| e.to_string())?;
+
+  let palette = resolve_palette(&config.palette)?;
+  let backdrop = alpha_backdrop(&config)?;
+  for (index, rule) in config.window_rules.iter().enumerate() {
+    for (field, color) in COLOR_FIELDS
+      .into_iter()
+      .zip([&rule.active_border_color, &rule.inactive_border_color])
+    {
+      let error = match color.lookup(&palette) {
+        Err(e) => Some((e, "define it under palette or fix the name")),
+        Ok(color) => finish_color(color, backdrop)
+          .err()
+          .map(|e| (e, "set alpha_backdrop or drop the alpha channel")),
+      };
+      if let Some((e, fix)) = error {
+        findings.push(invalid_color(index, field, e, fix));
+      }
+    }
+  }
+
+  findings.extend(
+    lint_rules(&config.window_rules)
+      .into_iter()
+      .filter(|finding| !unreadable.contains(&finding.rule)),
+  );
+  findings.sort_by_key(|finding| finding.rule);
+  Ok(findings)
+}
+
+pub fn lint_file(path: &Path) -> Result<Vec<Finding>, String> {
+  let yaml = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+  lint(&yaml).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+const COLOR_FIX: &str =
+  "use a color like #c6a0f6 or rgb(198 160 246), accent, rainbow, default, transparent or a $palette name";
+
+const RULE_FIX: &str =
+  "check the field names, a rule has Match with contains, equals, glob or regex, a when condition, or both";
+
+fn invalid_color(rule: usize, field: &str, error: String, fix: &str) -> Finding {
+  Finding {
+    rule,
+    kind: FindingKind::InvalidColor,
+    message: format!("{}: {}", field, error),
+    fix: fix.to_string(),
+    related: None,
+  }
+}
+
+/// The checks that only need the rules, in config order.
+pub fn lint_rules(rules: &[Rule]) -> Vec<Finding> {
+  let mut findings = Vec::new();
+  let serialized: Vec<Option<Value>> = rules
+    .iter()
+    .map(|rule| serde_yaml::to_value(rule).ok())
+    .collect();
+  let last_global = rules.iter().rposition(Rule::is_global);
+
+  for (index, rule) in rules.iter().enumerate() {
+    let finding = |kind, message: String, fix: String, related| Finding {
+      rule: index,
+      kind,
+      message,
+      fix,
+      related,
+    };
+
+    if rule.matcher.is_none() && rule.when.is_none() {
+      findings.push(finding(
+        FindingKind::NoCondition,
+        "has only colors, without Match or when it never matches".to_string(),
+        "add a Match or a when condition, or remove the rule".to_string(),
+        None,
+      ));
+    }
+    for_each_matcher(rule, &mut |matcher| {
+      if let Some((message, fix)) = empty_contains(matcher) {
+        findings.push(finding(FindingKind::EmptyContains, message, fix, None));
+      }
+      if let Some((message, fix)) = invalid_regex(matcher) {
+        findings.push(finding(FindingKind::InvalidRegex, message, fix, None));
+      }
+    });
+
+    let duplicate = (0..index)
+      .find(|&earlier| serialized[earlier].is_some() && serialized[earlier] == serialized[index]);
+    if let Some(earlier) = duplicate {
+      findings.push(finding(
+        FindingKind::Duplicate,
+        format!("is the same as window_rules[{}]", earlier),
+        format!("remove window_rules[{}]", index),
+        Some(earlier),
+      ));
+      continue;
+    }
+
+    if rule.is_global() {
+      if last_global != Some(index) {
+        let last = last_global.unwrap_or(index);
+        findings.push(finding(
+          FindingKind::OverriddenGlobal,
+          format!(
+            "is a Global rule, but window_rules[{}] is another one and replaces it for every window",
+            last
+          ),
+          format!(
+            "keep a single Global rule: move the colors you want into window_rules[{}] and remove window_rules[{}]",
+            last, index
+          ),
+          Some(last),
+        ));
+      }
+      continue;
+    }
+
+    for (earlier, earlier_rule) in rules.iter().enumerate().take(index) {
+      if let Some(reason) = shadows(earlier_rule, rule) {
+        findings.push(finding(
+          FindingKind::Unreachable,
+          format!(
+            "never applies, window_rules[{}] matches every window it does first ({})",
+            earlier, reason
+          ),
+          format!(
+            "move window_rules[{}] above window_rules[{}], or narrow or remove it",
+            index, earlier
+          ),
+          Some(earlier),
+        ));
+        break;
+      }
+    }
+  }
+  findings
+}
+
+/// The flat matcher and every leaf of `when`.
+fn for_each_matcher(rule: &Rule, f: &mut dyn FnMut(&Matcher)) {
+  fn visit(condition: &Condition, f: &mut dyn FnMut(&Matcher)) {
+    match condition {
+      Condition::All(conditions) | Condition::Any(conditions) => {
+        conditions.iter().for_each(|c| visit(c, f))
+      }
+      Condition::Not(condition) => visit(condition, f),
+      Condition::Leaf(matcher) => f(matcher),
+    }
+  }
+  if let Some(matcher) = &rule.matcher {
+    f(matcher);
+  }
+  if let Some(when) = &rule.when {
+    visit(when, f);
+  }
+}
+
+fn empty_contains(matcher: &Matcher) -> Option<(String, String)> {
+  let needles: Vec<&String> = matcher.contains.iter().flat_map(|c| c.iter()).collect();
+  if matcher.contains.is_some() && needles.is_empty() {
+    let has_other = matcher.equals.is_some() || matcher.glob.is_some() || matcher.regex.is_some();
+    let message = if has_other {
+      format!(
+        "{:?} has an empty contains list, which is ignored",
+        matcher.rule_match
+      )
+    } else {
+      format!(
+        "{:?} has an empty contains list, it never matches",
+        matcher.rule_match
+      )
+    };
+    return Some((
+      message,
+      "add the text to look for, or remove contains".to_string(),
+    ));
+  }
+  if needles.iter().any(|needle| needle.is_empty()) {
+    return Some((
+      format!(
+        "{:?} contains \"\", which is part of every text, so it matches every window",
+        matcher.rule_match
+      ),
+      "remove the empty entry, or use Match: Global if that's intended".to_string(),
+    ));
+  }
+  None
+}
+
+/// Regex syntax errors quote the pattern with a marker under it over
+/// several lines, only the last one says what's wrong.
+fn invalid_regex(matcher: &Matcher) -> Option<(String, String)> {
+  let pattern = matcher.regex.as_ref()?;
+  let error = Regex::new(pattern).err()?.to_string();
+  let reason = error.lines().last().unwrap_or_default();
+  Some((
+    format!(
+      "{:?} regex {:?} is invalid ({}), the config won't load",
+      matcher.rule_match,
+      pattern,
+      reason.trim_start_matches("error: ")
+    ),
+    "fix the regex, or use contains or glob for plain text".to_string(),
+  ))
+}
+
+/// Why every window `later` matches is also matched by `earlier`, if it is.
+/// Only `earlier` rules with nothing but `contains` can be compared: a
+/// window that `later` matches has one of its `contains` or its `equals`
+/// in the text, and each of those has to include one of `earlier`'s.
+fn shadows(earlier: &Rule, later: &Rule) -> Option<String> {
+  if earlier.when.is_some() || earlier.is_global() {
+    return None;
+  }
+  let earlier = earlier.matcher.as_ref()?;
+  let later = later.matcher.as_ref()?;
+  if earlier.rule_match != later.rule_match
+    || earlier.rule_match == RuleMatch::Global
+    || earlier.equals.is_some()
+    || earlier.glob.is_some()
+    || earlier.regex.is_some()
+  {
+    return None;
+  }
+  let broader: Vec<&String> = earlier.contains.iter().flat_map(|c| c.iter()).collect();
+  let covered = |text: &String| {
+    let text_lower = text.to_lowercase();
+    let needle = broader
+      .iter()
+      .find(|needle| text_lower.contains(needle.to_lowercase().as_str()))?;
+    Some(format!("{:?} contains {:?}", text, needle))
+  };
+
+  if let Some(reason) = later.equals.as_ref().and_then(covered) {
+    return Some(format!("{:?} {}", later.rule_match, reason));
+  }
+  let narrower: Vec<&String> = later.contains.iter().flat_map(|c| c.iter()).collect();
+  if narrower.is_empty() {
+    return None;
+  }
+  let reasons = narrower
+    .into_iter()
+    .map(covered)
+    .collect::<Option<Vec<_>>>()?;
+  Some(format!("{:?} {}", later.rule_match, reasons.join(", ")))
+}
+
+/// Each finding with its fix below, then a count.
+pub fn format_findings(findings: &[Finding]) -> String {
+  let mut text = String::new();
+  for finding in findings {
+    text.push_str(&format!("{}\n", finding));
+  }
+  match findings.len() {
+    0 => text.push_str("no problems found\n"),
+    1 => text.push_str("1 problem\n"),
+    n => text.push_str(&format!("{} problems\n", n)),
+  }
+  text
+}
+
+impl fmt::Display for FindingKind {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      FindingKind::Unreachable => write!(f, "unreachable"),
+      FindingKind::Duplicate => write!(f, "duplicate"),
+      FindingKind::OverriddenGlobal => write!(f, "overridden global"),
+      FindingKind::EmptyContains => write!(f, "empty contains"),
+      FindingKind::NoCondition => write!(f, "no condition"),
+      FindingKind::InvalidColor => write!(f, "invalid color"),
+      FindingKind::InvalidRegex => write!(f, "invalid regex"),
+      FindingKind::InvalidRule => write!(f, "invalid rule"),
+    }
+  }
+}
+
+impl fmt::Display for Finding {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(
+      f,
+      "window_rules[{}] {}: {}",
+      self.rule, self.kind, self.message
+    )?;
+    write!(f, "  fix: {}", self.fix)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn kinds(yaml: &str) -> Vec<(usize, FindingKind)> {
+    lint(yaml)
+      .unwrap()
+      .into_iter()
+      .map(|finding| (finding.rule, finding.kind))
+      .collect()
+  }
+
+  #[test]
+  fn clean_rules_have_no_findings() {
+    let yaml = "window_rules:
+  - Match: Title
+    regex: '^vim'
+    active_border_color: red
+  - Match: Global
+    active_border_color: blue
+";
+    assert_eq!(kinds(yaml), []);
+  }
+
+  #[test]
+  fn unreadable_rules_are_findings() {
+    let yaml = "window_rules:
+  - Matc: Title
+    contains: vim
+    active_border_color: red
+  - contains: code
+    active_border_color: red
+  - active_border_color: red
+  - Match: Title
+    contains: vim
+    active_border_color: red
+";
+    assert_eq!(
+      kinds(yaml),
+      [
+        (0, FindingKind::InvalidRule),
+        (1, FindingKind::InvalidRule),
+        (2, FindingKind::NoCondition)
+      ]
+    );
+    let findings = lint(yaml).unwrap();
+    assert!(findings[0].message.contains("unknown field `Matc`"));
+    assert_eq!(
+      findings[1].message,
+      "contains, equals, glob and regex need a Match"
+    );
+  }
+
+  #[test]
+  fn invalid_regexes_are_findings() {
+    let yaml = "window_rules:
+  - Match: Title
+    regex: '('
+    active_border_color: red
+  - when:
+      not:
+        Match: Class
+        regex: '[a-'
+    active_border_color: red
+";
+    let findings = lint(yaml).unwrap();
+    assert_eq!(
+      kinds(yaml),
+      [
+        (0, FindingKind::InvalidRegex),
+        (1, FindingKind::InvalidRegex)
+      ]
+    );
+    assert_eq!(
+      findings[0].message,
+      "Title regex \"(\" is invalid (unclosed group), the config won't load"
+    );
+  }
+
+  #[test]
+  fn bad_colors_and_rules_are_both_reported() {
+    let yaml = "window_rules:
+  - Match: Title
+    contains: vim
+    colour: red
+    active_border_color: '#zzzzzz'
+  - Match: Title
+    contains: vi
+    active_border_color: red
+  - Match: Title
+    contains: vim
+    active_border_color: red
+";
+    assert_eq!(
+      kinds(yaml),
+      [
+        (0, FindingKind::InvalidColor),
+        (0, FindingKind::InvalidRule),
+        (2, FindingKind::Unreachable)
+      ]
+    );
+  }
+}
@@ -1,27 +1,59 @@
 use crate::util::get_file;
 use lazy_static::lazy_static;
-use std::{io::Write, sync::Mutex};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::{io, io::Write, sync::Mutex};
+
+static EXPLAIN: AtomicBool = AtomicBool::new(false);
 
 lazy_static! {
-  static ref LOGGER: Mutex<Logger> = Mutex::new(Logger::new().unwrap());
+  static ref LOGGER: Mutex<Logger> = Mutex::new(Logger {
+    file: None,
+    last_message: None,
+  });
+}
+
+/// How much goes into log.txt.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+  #[default]
+  Info,
+  /// Also why each window got its rule and border color, as `[EXPLAIN]` lines.
+  Explain,
 }
 
+/// Writes to `~/.cuteborders/log.txt` once the app has called
+/// `log_to_file`. Until then, e.g. in a library user or a test, messages
+/// go nowhere.
 pub struct Logger {
-  file: std::fs::File,
+  file: Option<std::fs::File>,
   last_message: Option<String>,
 }
 
 impl Logger {
-  fn new() -> Result<Self, std::io::Error> {
-    let file = get_file("log.txt", "");
-    Ok(Logger {
-      file,
-      last_message: None,
-    })
+  /// Sends every message from now on to `~/.cuteborders/log.txt`.
+  pub fn log_to_file() -> io::Result<()> {
+    let file = get_file("log.txt", "")?;
+    LOGGER.lock().unwrap().file = Some(file);
+    Ok(())
+  }
+
+  pub fn set_level(level: LogLevel) {
+    EXPLAIN.store(level == LogLevel::Explain, Ordering::Relaxed);
+  }
+
+  /// Whether `[EXPLAIN]` lines should be logged. Check it before building them.
+  pub fn explains() -> bool {
+    EXPLAIN.load(Ordering::Relaxed)
   }
 
   pub fn log(message: &str) {
     let mut logger = LOGGER.lock().unwrap();
+    let logger = &mut *logger;
+    let Some(file) = &mut logger.file else {
+      return;
+    };
 
     if let Some(ref last_message) = logger.last_message {
       if last_message == message {
@@ -30,11 +62,10 @@ impl Logger {
     }
 
     let formatted_message = format!("{}\n", message);
-    logger
-      .file
+    file
       .write_all(formatted_message.as_bytes())
       .expect("Failed to write to log");
-    logger.file.flush().expect("Failed to flush log");
+    file.flush().expect("Failed to flush log");
 
     logger.last_message = Some(message.to_string());
   }
@@ -4,190 +4,153 @@
 #![allow(unused_assignments)]
 
 // --- Importações ---
+#[cfg(windows)]
 use check_elevation::is_elevated;
+#[cfg(windows)]
 use tray_icon::menu::{Menu, MenuEvent, MenuId, MenuItemBuilder};
+#[cfg(windows)]
 use tray_icon::{Icon, TrayIconBuilder};
-use winapi::ctypes::{c_int, c_void};
-use winapi::shared::minwindef::{BOOL, DWORD, LPARAM};
-use winapi::shared::windef::{HWND, HWINEVENTHOOK};
-use winapi::um::dwmapi::DwmSetWindowAttribute;
+#[cfg(windows)]
+use winapi::shared::minwindef::DWORD;
+#[cfg(windows)]
 use winapi::um::processthreadsapi::{OpenProcess, TerminateProcess};
+#[cfg(windows)]
 use winapi::um::shellapi::{ShellExecuteExW, SEE_MASK_NOASYNC, SEE_MASK_NOCLOSEPROCESS, SHELLEXECUTEINFOW};
+#[cfg(windows)]
 use winapi::um::winnt::PROCESS_TERMINATE;
-use winapi::um::winuser::{
-    EnumWindows, GetClassNameW, GetForegroundWindow, GetMessageW, GetWindowTextLengthW,
-    GetWindowTextW, GetWindowThreadProcessId, IsWindow, IsWindowVisible, PostQuitMessage,
-    TranslateMessage, DispatchMessageW, GWL_EXSTYLE, WS_EX_TOOLWINDOW, SetWinEventHook, UnhookWinEvent,
-    EVENT_SYSTEM_FOREGROUND, WINEVENT_OUTOFCONTEXT, GetWindow, GW_OWNER,
-};
-use std::ffi::{c_ulong, OsStr, OsString};
+#[cfg(windows)]
+use winapi::um::winuser::{GetMessageW, PostQuitMessage, TranslateMessage, DispatchMessageW};
+#[cfg(windows)]
+use std::ffi::OsStr;
+#[cfg(windows)]
 use std::os::windows::ffi::OsStrExt;
-use std::os::windows::prelude::OsStringExt;
-use std::ptr;
-use std::sync::Mutex;
-use std::time::Duration;
-use std::mem;
+#[cfg(windows)]
+use std::{mem, ptr, sync::Mutex};
 
 // --- Módulos Internos ---
-mod config;
-mod logger;
-mod rainbow;
-mod util;
+// O núcleo (regras, cores, animações) fica na biblioteca, aqui só a bandeja, a GUI e a tarefa de inicialização.
+mod cli;
 mod gui;
+#[cfg(windows)]
+mod startup;
 
 // --- Importações dos Módulos ---
-use config::{Config, RuleMatch};
-use logger::Logger;
-use rainbow::Rainbow;
-use util::{get_exe_path, hex_to_colorref, set_startup};
+use cute_borders::backend::{self, WindowEvent};
+use cute_borders::config::Config;
+use cute_borders::logger::Logger;
+use cute_borders::rainbow::{Rainbow, RainbowOffset};
+use cute_borders::util::{get_file_path, watch_directory};
+use cute_borders::{accent, animation, frame, paint, registry, tiling, transition};
+#[cfg(windows)]
+use cute_borders::border;
+#[cfg(windows)]
+use startup::{get_exe_path, set_startup};
 
 // --- Constantes e Globais ---
-const DWMWA_BORDER_COLOR: u32 = 34;
-const DWMWA_COLOR_DEFAULT: u32 = 0xFFFFFFFF;
-#[allow(dead_code)]
-const DWMWA_COLOR_NONE: u32 = 0xFFFFFFFE;
-const COLOR_INVALID: u32 = 0x000000FF;
-
+#[cfg(windows)]
 static GUI_PID: Mutex<Option<DWORD>> = Mutex::new(None);
 
 // --- Lógica Principal ---
 
-fn is_rainbow_active(config: &Config) -> bool {
-    config.window_rules.iter().any(|r| {
-        r.rule_match == RuleMatch::Global && r.active_border_color.to_lowercase() == "rainbow"
-    })
-}
-
-unsafe extern "system" fn win_event_proc(
-    _h_win_event_hook: HWINEVENTHOOK, event: u32, hwnd: HWND,
-    _id_object: i32, _id_child: i32, _id_event_thread: u32, _dwms_event_time: u32,
-) {
-    if event == EVENT_SYSTEM_FOREGROUND {
-        // O Hook agora só precisa se preocupar em repintar se o rainbow estiver DESLIGADO.
-        if !is_rainbow_active(&Config::get()) {
-            apply_colors(hwnd, false);
-        }
+// Garante a pasta ~/.cuteborders e manda o log para o log.txt dela. Só o app faz isso, a
+// biblioteca e o cli não escrevem nada na pasta do usuário por conta própria.
+fn setup() {
+    if let Err(e) = Logger::log_to_file() {
+        eprintln!("cute-borders: {}", e);
+        std::process::exit(1);
     }
 }
 
-unsafe extern "system" fn enum_windows_callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
-    if IsWindow(hwnd) == 0 || IsWindowVisible(hwnd) == 0 { return 1; }
-    let mut class_buffer: [u16; 256] = [0; 256];
-    GetClassNameW(hwnd, class_buffer.as_mut_ptr(), class_buffer.len() as c_int);
-    let class_name = OsString::from_wide(&class_buffer).to_string_lossy().into_owned();
-    let ex_style = winapi::um::winuser::GetWindowLongW(hwnd, GWL_EXSTYLE) as u32;
-
-    if (ex_style & WS_EX_TOOLWINDOW == 0) || class_name.contains("#32768") {
-        let mut title_buffer: [u16; 512] = [0; 512];
-        if GetWindowTextLengthW(hwnd) > 0 {
-            GetWindowTextW(hwnd, title_buffer.as_mut_ptr(), title_buffer.len() as c_int);
-        }
-        let title = OsString::from_wide(&title_buffer).to_string_lossy().into_owned();
-        let visible_windows: &mut Vec<(HWND, String, String)> = &mut *(lparam as *mut Vec<(HWND, String, String)>);
-        visible_windows.push((hwnd, title, class_name));
+// sway e i3 têm uma só cor para todas as janelas sem foco: avisa quando as regras pedem mais de uma.
+fn check_inactive_colors() {
+    if backend::current().has_per_window_colors() {
+        return;
+    }
+    let per_window_rainbow = Config::get().rainbow_settings().offset != RainbowOffset::None;
+    if let Some(reason) = Config::rules().varying_inactive_colors(per_window_rainbow) {
+        let message = format!("{}, but sway and i3 have one border color for all unfocused windows, they get the one most of them ask for", reason);
+        Logger::log(&format!("[ERROR] {}", message));
+        eprintln!("cute-borders: {}", message);
     }
-    1
 }
 
-unsafe fn is_part_of_active_chain(hwnd: HWND, active_hwnd: HWND) -> bool {
-    if active_hwnd.is_null() || hwnd.is_null() { return false; }
-    if hwnd == active_hwnd { return true; }
-    let owner = GetWindow(hwnd, GW_OWNER);
-    if owner.is_null() { return false; }
-    is_part_of_active_chain(owner, active_hwnd)
-}
+// Sobe as threads que mantêm as bordas pintadas.
+fn start_painting() {
+    check_inactive_colors();
 
-fn get_window_pid(hwnd: HWND) -> u32 {
-    let mut pid: DWORD = 0;
-    unsafe { GetWindowThreadProcessId(hwnd, &mut pid) };
-    pid
-}
+    // THREAD 1: O Mestre da Pintura
+    std::thread::spawn(|| {
+        let backend = backend::current();
+        loop {
+            let config = Config::get();
+            let rules = Config::rules();
+            if rules.is_animated() {
+                animation::tick();
+            }
+            if rules.uses_rainbow() {
+                Rainbow::tick(&config.rainbow_settings());
+            }
 
-fn apply_colors(active_hwnd: HWND, reset: bool) {
-    let mut visible_windows: Vec<(HWND, String, String)> = Vec::new();
-    let lparam = &mut visible_windows as *mut _ as LPARAM;
-    unsafe { EnumWindows(Some(enum_windows_callback), lparam) };
-    let active_pid = if !active_hwnd.is_null() { get_window_pid(active_hwnd) } else { 0 };
+            // Com komorebi ou GlazeWM, o foco que o gerenciador de janelas reporta vale mais.
+            paint::apply_colors(backend.as_ref(), tiling::focused_or(backend.foreground()));
 
-    for (hwnd, title, class) in visible_windows {
-        if unsafe { IsWindow(hwnd) } == 0 { continue; }
-        let (color_active, color_inactive) = get_colors_for_window(hwnd, title, class.clone(), reset);
-        let is_in_owner_chain = unsafe { is_part_of_active_chain(hwnd, active_hwnd) };
-        let window_pid = get_window_pid(hwnd);
-        let is_special_menu_of_active_process = active_pid != 0 && window_pid == active_pid && class.contains("#32768");
-        let is_considered_active = is_in_owner_chain || is_special_menu_of_active_process;
-        let color_to_apply = if is_considered_active { color_active } else { color_inactive };
+            // Só acorda a cada quadro enquanto algo anima ou faz fade. Parado, dorme
+            // até um evento de janela, do config.yaml ou da cor de destaque.
+            let is_fading = config.transition_settings().is_some_and(|settings| transition::is_running(&settings));
+            let next_frame = if rules.is_animated() || is_fading { Some(config.frame_interval()) } else { None };
+            frame::wait_for_repaint(next_frame);
+        }
+    });
 
-        if color_to_apply != COLOR_INVALID {
-            unsafe {
-                let _ = DwmSetWindowAttribute(
-                    hwnd, DWMWA_BORDER_COLOR,
-                    &color_to_apply as *const _ as *const c_void,
-                    mem::size_of::<c_ulong>() as u32,
-                );
+    // THREAD 2: Ouvinte de Eventos de Janela
+    std::thread::spawn(|| {
+        backend::current().subscribe(Box::new(|event| {
+            // Mudança de foco sempre repinta, o resto só se o registro mudou.
+            // A pintura fica toda na thread 1, que junta vários eventos num só repaint.
+            let is_focus_change = matches!(event, WindowEvent::Foreground(_));
+            if registry::handle(event) || is_focus_change {
+                frame::request_repaint();
             }
-        }
-    }
-}
+        }));
+    });
 
-fn get_colors_for_window(_hwnd: HWND, title: String, class: String, reset: bool) -> (u32, u32) {
-    if reset { return (DWMWA_COLOR_DEFAULT, DWMWA_COLOR_DEFAULT); }
-    let config = Config::get();
-    let mut color_active = COLOR_INVALID;
-    let mut color_inactive = COLOR_INVALID;
+    // THREAD 3: Observa a cor de destaque do sistema, que fica em cache até mudar.
+    std::thread::spawn(|| {
+        accent::watch(|_| {
+            if Config::rules().uses_accent() {
+                frame::request_repaint();
+            }
+        });
+    });
 
-    for rule in config.window_rules.iter() {
-        let rule_applies = match rule.rule_match {
-            RuleMatch::Global => true,
-            RuleMatch::Title => rule.contains.as_ref().map_or(false, |c| title.to_lowercase().contains(&c.to_lowercase())),
-            RuleMatch::Class => rule.contains.as_ref().map_or(false, |c| class.to_lowercase().contains(&c.to_lowercase())),
-        };
+    // THREAD 4: Observa o config.yaml, o único lugar que relê o arquivo. A pasta
+    // também tem o log.txt, então só repinta quando o próprio config.yaml mudou.
+    std::thread::spawn(|| {
+        let Ok(config_path) = get_file_path("config.yaml") else { return; };
+        let config_dir = std::path::Path::new(&config_path).parent().map(|p| p.to_string_lossy().into_owned()).unwrap_or_default();
+        watch_directory(&config_dir, || {
+            if Config::reload_if_changed() {
+                check_inactive_colors();
+                frame::request_repaint();
+            }
+        });
+    });
 
-        if rule_applies {
-            color_active = hex_to_colorref(&rule.active_border_color);
-            color_inactive = if rule.inactive_border_color.is_empty() { DWMWA_COLOR_DEFAULT } else { hex_to_colorref(&rule.inactive_border_color) };
-            if rule.rule_match != RuleMatch::Global { break; }
-        }
-    }
-    (color_active, color_inactive)
+    // THREAD 5: Segue o komorebi ou o GlazeWM (tiling_wm) para as regras Workspace, Layout e State.
+    std::thread::spawn(|| {
+        tiling::watch(frame::request_repaint);
+    });
 }
 
+#[cfg(windows)]
 fn main() {
     let args: Vec<String> = std::env::args().collect();
+    if let Some(code) = cli::run(&args[1..]) { std::process::exit(code); }
+    setup();
     if args.len() > 1 && args[1] == "--config-gui" { gui::run_gui(); return; }
     if let Err(err) = set_startup(true) { Logger::log(&format!("[ERROR] Falha ao criar tarefa: {:?}", err)); }
 
-    // THREAD 1: O Mestre da Pintura
-    std::thread::spawn(|| {
-        loop {
-            let config = Config::get();
-            let active_hwnd = unsafe { GetForegroundWindow() };
-
-            if is_rainbow_active(&config) {
-                Rainbow::tick(config.rainbow_speed.unwrap_or(1.0));
-            }
-
-            // [A MUDANÇA CRÍTICA]: A função apply_colors é chamada a cada quadro,
-            // garantindo que qualquer mudança no config.yaml (seja para rainbow
-            // ou cor estática) seja aplicada imediatamente.
-            apply_colors(active_hwnd, false);
-            
-            std::thread::sleep(Duration::from_millis(33));
-        }
-    });
-
-    // THREAD 2: Ouvinte de Eventos do Windows (para resposta instantânea em modo estático)
-    std::thread::spawn(|| {
-        unsafe {
-            let hook = SetWinEventHook(EVENT_SYSTEM_FOREGROUND, EVENT_SYSTEM_FOREGROUND, ptr::null_mut(), Some(win_event_proc), 0, 0, WINEVENT_OUTOFCONTEXT);
-            let mut msg = mem::zeroed();
-            while GetMessageW(&mut msg, ptr::null_mut(), 0, 0) != 0 {
-                TranslateMessage(&msg);
-                DispatchMessageW(&msg);
-            }
-            if !hook.is_null() { UnhookWinEvent(hook); }
-        }
-    });
+    start_painting();
 
     // THREAD PRINCIPAL: Cuida do Ícone da Bandeja
     let is_elevated = is_elevated().unwrap_or(false);
@@ -197,6 +160,7 @@ fn main() {
             let tray_menu = Menu::with_items(&[
                 &MenuItemBuilder::new().text("Abrir Configurações").id(MenuId::new("open_gui")).enabled(true).build(),
                 &MenuItemBuilder::new().text(if is_elevated { "Desinstalar" } else { "Instalar (Requer Admin)" }).id(MenuId::new("install")).enabled(true).build(),
+                &MenuItemBuilder::new().text("Registrar Status no Log").id(MenuId::new("status")).enabled(true).build(),
                 &MenuItemBuilder::new().text("Sair").id(MenuId::new("quit")).enabled(true).build(),
             ]).expect("Falha ao criar menu");
 
@@ -230,6 +194,7 @@ fn main() {
                         shutdown_app();
                     }
                 },
+                "status" => Logger::log(&format!("[STATUS] {}", border::report())),
                 "quit" => shutdown_app(),
                 _ => {}
             }
@@ -244,8 +209,35 @@ fn main() {
     }
 }
 
+#[cfg(not(windows))]
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(code) = cli::run(&args[1..]) { std::process::exit(code); }
+    setup();
+    if args.len() > 1 && args[1] == "--config-gui" { gui::run_gui(); return; }
+    if !backend::is_available() {
+        Logger::log("[ERROR] Nenhum backend de janelas encontrado ($SWAYSOCK, $I3SOCK ou $HYPRLAND_INSTANCE_SIGNATURE)");
+        eprintln!("cute-borders não encontrou um gerenciador de janelas suportado (sway, i3 ou Hyprland)");
+        std::process::exit(1);
+    }
+
+    // Sem bandeja fora do Windows: as threads de pintura fazem todo o trabalho.
+    start_painting();
+    // Ctrl+C, SIGTERM e SIGHUP devolvem as cores do sway, i3 ou Hyprland antes de sair.
+    if let Err(e) = ctrlc::set_handler(|| {
+        paint::reset_colors(backend::current().as_ref());
+        std::process::exit(0);
+    }) {
+        Logger::log(&format!("[ERROR] Falha ao tratar sinais, as cores não serão restauradas ao sair: {}", e));
+    }
+    loop {
+        std::thread::park();
+    }
+}
+
+#[cfg(windows)]
 fn shutdown_app() {
-    apply_colors(ptr::null_mut(), true);
+    paint::reset_colors(backend::current().as_ref());
     if let Some(pid) = *GUI_PID.lock().unwrap() {
         unsafe {
             let handle = OpenProcess(PROCESS_TERMINATE, 0, pid);
@@ -0,0 +1,155 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::backend::{WindowBackend, WindowEvent};
+use crate::border::{self, Borders};
+use crate::color::ColorSpec;
+use crate::config::Config;
+use crate::focus::Focus;
+use crate::logger::Logger;
+use crate::rainbow::{self, RainbowOffset, RainbowSettings};
+use crate::registry::{self, WindowRegistry};
+use crate::rules::RuleSet;
+use crate::transition::{self, Transitions};
+use crate::{process, DWMWA_COLOR_DEFAULT};
+
+/// What the paint loop keeps from one frame to the next. The app paints
+/// with the shared ones through `apply_colors`, a test brings its own.
+pub struct Painter<'a> {
+  pub registry: &'a mut WindowRegistry,
+  pub borders: &'a mut Borders,
+  pub transitions: &'a mut Transitions,
+}
+
+impl Painter<'_> {
+  /// Gives every window the color its rule asks for, with `active` as the
+  /// focused window. Only windows whose color changed are set.
+  pub fn apply_colors(
+    &mut self,
+    backend: &dyn WindowBackend,
+    config: &Config,
+    rules: &Arc<RuleSet>,
+    active: Option<usize>,
+  ) {
+    let focus = Focus::new(backend, active);
+    let transition_settings = config.transition_settings();
+    let rainbow_settings = config.rainbow_settings();
+
+    let mut seen_pids = HashSet::new();
+    let mut seen_windows = HashSet::new();
+
+    // Rules are only evaluated again for windows that are new or changed.
+    for matched in self.registry.matched(rules) {
+      if !backend.is_alive(matched.window) {
+        // A missed destroy event doesn't keep the window around forever.
+        self.registry.handle(WindowEvent::Destroyed(matched.window));
+        continue;
+      }
+      seen_pids.insert(matched.pid);
+      seen_windows.insert(matched.window);
+      let hue_offset = if rules.uses_rainbow() {
+        rainbow_offset(backend, matched.window, matched.pid, &rainbow_settings)
+      } else {
+        0.0
+      };
+      let (active, inactive) = rules.colors_of(matched.rule, hue_offset);
+      let is_active = focus.is_active(backend, matched.window, matched.pid, matched.is_menu);
+      // With transition_ms the color fades instead of switching at once.
+      let color = match &transition_settings {
+        Some(settings) => {
+          let colors = (active, inactive);
+          self
+            .transitions
+            .color_for(matched.window, is_active, colors, settings)
+        }
+        None if is_active => active,
+        None => inactive,
+      };
+
+      self.borders.set(matched.window, color, |window, color| {
+        if Logger::explains() {
+          let reason = match focus.active_reason(backend, window, matched.pid, matched.is_menu) {
+            Some(reason) => format!("active, {}", reason),
+            None => format!("inactive, {}", focus.inactive_reason()),
+          };
+          Logger::log(&format!(
+            "[EXPLAIN] 0x{:x} -> {} (window_rules[{}], {})",
+            window,
+            ColorSpec::from_colorref(color),
+            rules.config_index(matched.rule),
+            reason
+          ));
+        }
+        backend.set_border_color(window, color);
+      });
+    }
+
+    backend.flush();
+    process::retain_pids(&seen_pids);
+    self.transitions.retain(&seen_windows);
+    self.borders.retain(&seen_windows);
+  }
+
+  /// Gives every window its default border back, e.g. before exiting.
+  pub fn reset_colors(&mut self, backend: &dyn WindowBackend) {
+    for window in self.registry.windows() {
+      self
+        .borders
+        .set(window, DWMWA_COLOR_DEFAULT, |window, color| {
+          backend.set_border_color(window, color)
+        });
+    }
+    backend.flush();
+  }
+}
+
+/// How far ahead of the shared rainbow this window's is, per `rainbow_offset`.
+fn rainbow_offset(
+  backend: &dyn WindowBackend,
+  window: usize,
+  pid: u32,
+  settings: &RainbowSettings,
+) -> f32 {
+  match settings.offset {
+    RainbowOffset::None => 0.0,
+    RainbowOffset::Window => rainbow::hash_offset(&window.to_le_bytes()),
+    RainbowOffset::Process => match process::get_process_info(pid) {
+      Some(info) => rainbow::hash_offset(info.name.to_lowercase().as_bytes()),
+      None => rainbow::hash_offset(&pid.to_le_bytes()),
+    },
+    RainbowOffset::Position => {
+      let Some(bounds) = backend.bounds(window) else {
+        return 0.0;
+      };
+      let desktop = backend.desktop();
+      rainbow::position_offset(
+        bounds.center_x(),
+        desktop.left,
+        desktop.width(),
+        settings.spread,
+      )
+    }
+  }
+}
+
+/// Paints one frame of the app with the current config.
+pub fn apply_colors(backend: &dyn WindowBackend, active: Option<usize>) {
+  let (config, rules) = (Config::get(), Config::rules());
+  with_painter(|painter| painter.apply_colors(backend, &config, &rules, active));
+}
+
+pub fn reset_colors(backend: &dyn WindowBackend) {
+  with_painter(|painter| painter.reset_colors(backend));
+}
+
+// Window events wait for the registry while a frame is painted.
+fn with_painter(f: impl FnOnce(&mut Painter)) {
+  let mut registry = registry::lock();
+  let mut borders = border::lock();
+  let mut transitions = transition::lock();
+  f(&mut Painter {
+    registry: &mut registry,
+    borders: &mut borders,
+    transitions: &mut transitions,
+  });
+}
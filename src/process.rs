@@ -0,0 +1,75 @@
+use lazy_static::lazy_static;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+lazy_static! {
+  // None is cached as well, so processes we can't open (e.g. elevated ones)
+  // aren't queried again on every frame.
+  static ref PROCESS_CACHE: Mutex<HashMap<u32, Option<ProcessInfo>>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Clone)]
+pub struct ProcessInfo {
+  /// Executable name, e.g. `firefox.exe`
+  pub name: String,
+  /// Full executable path, e.g. `C:\Program Files\Mozilla Firefox\firefox.exe`
+  pub path: String,
+}
+
+pub fn get_process_info(pid: u32) -> Option<ProcessInfo> {
+  if pid == 0 {
+    return None;
+  }
+
+  let mut cache = PROCESS_CACHE.lock().unwrap();
+  cache.entry(pid).or_insert_with(|| query_process_info(pid)).clone()
+}
+
+/// Drops cached entries for pids that no longer own a visible window,
+/// so a reused pid doesn't inherit the info of a dead process.
+pub fn retain_pids(pids: &HashSet<u32>) {
+  let mut cache = PROCESS_CACHE.lock().unwrap();
+  cache.retain(|pid, _| pids.contains(pid));
+}
+
+#[cfg(windows)]
+fn query_process_info(pid: u32) -> Option<ProcessInfo> {
+  use std::ffi::OsString;
+  use std::os::windows::prelude::OsStringExt;
+  use winapi::shared::minwindef::DWORD;
+  use winapi::um::handleapi::CloseHandle;
+  use winapi::um::processthreadsapi::OpenProcess;
+  use winapi::um::winbase::QueryFullProcessImageNameW;
+  use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+
+  unsafe {
+    let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+    if handle.is_null() {
+      return None;
+    }
+
+    let mut buffer: [u16; 1024] = [0; 1024];
+    let mut size = buffer.len() as DWORD;
+    let result = QueryFullProcessImageNameW(handle, 0, buffer.as_mut_ptr(), &mut size);
+    CloseHandle(handle);
+    if result == 0 {
+      return None;
+    }
+
+    let path = OsString::from_wide(&buffer[..size as usize])
+      .to_string_lossy()
+      .into_owned();
+    let name = path.rsplit('\\').next().unwrap_or(&path).to_string();
+    Some(ProcessInfo { name, path })
+  }
+}
+
+/// Reads the executable from procfs, so process rules work the same way
+/// off Windows.
+#[cfg(not(windows))]
+fn query_process_info(pid: u32) -> Option<ProcessInfo> {
+  let path = std::fs::read_link(format!("/proc/{}/exe", pid)).ok()?;
+  let path = path.to_string_lossy().into_owned();
+  let name = path.rsplit('/').next().unwrap_or(&path).to_string();
+  Some(ProcessInfo { name, path })
+}
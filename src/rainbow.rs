@@ -1,62 +1,347 @@
-use lazy_static::lazy_static;
-use std::sync::{Arc, Mutex};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+use std::time::Duration;
 
+use crate::animation::{Clock, SystemClock};
+use crate::css::Rgba;
 use crate::DWMWA_COLOR_DEFAULT;
 
-lazy_static! {
-  static ref RAINBOW: Mutex<Rainbow> = Mutex::new(Rainbow::new());
+static RAINBOW: Lazy<Rainbow> = Lazy::new(|| Rainbow::new(Box::new(SystemClock::new())));
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RainbowDirection {
+  /// Red, yellow, green, blue...
+  #[default]
+  Forward,
+  Reverse,
+}
+
+/// How far ahead of the shared rainbow each window is.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RainbowOffset {
+  /// Every window has the same color.
+  #[default]
+  None,
+  /// Each window gets its own hue, which stays the same while it's open.
+  Window,
+  /// All windows of a program share a hue.
+  Process,
+  /// The hue follows the window's horizontal position, so the rainbow
+  /// flows across the desktop.
+  Position,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RainbowSettings {
+  /// Degrees of hue per second.
+  pub speed: f32,
+  pub saturation: f32,
+  pub lightness: f32,
+  pub direction: RainbowDirection,
+  pub offset: RainbowOffset,
+  /// Degrees of hue between the left and right edge of the desktop with
+  /// `RainbowOffset::Position`.
+  pub spread: f32,
+}
+
+impl Default for RainbowSettings {
+  fn default() -> RainbowSettings {
+    RainbowSettings {
+      speed: 30.0,
+      saturation: 1.0,
+      lightness: 0.5,
+      direction: RainbowDirection::Forward,
+      offset: RainbowOffset::None,
+      spread: 360.0,
+    }
+  }
 }
 
+/// The hue cycle behind the `rainbow` color. The hue advances with the
+/// clock, so the speed doesn't depend on how often `tick` is called.
 pub struct Rainbow {
-  color: Arc<Mutex<u32>>,
-  hue: Arc<Mutex<f32>>,
+  clock: Box<dyn Clock>,
+  // One lock for everything, so a color is never computed from the hue of
+  // one update and the saturation of another. Only the paint thread
+  // writes, once per frame; reads don't block each other.
+  state: RwLock<RainbowState>,
+}
+
+struct RainbowState {
+  last_tick: Duration,
+  hue: f32,
+  saturation: f32,
+  lightness: f32,
+  color: u32,
 }
 
 impl Rainbow {
-  fn new() -> Self {
-    let color = Arc::new(Mutex::new(DWMWA_COLOR_DEFAULT));
-    let hue = Arc::new(Mutex::new(0.0));
-    Rainbow { color, hue }
-  }
-  pub fn tick(speed: f32) {
-    let rainbow = RAINBOW.lock().unwrap();
-    let mut hue = rainbow.hue.lock().unwrap();
-    let (r, g, b) = hsl_to_rgb(*hue, 1.0, 0.5);
-    let color_value = 0x00u32 | ((b as u32) << 16) | ((g as u32) << 8) | ((r as u32) << 0);
-
-    let mut color = rainbow.color.lock().unwrap();
-    *color = color_value;
-    *hue = (*hue + speed) % 360.0;
-  }
-  pub fn get_color() -> u32 {
-    let rainbow = RAINBOW.lock().unwrap();
-    let color = rainbow.color.lock().unwrap();
-    *color
-  }
-}
-
-fn hsl_to_rgb(h: f32, s: f32, l: f32) -> (u8, u8, u8) {
-  let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
-  let x = c * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
-  let m = l - c / 2.0;
-
-  let (r_prime, g_prime, b_prime) = if 0.0 <= h && h < 60.0 {
-    (c, x, 0.0)
-  } else if 60.0 <= h && h < 120.0 {
-    (x, c, 0.0)
-  } else if 120.0 <= h && h < 180.0 {
-    (0.0, c, x)
-  } else if 180.0 <= h && h < 240.0 {
-    (0.0, x, c)
-  } else if 240.0 <= h && h < 300.0 {
-    (x, 0.0, c)
-  } else {
-    (c, 0.0, x)
-  };
-
-  let r = ((r_prime + m) * 255.0).round() as u8;
-  let g = ((g_prime + m) * 255.0).round() as u8;
-  let b = ((b_prime + m) * 255.0).round() as u8;
-
-  (r, g, b)
+  pub fn new(clock: Box<dyn Clock>) -> Rainbow {
+    let last_tick = clock.elapsed();
+    Rainbow {
+      clock,
+      state: RwLock::new(RainbowState {
+        last_tick,
+        hue: 0.0,
+        saturation: 1.0,
+        lightness: 0.5,
+        color: DWMWA_COLOR_DEFAULT,
+      }),
+    }
+  }
+
+  /// Advances the hue by the time passed since the last update. Integrating
+  /// instead of computing `elapsed * speed` keeps the hue from jumping when
+  /// the speed changes in config.yaml.
+  pub fn update(&self, settings: &RainbowSettings) -> u32 {
+    let mut state = self.state.write().unwrap();
+    let now = self.clock.elapsed();
+    let seconds = now.saturating_sub(state.last_tick).as_secs_f32();
+    let step = match settings.direction {
+      RainbowDirection::Forward => settings.speed * seconds,
+      RainbowDirection::Reverse => -settings.speed * seconds,
+    };
+    state.hue = (state.hue + step).rem_euclid(360.0);
+    state.last_tick = now;
+    state.saturation = settings.saturation;
+    state.lightness = settings.lightness;
+    state.color = color_at(state.hue, state.saturation, state.lightness);
+    state.color
+  }
+
+  pub fn color(&self) -> u32 {
+    self.state.read().unwrap().color
+  }
+
+  /// The color `hue_offset` degrees ahead of the current one.
+  pub fn color_with_offset(&self, hue_offset: f32) -> u32 {
+    let state = self.state.read().unwrap();
+    if hue_offset == 0.0 {
+      return state.color;
+    }
+    color_at(state.hue + hue_offset, state.saturation, state.lightness)
+  }
+
+  pub fn tick(settings: &RainbowSettings) {
+    RAINBOW.update(settings);
+  }
+
+  pub fn get_color(hue_offset: f32) -> u32 {
+    RAINBOW.color_with_offset(hue_offset)
+  }
+}
+
+/// The hue of a rainbow that started at 0 with `settings`, `seconds` later.
+pub fn hue_at(seconds: f64, settings: &RainbowSettings) -> f32 {
+  let degrees = (settings.speed as f64 * seconds).rem_euclid(360.0) as f32;
+  match settings.direction {
+    RainbowDirection::Forward => degrees,
+    RainbowDirection::Reverse => (360.0 - degrees).rem_euclid(360.0),
+  }
+}
+
+/// A stable hue offset for anything that can be turned into bytes, e.g. a
+/// window handle or an executable name. FNV-1a instead of std's hasher,
+/// which is seeded randomly, so the same key gets the same hue every run.
+pub fn hash_offset(key: &[u8]) -> f32 {
+  let mut hash: u32 = 0x811c9dc5;
+  for byte in key {
+    hash ^= *byte as u32;
+    hash = hash.wrapping_mul(0x01000193);
+  }
+  (hash % 360) as f32
+}
+
+/// The hue offset of a window whose center is at `x`, for a desktop that
+/// starts at `left` and is `width` pixels wide.
+pub fn position_offset(x: i32, left: i32, width: i32, spread: f32) -> f32 {
+  if width <= 0 {
+    return 0.0;
+  }
+  let position = ((x - left) as f32 / width as f32).clamp(0.0, 1.0);
+  position * spread
+}
+
+/// The rainbow color at a given hue, as a COLORREF. Computed straight from
+/// HSL since it's called for every window with an offset on every frame.
+pub fn color_at(hue: f32, saturation: f32, lightness: f32) -> u32 {
+  Rgba::from_hsl(hue.rem_euclid(360.0), saturation, lightness, 255).to_colorref()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::animation::{Animation, AnimationMode, ColorSpace, Easing, Keyframe, ManualClock};
+  use crate::color::ColorSpec;
+
+  const RED: u32 = 0x0000ff;
+  const YELLOW: u32 = 0x00ffff;
+  const GREEN: u32 = 0x00ff00;
+  const MAGENTA: u32 = 0xff00ff;
+
+  fn rainbow() -> (Rainbow, ManualClock) {
+    let clock = ManualClock::default();
+    (Rainbow::new(Box::new(clock.clone())), clock)
+  }
+
+  #[test]
+  fn hue_follows_elapsed_time() {
+    let (rainbow, clock) = rainbow();
+    let settings = RainbowSettings::default();
+    assert_eq!(rainbow.update(&settings), RED);
+    // Ticking again without time passing doesn't move the hue.
+    assert_eq!(rainbow.update(&settings), RED);
+    clock.set_ms(2000);
+    assert_eq!(rainbow.update(&settings), YELLOW);
+    clock.set_ms(4000);
+    assert_eq!(rainbow.update(&settings), GREEN);
+    assert_eq!(rainbow.color_with_offset(-120.0), RED);
+  }
+
+  #[test]
+  fn speed_changes_keep_the_hue() {
+    let (rainbow, clock) = rainbow();
+    let mut settings = RainbowSettings::default();
+    rainbow.update(&settings);
+    clock.set_ms(2000);
+    assert_eq!(rainbow.update(&settings), YELLOW);
+    settings.speed = 60.0;
+    clock.set_ms(3000);
+    assert_eq!(rainbow.update(&settings), GREEN);
+  }
+
+  #[test]
+  fn reverse_goes_backwards() {
+    let (rainbow, clock) = rainbow();
+    let settings = RainbowSettings {
+      direction: RainbowDirection::Reverse,
+      ..RainbowSettings::default()
+    };
+    rainbow.update(&settings);
+    clock.set_ms(2000);
+    assert_eq!(rainbow.update(&settings), MAGENTA);
+  }
+
+  #[test]
+  fn settings_changes_are_never_seen_halfway() {
+    let rainbow = Rainbow::new(Box::new(ManualClock::default()));
+    let gray = RainbowSettings {
+      saturation: 0.0,
+      lightness: 0.25,
+      ..RainbowSettings::default()
+    };
+    let default = RainbowSettings::default();
+    let colors = [color_at(90.0, 1.0, 0.5), color_at(90.0, 0.0, 0.25)];
+    std::thread::scope(|scope| {
+      scope.spawn(|| {
+        for i in 0..2000 {
+          rainbow.update(if i % 2 == 0 { &gray } else { &default });
+        }
+      });
+      for _ in 0..2000 {
+        // Whatever the update, the offset color comes from one of them.
+        let color = rainbow.color_with_offset(90.0);
+        assert!(colors.contains(&color), "{:06x}", color);
+      }
+    });
+  }
+
+  #[test]
+  fn hash_offsets_are_stable() {
+    // FNV-1a, so the same process keeps its color across restarts.
+    assert_eq!(hash_offset(b""), 61.0);
+    assert_eq!(hash_offset(b"notepad.exe"), 148.0);
+    assert_eq!(hash_offset(b"notepad.exe"), hash_offset(b"notepad.exe"));
+    assert_ne!(hash_offset(b"notepad.exe"), hash_offset(b"Notepad"));
+  }
+
+  #[test]
+  fn hash_offsets_stay_on_the_wheel() {
+    for window in (0..100_000usize).step_by(97) {
+      let offset = hash_offset(&window.to_le_bytes());
+      assert!((0.0..360.0).contains(&offset), "{}", offset);
+    }
+  }
+
+  #[test]
+  fn position_offsets_follow_the_desktop() {
+    assert_eq!(position_offset(-1920, -1920, 3840, 360.0), 0.0);
+    assert_eq!(position_offset(0, -1920, 3840, 360.0), 180.0);
+    assert_eq!(position_offset(960, -1920, 3840, 120.0), 90.0);
+    assert_eq!(position_offset(1920, -1920, 3840, 360.0), 360.0);
+  }
+
+  #[test]
+  fn position_offsets_clamp_windows_off_the_desktop() {
+    assert_eq!(position_offset(-5000, 0, 1920, 360.0), 0.0);
+    assert_eq!(position_offset(5000, 0, 1920, 360.0), 360.0);
+  }
+
+  #[test]
+  fn position_offsets_of_an_empty_desktop_are_zero() {
+    assert_eq!(position_offset(100, 0, 0, 360.0), 0.0);
+    assert_eq!(position_offset(100, 0, -10, 360.0), 0.0);
+  }
+
+  #[test]
+  fn color_at_fixed_hues() {
+    assert_eq!(color_at(0.0, 1.0, 0.5), RED);
+    assert_eq!(color_at(60.0, 1.0, 0.5), YELLOW);
+    assert_eq!(color_at(420.0, 1.0, 0.5), YELLOW);
+    assert_eq!(color_at(-60.0, 1.0, 0.5), MAGENTA);
+    // Halfway from red to yellow, as a COLORREF.
+    assert_eq!(color_at(30.0, 1.0, 0.5), 0x0080ff);
+    assert_eq!(color_at(120.0, 0.0, 0.5), 0x808080);
+  }
+
+  #[test]
+  fn offsets_are_added_to_the_current_hue() {
+    let (rainbow, clock) = rainbow();
+    let settings = RainbowSettings::default();
+    rainbow.update(&settings);
+    clock.set_ms(2000);
+    rainbow.update(&settings);
+    assert_eq!(rainbow.color_with_offset(0.0), YELLOW);
+    assert_eq!(rainbow.color_with_offset(60.0), GREEN);
+    assert_eq!(rainbow.color_with_offset(300.0), RED);
+    assert_eq!(rainbow.color_with_offset(-120.0), MAGENTA);
+  }
+
+  // The six primary and secondary hues one second apart, interpolated in
+  // sRGB, follow the HSL hue wheel exactly.
+  #[test]
+  fn color_at_matches_an_srgb_hue_wheel() {
+    for (saturation, lightness) in [(1.0, 0.5), (0.6, 0.4), (0.3, 0.8)] {
+      let stops = (0..6)
+        .map(|i| Keyframe {
+          color: ColorSpec::Hex(
+            Rgba::from_hsl(i as f32 * 60.0, saturation, lightness, 255).to_colorref(),
+          ),
+          duration_ms: 1000.0,
+        })
+        .collect();
+      let preset =
+        Animation::new(stops, AnimationMode::Loop, Easing::Linear, ColorSpace::Srgb).unwrap();
+      for hue in (0..360).step_by(7) {
+        let sampled = preset.sample(hue as f64 / 60.0, 0.0).unwrap();
+        let direct = Rgba::from_colorref(color_at(hue as f32, saturation, lightness));
+        for (a, b) in [
+          (sampled.r, direct.r),
+          (sampled.g, direct.g),
+          (sampled.b, direct.b),
+        ] {
+          assert!(
+            a.abs_diff(b) <= 1,
+            "hue {}: {:?} {:?}",
+            hue,
+            sampled,
+            direct
+          );
+        }
+      }
+    }
+  }
 }
@@ -0,0 +1,324 @@
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::backend::{self, WindowAttributes, WindowBackend, WindowEvent};
+use crate::logger::Logger;
+use crate::rules::{RuleSet, WindowInfo};
+use crate::tiling::TilingSnapshot;
+
+static REGISTRY: Lazy<Mutex<WindowRegistry>> =
+  Lazy::new(|| Mutex::new(WindowRegistry::new(backend::current())));
+
+/// A window that gets a border and the rule that applies to it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MatchedWindow {
+  pub window: usize,
+  pub pid: u32,
+  pub is_menu: bool,
+  /// Index into the `RuleSet` passed to `WindowRegistry::matched`.
+  pub rule: usize,
+}
+
+/// The windows that get borders, kept up to date from window events so
+/// titles and classes aren't read again on every frame, and rules are
+/// only evaluated again for windows whose attributes changed.
+pub struct WindowRegistry {
+  backend: Arc<dyn WindowBackend>,
+  windows: HashMap<usize, TrackedWindow>,
+  // The rules the cached matches were computed with.
+  rules: Option<Arc<RuleSet>>,
+  tiling: Option<Arc<TilingSnapshot>>,
+}
+
+struct TrackedWindow {
+  attributes: WindowAttributes,
+  // `None` until rules are evaluated, `Some(None)` if no rule applies.
+  rule: Option<Option<usize>>,
+}
+
+impl WindowRegistry {
+  pub fn new(backend: Arc<dyn WindowBackend>) -> WindowRegistry {
+    let mut registry = WindowRegistry {
+      backend,
+      windows: HashMap::new(),
+      rules: None,
+      tiling: None,
+    };
+    registry.rescan();
+    registry
+  }
+
+  /// Enumerates all windows again, e.g. at startup.
+  pub fn rescan(&mut self) {
+    let windows = self.backend.enumerate();
+    self.windows.retain(|window, _| windows.contains(window));
+    for window in windows {
+      self.refresh(window);
+    }
+  }
+
+  /// Updates the registry after an event. Returns whether a window was
+  /// added, removed or changed.
+  pub fn handle(&mut self, event: WindowEvent) -> bool {
+    match event {
+      WindowEvent::Destroyed(window) | WindowEvent::Hidden(window) => {
+        self.windows.remove(&window).is_some()
+      }
+      WindowEvent::Created(window)
+      | WindowEvent::Shown(window)
+      | WindowEvent::NameChanged(window)
+      | WindowEvent::Foreground(window) => self.refresh(window),
+    }
+  }
+
+  /// Reads a window's attributes again, forgetting its rule if they changed.
+  fn refresh(&mut self, window: usize) -> bool {
+    let Some(attributes) = self.backend.describe(window) else {
+      return self.windows.remove(&window).is_some();
+    };
+    match self.windows.get_mut(&window) {
+      Some(tracked) if tracked.attributes == attributes => false,
+      Some(tracked) => {
+        tracked.attributes = attributes;
+        tracked.rule = None;
+        true
+      }
+      None => {
+        self.windows.insert(
+          window,
+          TrackedWindow {
+            attributes,
+            rule: None,
+          },
+        );
+        true
+      }
+    }
+  }
+
+  /// Replaces what the tiling window manager says about windows. Only
+  /// windows whose workspace, layout or state changed are matched again.
+  pub fn set_tiling(&mut self, tiling: Option<Arc<TilingSnapshot>>) {
+    let tiling_of = |snapshot: &Option<Arc<TilingSnapshot>>, window: &usize| {
+      snapshot
+        .as_ref()
+        .and_then(|snapshot| snapshot.windows.get(window).cloned())
+    };
+    for (window, tracked) in self.windows.iter_mut() {
+      if tiling_of(&self.tiling, window) != tiling_of(&tiling, window) {
+        tracked.rule = None;
+      }
+    }
+    self.tiling = tiling;
+  }
+
+  /// The windows some rule applies to. Rules are only evaluated for
+  /// windows that are new or changed, or for all of them after a reload.
+  pub fn matched(&mut self, rules: &Arc<RuleSet>) -> Vec<MatchedWindow> {
+    if !self
+      .rules
+      .as_ref()
+      .is_some_and(|cached| Arc::ptr_eq(cached, rules))
+    {
+      self.rules = Some(rules.clone());
+      for tracked in self.windows.values_mut() {
+        tracked.rule = None;
+      }
+    }
+
+    let mut matched = Vec::with_capacity(self.windows.len());
+    for (&window, tracked) in self.windows.iter_mut() {
+      let attributes = &tracked.attributes;
+      let tiling = self
+        .tiling
+        .as_ref()
+        .and_then(|snapshot| snapshot.windows.get(&window));
+      let rule = *tracked.rule.get_or_insert_with(|| {
+        let info =
+          WindowInfo::new(&attributes.title, &attributes.class, attributes.pid).with_tiling(tiling);
+        if Logger::explains() {
+          Logger::log(&format!(
+            "[EXPLAIN] 0x{:x} {:?} ({}, pid {})\n{}",
+            window,
+            attributes.title,
+            attributes.class,
+            attributes.pid,
+            rules.explain(&info)
+          ));
+        }
+        rules.rule_for(&info)
+      });
+      if let Some(rule) = rule {
+        matched.push(MatchedWindow {
+          window,
+          pid: attributes.pid,
+          is_menu: attributes.is_menu,
+          rule,
+        });
+      }
+    }
+    matched
+  }
+
+  /// Every window that gets a border, whether a rule applies or not.
+  pub fn windows(&self) -> Vec<usize> {
+    self.windows.keys().copied().collect()
+  }
+}
+
+pub(crate) fn lock() -> MutexGuard<'static, WindowRegistry> {
+  REGISTRY.lock().unwrap()
+}
+
+pub fn handle(event: WindowEvent) -> bool {
+  REGISTRY.lock().unwrap().handle(event)
+}
+
+pub fn set_tiling(tiling: Option<Arc<TilingSnapshot>>) {
+  REGISTRY.lock().unwrap().set_tiling(tiling);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::backend::{MockBackend, MockWindow, Rect};
+  use crate::config::Config;
+  use crate::tiling::WindowTiling;
+
+  const RULES: &str = "window_rules:
+  - Match: Title
+    contains: Editor
+    active_border_color: red
+  - Match: Global
+    active_border_color: blue
+";
+
+  fn window(title: &str) -> MockWindow {
+    MockWindow {
+      attributes: WindowAttributes {
+        title: title.to_string(),
+        class: "Class".to_string(),
+        pid: 1,
+        is_menu: false,
+      },
+      owner: None,
+      bounds: Rect::default(),
+    }
+  }
+
+  fn rules() -> Arc<RuleSet> {
+    Arc::new(RuleSet::compile(&Config::from_yaml(RULES).unwrap()).unwrap())
+  }
+
+  /// Feeds the mock's queued events to the registry, returns what `handle` said about each.
+  fn pump(backend: &MockBackend, registry: &mut WindowRegistry) -> Vec<bool> {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let queue = events.clone();
+    backend.subscribe(Box::new(move |event| queue.lock().unwrap().push(event)));
+    let events = std::mem::take(&mut *events.lock().unwrap());
+    events
+      .into_iter()
+      .map(|event| registry.handle(event))
+      .collect()
+  }
+
+  fn rule_of(matched: &[MatchedWindow], window: usize) -> Option<usize> {
+    matched.iter().find(|m| m.window == window).map(|m| m.rule)
+  }
+
+  fn is_cached(registry: &WindowRegistry, window: usize) -> bool {
+    registry.windows[&window].rule.is_some()
+  }
+
+  fn setup() -> (Arc<MockBackend>, WindowRegistry, Arc<RuleSet>) {
+    let backend = Arc::new(MockBackend::new());
+    let mut registry = WindowRegistry::new(backend.clone());
+    backend.open(1, window("notes - Editor"));
+    backend.open(2, window("Terminal"));
+    assert_eq!(pump(&backend, &mut registry), [true, false, true, false]);
+    let rules = rules();
+    let matched = registry.matched(&rules);
+    assert_eq!(rule_of(&matched, 1), Some(0));
+    assert_eq!(rule_of(&matched, 2), Some(1));
+    (backend, registry, rules)
+  }
+
+  #[test]
+  fn rename_only_reevaluates_that_window() {
+    let (backend, mut registry, rules) = setup();
+    backend.rename(2, "Editor 2");
+    assert_eq!(pump(&backend, &mut registry), [true]);
+    assert!(is_cached(&registry, 1));
+    assert!(!is_cached(&registry, 2));
+    assert_eq!(rule_of(&registry.matched(&rules), 2), Some(0));
+    assert!(is_cached(&registry, 2));
+  }
+
+  #[test]
+  fn unchanged_name_keeps_the_match() {
+    let (backend, mut registry, _) = setup();
+    backend.rename(1, "notes - Editor");
+    backend.focus(1);
+    assert_eq!(pump(&backend, &mut registry), [false, false]);
+    assert!(is_cached(&registry, 1));
+  }
+
+  #[test]
+  fn destroyed_windows_are_dropped() {
+    let (backend, mut registry, rules) = setup();
+    backend.close(1);
+    backend.close(1);
+    assert_eq!(pump(&backend, &mut registry), [true, false]);
+    assert_eq!(registry.windows(), [2]);
+    let matched = registry.matched(&rules);
+    assert_eq!(rule_of(&matched, 1), None);
+    assert_eq!(matched.len(), 1);
+  }
+
+  #[test]
+  fn reloaded_rules_reevaluate_everything() {
+    let (_, mut registry, rules) = setup();
+    registry.matched(&rules);
+    assert!(is_cached(&registry, 1) && is_cached(&registry, 2));
+
+    let reloaded = Arc::new(
+      RuleSet::compile(
+        &Config::from_yaml(
+          "window_rules:\n  - Match: Title\n    contains: Term\n    active_border_color: red\n",
+        )
+        .unwrap(),
+      )
+      .unwrap(),
+    );
+    let matched = registry.matched(&reloaded);
+    assert_eq!(rule_of(&matched, 1), None);
+    assert_eq!(rule_of(&matched, 2), Some(0));
+  }
+
+  #[test]
+  fn tiling_changes_reevaluate_those_windows() {
+    let (_, mut registry, rules) = setup();
+    let tiling = |workspace_index| WindowTiling {
+      workspace_index,
+      workspace_name: workspace_index.to_string(),
+      layout: "bsp".to_string(),
+      states: vec!["tiled"],
+    };
+    let snapshot = |windows: Vec<(usize, WindowTiling)>| {
+      Some(Arc::new(TilingSnapshot {
+        windows: windows.into_iter().collect(),
+        focused: None,
+      }))
+    };
+
+    registry.set_tiling(snapshot(vec![(1, tiling(0))]));
+    assert!(!is_cached(&registry, 1));
+    assert!(is_cached(&registry, 2));
+    registry.matched(&rules);
+
+    registry.set_tiling(snapshot(vec![(1, tiling(0)), (2, tiling(1))]));
+    assert!(is_cached(&registry, 1));
+    assert!(!is_cached(&registry, 2));
+  }
+}
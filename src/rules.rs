@@ -0,0 +1,1127 @@
+use once_cell::sync::OnceCell;
+use regex::Regex;
+use serde::Serialize;
+use std::fmt;
+
+use crate::color::{resolve_palette, ColorSpec, Derivation, Moment};
+use crate::config::{Condition, Config, Matcher, Rule, RuleMatch};
+use crate::css::Rgba;
+use crate::logger::Logger;
+use crate::process::{self, ProcessInfo};
+use crate::tiling::WindowTiling;
+use crate::util::{glob_match, hex_to_colorref};
+
+/// `window_rules` compiled once per config load: needles are lowercased
+/// and regexes compiled, so evaluating a window on every frame doesn't
+/// allocate or parse anything per rule.
+pub struct RuleSet {
+  rules: Vec<CompiledRule>,
+  // Indices into `rules`, each list in config order.
+  globals: Vec<usize>,
+  title_rules: Vec<usize>,
+  class_rules: Vec<usize>,
+  process_rules: Vec<usize>,
+  compound_rules: Vec<usize>,
+  uses_rainbow: bool,
+  uses_accent: bool,
+  is_animated: bool,
+  errors: Vec<String>,
+}
+
+struct CompiledRule {
+  // Position in `window_rules`, rules that can never match are left out.
+  index: usize,
+  condition: CompiledCondition,
+  active: ColorSpec,
+  inactive: ColorSpec,
+}
+
+enum CompiledCondition {
+  All(Vec<CompiledCondition>),
+  Any(Vec<CompiledCondition>),
+  Not(Box<CompiledCondition>),
+  Leaf(CompiledMatcher),
+}
+
+struct CompiledMatcher {
+  rule_match: RuleMatch,
+  contains: Vec<String>,
+  equals: Option<String>,
+  glob: Option<String>,
+  regex: Option<Regex>,
+}
+
+/// What rules get evaluated against. Lowercased strings are computed once
+/// per window and the process info is only looked up the first time a
+/// `Process` matcher asks for it.
+pub struct WindowInfo<'a> {
+  pub title: &'a str,
+  pub class: &'a str,
+  pub pid: u32,
+  /// Degrees this window's `rainbow` is ahead of the shared one.
+  pub hue_offset: f32,
+  /// Set for windows managed by a tiling window manager, otherwise
+  /// `Workspace`, `Layout` and `State` matchers never match.
+  pub tiling: Option<&'a WindowTiling>,
+  title_lower: String,
+  class_lower: String,
+  process: OnceCell<Option<(ProcessInfo, String, String)>>,
+}
+
+impl<'a> WindowInfo<'a> {
+  pub fn new(title: &'a str, class: &'a str, pid: u32) -> WindowInfo<'a> {
+    WindowInfo {
+      title,
+      class,
+      pid,
+      hue_offset: 0.0,
+      tiling: None,
+      title_lower: title.to_lowercase(),
+      class_lower: class.to_lowercase(),
+      process: OnceCell::new(),
+    }
+  }
+
+  pub fn with_hue_offset(mut self, hue_offset: f32) -> WindowInfo<'a> {
+    self.hue_offset = hue_offset;
+    self
+  }
+
+  /// Uses `process` for `Process` matchers instead of looking up the pid.
+  pub fn with_process(self, process: Option<ProcessInfo>) -> WindowInfo<'a> {
+    let _ = self.process.set(process.map(lowercase_process));
+    self
+  }
+
+  pub fn with_tiling(mut self, tiling: Option<&'a WindowTiling>) -> WindowInfo<'a> {
+    self.tiling = tiling;
+    self
+  }
+
+  fn process(&self) -> Option<&(ProcessInfo, String, String)> {
+    self
+      .process
+      .get_or_init(|| process::get_process_info(self.pid).map(lowercase_process))
+      .as_ref()
+  }
+}
+
+fn lowercase_process(process: ProcessInfo) -> (ProcessInfo, String, String) {
+  let name_lower = process.name.to_lowercase();
+  let path_lower = normalize_separators(process.path.to_lowercase());
+  (process, name_lower, path_lower)
+}
+
+/// `Process` paths match with either separator, so `C:/Program Files/*`
+/// works like `C:\Program Files\*`. Regexes see the path as it is.
+fn normalize_separators(path: String) -> String {
+  path.replace('/', "\\")
+}
+
+/// Why each rule did or didn't apply to a window, see `RuleSet::explain`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleTrace {
+  pub checks: Vec<RuleCheck>,
+  /// Index in `window_rules` of the rule that applies.
+  pub winner: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleCheck {
+  /// Index in `window_rules`.
+  pub index: usize,
+  pub is_global: bool,
+  pub matched: bool,
+  /// What the conditions found, e.g. `Title "Mozilla Firefox" contains "firefox"`.
+  pub reason: String,
+  pub outcome: RuleOutcome,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleOutcome {
+  Wins,
+  NoMatch,
+  /// Matched, but the rule at this index applies instead: an earlier
+  /// non-Global rule, or for Global rules any non-Global rule.
+  ShadowedBy(usize),
+  /// A Global rule replaced by a later Global rule.
+  OverriddenBy(usize),
+}
+
+impl RuleSet {
+  /// Fails on anything that should keep the config from loading, with the
+  /// path of the offending value in the message.
+  pub fn compile(config: &Config) -> Result<RuleSet, String> {
+    let backdrop = alpha_backdrop(config)?;
+    let palette = resolve_palette(&config.palette)?;
+    let color_for = |color: &ColorSpec, path: String| {
+      color
+        .lookup(&palette)
+        .and_then(|color| finish_color(color, backdrop))
+        .map_err(|e| format!("{}: {}", path, e))
+    };
+
+    let mut rule_set = RuleSet {
+      rules: Vec::new(),
+      globals: Vec::new(),
+      title_rules: Vec::new(),
+      class_rules: Vec::new(),
+      process_rules: Vec::new(),
+      compound_rules: Vec::new(),
+      uses_rainbow: false,
+      uses_accent: false,
+      is_animated: false,
+      errors: Vec::new(),
+    };
+
+    for (index, rule) in config.window_rules.iter().enumerate() {
+      let path = format!("window_rules[{}]", index);
+      let Some(condition) = compile_rule_condition(rule, &path)? else {
+        rule_set
+          .errors
+          .push(format!("{} has neither Match nor when, it will never match", path));
+        continue;
+      };
+
+      let compiled = CompiledRule {
+        index,
+        condition,
+        active: color_for(&rule.active_border_color, format!("{}.active_border_color", path))?,
+        inactive: color_for(&rule.inactive_border_color, format!("{}.inactive_border_color", path))?,
+      };
+      rule_set.uses_rainbow |= compiled.active.uses_rainbow() || compiled.inactive.uses_rainbow();
+      rule_set.uses_accent |= compiled.active.uses_accent() || compiled.inactive.uses_accent();
+      rule_set.is_animated |= compiled.active.is_animated() || compiled.inactive.is_animated();
+
+      let position = rule_set.rules.len();
+      let bucket = match (&rule.when, rule.matcher.as_ref().map(|m| &m.rule_match)) {
+        (None, Some(RuleMatch::Global)) => &mut rule_set.globals,
+        (None, Some(RuleMatch::Title)) => &mut rule_set.title_rules,
+        (None, Some(RuleMatch::Class)) => &mut rule_set.class_rules,
+        (None, Some(RuleMatch::Process)) => &mut rule_set.process_rules,
+        _ => &mut rule_set.compound_rules,
+      };
+      bucket.push(position);
+      rule_set.rules.push(compiled);
+    }
+
+    for error in &rule_set.errors {
+      Logger::log(&format!("[ERROR] {}", error));
+    }
+    Ok(rule_set)
+  }
+
+  /// Problems that don't keep the config from loading but make rules
+  /// useless: rules left out because they have no condition.
+  pub fn errors(&self) -> &[String] {
+    &self.errors
+  }
+
+  /// Whether any rule uses `rainbow`, i.e. whether the rainbow needs ticking.
+  pub fn uses_rainbow(&self) -> bool {
+    self.uses_rainbow
+  }
+
+  /// Whether any rule uses `rainbow` or an animation, i.e. whether borders
+  /// have to be repainted every frame.
+  pub fn is_animated(&self) -> bool {
+    self.is_animated
+  }
+
+  /// Whether any rule uses `accent`, i.e. whether accent changes need a repaint.
+  pub fn uses_accent(&self) -> bool {
+    self.uses_accent
+  }
+
+  /// Why windows without focus can end up with different colors, for
+  /// backends that only have one for all of them. `None` if every window
+  /// gets the same inactive color.
+  pub fn varying_inactive_colors(&self, per_window_rainbow: bool) -> Option<String> {
+    let first = self.rules.first()?;
+    if let Some(other) = self.rules.iter().find(|rule| rule.inactive != first.inactive) {
+      return Some(format!(
+        "window_rules[{}] and window_rules[{}] have different inactive_border_color",
+        first.index, other.index
+      ));
+    }
+    if per_window_rainbow && first.inactive.uses_rainbow() {
+      return Some("rainbow_offset gives each window its own inactive color".to_string());
+    }
+    None
+  }
+
+  /// Returns `(active, inactive)` colors for a window. The first matching
+  /// non-Global rule wins; without one the last Global rule applies.
+  /// `None` if no rule applies at all.
+  pub fn colors_for(&self, window: &WindowInfo) -> Option<(u32, u32)> {
+    self
+      .rule_for(window)
+      .map(|rule| self.colors_of(rule, window.hue_offset))
+  }
+
+  /// The index of the rule that applies to a window, see `colors_for`.
+  /// Only valid for this `RuleSet`; it stays the same as long as the
+  /// window's title, class, process and tiling do, so callers can cache it.
+  pub fn rule_for(&self, window: &WindowInfo) -> Option<usize> {
+    // Each bucket is in config order, so only rules before the best
+    // match found so far need to be checked.
+    let mut winner = usize::MAX;
+    for bucket in [
+      &self.title_rules,
+      &self.class_rules,
+      &self.process_rules,
+      &self.compound_rules,
+    ] {
+      for &position in bucket.iter().take_while(|&&p| p < winner) {
+        if self.rules[position].condition.matches(window) {
+          winner = position;
+          break;
+        }
+      }
+    }
+
+    if winner != usize::MAX {
+      Some(winner)
+    } else {
+      self.globals.last().copied()
+    }
+  }
+
+  /// The index in `window_rules` of a rule from `rule_for`.
+  pub fn config_index(&self, rule: usize) -> usize {
+    self.rules[rule].index
+  }
+
+  /// Goes through every rule like `rule_for` does and records whether it
+  /// matched, why, and whether it's the one that applies. Much slower
+  /// than `rule_for`, for troubleshooting only.
+  pub fn explain(&self, window: &WindowInfo) -> RuleTrace {
+    let winner = self.rule_for(window);
+    let winner_is_global = winner.is_some_and(|winner| self.globals.contains(&winner));
+    let checks = self
+      .rules
+      .iter()
+      .enumerate()
+      .map(|(position, rule)| {
+        let (matched, reason) = rule.condition.explain(window);
+        let is_global = self.globals.contains(&position);
+        let outcome = match winner {
+          Some(winner) if winner == position => RuleOutcome::Wins,
+          _ if !matched => RuleOutcome::NoMatch,
+          Some(winner) if is_global && winner_is_global => RuleOutcome::OverriddenBy(self.rules[winner].index),
+          Some(winner) => RuleOutcome::ShadowedBy(self.rules[winner].index),
+          // Something matched, so there is a winner.
+          None => RuleOutcome::NoMatch,
+        };
+        RuleCheck {
+          index: rule.index,
+          is_global,
+          matched,
+          reason,
+          outcome,
+        }
+      })
+      .collect();
+    RuleTrace {
+      checks,
+      winner: winner.map(|winner| self.rules[winner].index),
+    }
+  }
+
+  /// Resolves the `(active, inactive)` colors of a rule from `rule_for`.
+  pub fn colors_of(&self, rule: usize, hue_offset: f32) -> (u32, u32) {
+    self.colors_at(rule, &Moment::Live, hue_offset)
+  }
+
+  /// `colors_of` with the dynamic colors taken from `moment`.
+  pub fn colors_at(&self, rule: usize, moment: &Moment, hue_offset: f32) -> (u32, u32) {
+    let rule = &self.rules[rule];
+    (rule.active.resolve_at(moment, hue_offset), rule.inactive.resolve_at(moment, hue_offset))
+  }
+
+  /// The `(active, inactive)` colors of a rule as written, with palette
+  /// names looked up and alpha blended.
+  pub fn color_specs_of(&self, rule: usize) -> (&ColorSpec, &ColorSpec) {
+    let rule = &self.rules[rule];
+    (&rule.active, &rule.inactive)
+  }
+}
+
+/// The parsed `alpha_backdrop` of a config.
+pub(crate) fn alpha_backdrop(config: &Config) -> Result<Option<Rgba>, String> {
+  match &config.alpha_backdrop {
+    Some(backdrop) => Ok(Some(Rgba::from_colorref(
+      hex_to_colorref(backdrop).map_err(|e| format!("alpha_backdrop: {}", e))?,
+    ))),
+    None => Ok(None),
+  }
+}
+
+/// Blends translucent colors and computes fixed color functions, including
+/// the ones in animation stops.
+pub(crate) fn finish_color(color: ColorSpec, backdrop: Option<Rgba>) -> Result<ColorSpec, String> {
+  match color {
+    ColorSpec::Animated(animation) => Ok(ColorSpec::Animated(Box::new(
+      animation.map_colors(|stop| finish_color(stop.clone(), backdrop))?,
+    ))),
+    // Color functions work on opaque colors, the foreground of `over` is
+    // the only operand whose alpha is used.
+    ColorSpec::Derived(derivation) => {
+      let derivation = match *derivation {
+        Derivation::Over(foreground @ ColorSpec::Translucent(..), color) => {
+          Derivation::Over(foreground, finish_color(color, backdrop)?)
+        }
+        derivation => derivation.map_operands(|c| finish_color(c.clone(), backdrop))?,
+      };
+      Ok(ColorSpec::Derived(Box::new(derivation)).simplify())
+    }
+    color => blend_alpha(color, backdrop).map(ColorSpec::simplify),
+  }
+}
+
+/// DWM borders are always opaque, so colors with alpha are blended against
+/// `alpha_backdrop` once here. Without a backdrop they are rejected.
+fn blend_alpha(color: ColorSpec, backdrop: Option<Rgba>) -> Result<ColorSpec, String> {
+  match (&color, backdrop) {
+    (ColorSpec::Translucent(rgba, _), Some(backdrop)) => Ok(ColorSpec::Hex(rgba.over(backdrop).to_colorref())),
+    (ColorSpec::Translucent(..), None) => Err(format!(
+      "\"{}\" has an alpha channel but borders can't be translucent, set alpha_backdrop to blend it against a color",
+      color
+    )),
+    _ => Ok(color),
+  }
+}
+
+/// `None` for rules without `Match` and `when`. An invalid regex fails
+/// the config like an invalid color: a matcher that silently never
+/// matched would match every window under `not`.
+fn compile_rule_condition(rule: &Rule, path: &str) -> Result<Option<CompiledCondition>, String> {
+  let flat = rule
+    .matcher
+    .as_ref()
+    .map(|m| compile_matcher(m, path))
+    .transpose()?;
+  let when = rule
+    .when
+    .as_ref()
+    .map(|c| compile_condition(c, &format!("{}.when", path)))
+    .transpose()?;
+
+  Ok(match (flat, when) {
+    (Some(flat), Some(when)) => Some(CompiledCondition::All(vec![flat, when])),
+    (flat, when) => flat.or(when),
+  })
+}
+
+fn compile_condition(condition: &Condition, path: &str) -> Result<CompiledCondition, String> {
+  let compile_list = |conditions: &[Condition], path: String| {
+    conditions
+      .iter()
+      .enumerate()
+      .map(|(index, c)| compile_condition(c, &format!("{}[{}]", path, index)))
+      .collect::<Result<_, _>>()
+  };
+
+  Ok(match condition {
+    Condition::All(conditions) => {
+      CompiledCondition::All(compile_list(conditions, format!("{}.all", path))?)
+    }
+    Condition::Any(conditions) => {
+      CompiledCondition::Any(compile_list(conditions, format!("{}.any", path))?)
+    }
+    Condition::Not(condition) => CompiledCondition::Not(Box::new(compile_condition(
+      condition,
+      &format!("{}.not", path),
+    )?)),
+    Condition::Leaf(matcher) => compile_matcher(matcher, path)?,
+  })
+}
+
+fn compile_matcher(matcher: &Matcher, path: &str) -> Result<CompiledCondition, String> {
+  let regex = matcher
+    .regex
+    .as_ref()
+    .map(|pattern| Regex::new(pattern).map_err(|e| format!("{}.regex: {}", path, e)))
+    .transpose()?;
+
+  // Executable names have no separators, so they aren't affected.
+  let lowercase = |text: &String| match matcher.rule_match {
+    RuleMatch::Process => normalize_separators(text.to_lowercase()),
+    _ => text.to_lowercase(),
+  };
+  Ok(CompiledCondition::Leaf(CompiledMatcher {
+    rule_match: matcher.rule_match.clone(),
+    contains: matcher
+      .contains
+      .iter()
+      .flat_map(|c| c.iter())
+      .map(lowercase)
+      .collect(),
+    equals: matcher.equals.as_ref().map(lowercase),
+    glob: matcher.glob.as_ref().map(lowercase),
+    regex,
+  }))
+}
+
+impl CompiledCondition {
+  fn matches(&self, window: &WindowInfo) -> bool {
+    match self {
+      CompiledCondition::All(conditions) => conditions.iter().all(|c| c.matches(window)),
+      CompiledCondition::Any(conditions) => conditions.iter().any(|c| c.matches(window)),
+      CompiledCondition::Not(condition) => !condition.matches(window),
+      CompiledCondition::Leaf(matcher) => matcher.matches(window),
+    }
+  }
+}
+
+impl CompiledCondition {
+  /// `matches`, along with what decided it.
+  fn explain(&self, window: &WindowInfo) -> (bool, String) {
+    match self {
+      CompiledCondition::All(conditions) => {
+        let results: Vec<_> = conditions.iter().map(|c| c.explain(window)).collect();
+        match results.iter().find(|(matched, _)| !matched) {
+          Some((_, reason)) => (false, reason.clone()),
+          None => (true, join_reasons(&results, " and ")),
+        }
+      }
+      CompiledCondition::Any(conditions) => {
+        let results: Vec<_> = conditions.iter().map(|c| c.explain(window)).collect();
+        match results.iter().find(|(matched, _)| *matched) {
+          Some((_, reason)) => (true, reason.clone()),
+          None => (false, format!("none of: {}", join_reasons(&results, "; "))),
+        }
+      }
+      CompiledCondition::Not(condition) => {
+        let (matched, reason) = condition.explain(window);
+        (!matched, format!("not ({})", reason))
+      }
+      CompiledCondition::Leaf(matcher) => matcher.explain(window),
+    }
+  }
+}
+
+fn join_reasons(results: &[(bool, String)], separator: &str) -> String {
+  results
+    .iter()
+    .map(|(_, reason)| reason.as_str())
+    .collect::<Vec<_>>()
+    .join(separator)
+}
+
+impl CompiledMatcher {
+  fn matches(&self, window: &WindowInfo) -> bool {
+    match self.rule_match {
+      RuleMatch::Global => true,
+      RuleMatch::Title => self.matches_text(window.title, &window.title_lower),
+      RuleMatch::Class => self.matches_text(window.class, &window.class_lower),
+      // Process rules match against either the executable name or its full path,
+      // so both `equals: "firefox.exe"` and `glob: "C:\\Program Files\\*"` work.
+      RuleMatch::Process => window.process().is_some_and(|(p, name_lower, path_lower)| {
+        self.matches_text(&p.name, name_lower) || self.matches_text(&p.path, path_lower)
+      }),
+      RuleMatch::Workspace => window
+        .tiling
+        .is_some_and(|tiling| self.matches_lowercased(&tiling.workspace_name)),
+      RuleMatch::Layout => window
+        .tiling
+        .is_some_and(|tiling| self.matches_lowercased(&tiling.layout)),
+      RuleMatch::State => window.tiling.is_some_and(|tiling| {
+        tiling.states.iter().any(|state| self.matches_text(state, state))
+      }),
+    }
+  }
+
+  /// `matches`, along with what decided it.
+  fn explain(&self, window: &WindowInfo) -> (bool, String) {
+    let unmanaged = || (false, "not managed by a tiling window manager".to_string());
+    match self.rule_match {
+      RuleMatch::Global => (true, "Global".to_string()),
+      RuleMatch::Title => self.explain_text("Title", window.title, &window.title_lower),
+      RuleMatch::Class => self.explain_text("Class", window.class, &window.class_lower),
+      RuleMatch::Process => match window.process() {
+        Some((p, name_lower, path_lower)) => {
+          let (matched, name_reason) = self.explain_text("Process", &p.name, name_lower);
+          if matched {
+            return (true, name_reason);
+          }
+          let (matched, path_reason) = self.explain_text("Process path", &p.path, path_lower);
+          if matched {
+            (true, path_reason)
+          } else {
+            (false, format!("{}; {}", name_reason, path_reason))
+          }
+        }
+        None => (false, format!("process {} unknown", window.pid)),
+      },
+      RuleMatch::Workspace => match window.tiling {
+        Some(tiling) => self.explain_text(
+          "Workspace",
+          &tiling.workspace_name,
+          &tiling.workspace_name.to_lowercase(),
+        ),
+        None => unmanaged(),
+      },
+      RuleMatch::Layout => match window.tiling {
+        Some(tiling) => self.explain_text("Layout", &tiling.layout, &tiling.layout.to_lowercase()),
+        None => unmanaged(),
+      },
+      RuleMatch::State => match window.tiling {
+        Some(tiling) => {
+          let results: Vec<_> = tiling
+            .states
+            .iter()
+            .map(|state| self.explain_text("State", state, state))
+            .collect();
+          match results.iter().find(|(matched, _)| *matched) {
+            Some((_, reason)) => (true, reason.clone()),
+            None if results.is_empty() => (false, "no state".to_string()),
+            None => (false, join_reasons(&results, "; ")),
+          }
+        }
+        None => unmanaged(),
+      },
+    }
+  }
+
+  /// `matches_text`, describing each check, e.g. `Title "Mozilla Firefox" contains "firefox"`.
+  fn explain_text(&self, subject: &str, text: &str, text_lower: &str) -> (bool, String) {
+    if self.contains.is_empty() && self.equals.is_none() && self.glob.is_none() && self.regex.is_none() {
+      return (false, format!("{} has no contains, equals, glob or regex", subject));
+    }
+
+    let mut matched = true;
+    let mut checks = Vec::new();
+    if !self.contains.is_empty() {
+      match self.contains.iter().find(|c| text_lower.contains(c.as_str())) {
+        Some(needle) => checks.push(format!("contains {:?}", needle)),
+        None => {
+          matched = false;
+          checks.push(format!("doesn't contain {:?}", self.contains));
+        }
+      }
+    }
+    if let Some(equals) = &self.equals {
+      if text_lower == equals {
+        checks.push(format!("equals {:?}", equals));
+      } else {
+        matched = false;
+        checks.push(format!("isn't {:?}", equals));
+      }
+    }
+    if let Some(glob) = &self.glob {
+      if glob_match(glob, text_lower) {
+        checks.push(format!("matches glob {:?}", glob));
+      } else {
+        matched = false;
+        checks.push(format!("doesn't match glob {:?}", glob));
+      }
+    }
+    if let Some(regex) = &self.regex {
+      if regex.is_match(text) {
+        checks.push(format!("matches regex /{}/", regex));
+      } else {
+        matched = false;
+        checks.push(format!("doesn't match regex /{}/", regex));
+      }
+    }
+    (matched, format!("{} {:?} {}", subject, text, checks.join(", ")))
+  }
+
+  /// Tiling values are short and change rarely, they're lowercased when matched.
+  fn matches_lowercased(&self, text: &str) -> bool {
+    self.matches_text(text, &text.to_lowercase())
+  }
+
+  /// `contains`, `equals` and `glob` are case-insensitive, `regex` is not.
+  /// When more than one is set, all of them have to match.
+  fn matches_text(&self, text: &str, text_lower: &str) -> bool {
+    if self.contains.is_empty() && self.equals.is_none() && self.glob.is_none() && self.regex.is_none() {
+      return false;
+    }
+
+    (self.contains.is_empty() || self.contains.iter().any(|c| text_lower.contains(c.as_str())))
+      && self.equals.as_ref().is_none_or(|e| text_lower == e)
+      && self.glob.as_ref().is_none_or(|g| glob_match(g, text_lower))
+      && self.regex.as_ref().is_none_or(|re| re.is_match(text))
+  }
+}
+
+impl fmt::Display for RuleOutcome {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      RuleOutcome::Wins => write!(f, "wins"),
+      RuleOutcome::NoMatch => write!(f, "no match"),
+      RuleOutcome::ShadowedBy(index) => write!(f, "matched, but window_rules[{}] applies", index),
+      RuleOutcome::OverriddenBy(index) => write!(f, "matched, overridden by window_rules[{}]", index),
+    }
+  }
+}
+
+impl fmt::Display for RuleCheck {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "window_rules[{}] {}: {}", self.index, self.outcome, self.reason)
+  }
+}
+
+/// One line per rule, in config order.
+impl fmt::Display for RuleTrace {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for check in &self.checks {
+      writeln!(f, "  {}", check)?;
+    }
+    match self.winner {
+      Some(winner) => write!(f, "  rule: window_rules[{}]", winner),
+      None => write!(f, "  rule: none, the border is left alone"),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn rules(yaml: &str) -> RuleSet {
+    RuleSet::compile(&Config::from_yaml(yaml).unwrap()).unwrap()
+  }
+
+  #[test]
+  fn one_inactive_color_for_every_rule() {
+    let same = rules(
+      "window_rules:
+  - Match: Title
+    contains: vim
+    active_border_color: red
+    inactive_border_color: gray
+  - Match: Global
+    active_border_color: blue
+    inactive_border_color: gray
+",
+    );
+    assert_eq!(same.varying_inactive_colors(true), None);
+
+    let different = rules(
+      "window_rules:
+  - Match: Title
+    contains: vim
+    active_border_color: red
+  - Match: Global
+    active_border_color: blue
+    inactive_border_color: gray
+",
+    );
+    assert_eq!(
+      different.varying_inactive_colors(false).unwrap(),
+      "window_rules[0] and window_rules[1] have different inactive_border_color"
+    );
+  }
+
+  #[test]
+  fn explain_records_every_outcome() {
+    let rules = rules(
+      "window_rules:
+  - Match: Global
+    active_border_color: red
+  - Match: Title
+    contains: vim
+    active_border_color: green
+  - Match: Title
+    contains: v
+    active_border_color: blue
+  - Match: Class
+    equals: Notepad
+    active_border_color: blue
+  - Match: Global
+    active_border_color: gray
+",
+    );
+    let outcomes = |title| {
+      let trace = rules.explain(&WindowInfo::new(title, "Terminal", 0));
+      let outcomes: Vec<_> = trace.checks.iter().map(|check| check.outcome).collect();
+      (trace.winner, outcomes)
+    };
+
+    assert_eq!(
+      outcomes("vim"),
+      (
+        Some(1),
+        vec![
+          RuleOutcome::ShadowedBy(1),
+          RuleOutcome::Wins,
+          RuleOutcome::ShadowedBy(1),
+          RuleOutcome::NoMatch,
+          RuleOutcome::ShadowedBy(1),
+        ]
+      )
+    );
+    assert_eq!(
+      outcomes("bash"),
+      (
+        Some(4),
+        vec![
+          RuleOutcome::OverriddenBy(4),
+          RuleOutcome::NoMatch,
+          RuleOutcome::NoMatch,
+          RuleOutcome::NoMatch,
+          RuleOutcome::Wins,
+        ]
+      )
+    );
+  }
+
+  fn compile_error(yaml: &str) -> String {
+    RuleSet::compile(&Config::from_yaml(yaml).unwrap()).err().unwrap()
+  }
+
+  /// The rule that applies to a window with this title and class.
+  fn rule_for(rules: &RuleSet, title: &str, class: &str) -> Option<usize> {
+    let window = WindowInfo::new(title, class, 0).with_process(None);
+    rules.rule_for(&window).map(|rule| rules.config_index(rule))
+  }
+
+  #[test]
+  fn rules_without_a_condition_are_errors() {
+    let useless = rules(
+      "window_rules:
+  - active_border_color: red
+  - Match: Title
+    regex: '^vim'
+    active_border_color: red
+",
+    );
+    assert_eq!(
+      useless.errors(),
+      ["window_rules[0] has neither Match nor when, it will never match"]
+    );
+    assert_eq!(rule_for(&useless, "vim", ""), Some(1));
+  }
+
+  #[test]
+  fn invalid_regexes_fail_the_config() {
+    let error = compile_error(
+      "window_rules:
+  - Match: Title
+    regex: '('
+    active_border_color: red
+",
+    );
+    assert!(error.starts_with("window_rules[0].regex: regex parse error"), "{}", error);
+
+    // Under `not`, a regex that never matched would match every window.
+    let error = compile_error(
+      "window_rules:
+  - when:
+      any:
+        - Match: Class
+          equals: Notepad
+        - not:
+            Match: Title
+            regex: '[a-'
+    active_border_color: red
+",
+    );
+    assert!(error.starts_with("window_rules[0].when.any[1].not.regex: "), "{}", error);
+  }
+
+  #[test]
+  fn regexes_match_and_are_case_sensitive() {
+    let rules = rules(
+      "window_rules:
+  - Match: Title
+    regex: '^Vim \\d+$'
+    active_border_color: red
+  - Match: Class
+    regex: '(?i)^terminal$'
+    active_border_color: red
+",
+    );
+    assert_eq!(rule_for(&rules, "Vim 9", ""), Some(0));
+    assert_eq!(rule_for(&rules, "vim 9", ""), None);
+    assert_eq!(rule_for(&rules, "Vim 9 - notes", ""), None);
+    assert_eq!(rule_for(&rules, "", "TERMINAL"), Some(1));
+  }
+
+  #[test]
+  fn contains_equals_and_glob_ignore_case() {
+    let rules = rules(
+      "window_rules:
+  - Match: Title
+    contains: FIREFOX
+    active_border_color: red
+  - Match: Title
+    equals: Notepad
+    active_border_color: red
+  - Match: Class
+    glob: 'Chrome_*'
+    active_border_color: red
+  - Match: Class
+    contains: alacritty
+    regex: 'Alacritty'
+    active_border_color: red
+",
+    );
+    assert_eq!(rule_for(&rules, "Mozilla Firefox", ""), Some(0));
+    assert_eq!(rule_for(&rules, "NOTEPAD", ""), Some(1));
+    assert_eq!(rule_for(&rules, "Notepad - a.txt", ""), None);
+    assert_eq!(rule_for(&rules, "", "chrome_widgetwin_1"), Some(2));
+    // Both have to match, and only the regex cares about case.
+    assert_eq!(rule_for(&rules, "", "Alacritty"), Some(3));
+    assert_eq!(rule_for(&rules, "", "alacritty"), None);
+  }
+
+  fn process(path: &str) -> Option<ProcessInfo> {
+    Some(ProcessInfo {
+      name: path.rsplit(['\\', '/']).next().unwrap().to_string(),
+      path: path.to_string(),
+    })
+  }
+
+  #[test]
+  fn process_rules_match_the_name_or_the_path() {
+    let rules = rules(
+      r#"window_rules:
+  - Match: Process
+    equals: code.exe
+    active_border_color: red
+  - Match: Process
+    glob: 'C:\Program Files\JetBrains\*'
+    active_border_color: red
+  - Match: Process
+    contains: /usr/bin/
+    active_border_color: red
+"#,
+    );
+    let rule_for = |process| {
+      let window = WindowInfo::new("", "", 0).with_process(process);
+      rules.rule_for(&window).map(|rule| rules.config_index(rule))
+    };
+    // The name, in any case.
+    assert_eq!(rule_for(process(r"C:\Users\me\AppData\Code.EXE")), Some(0));
+    assert_eq!(rule_for(process(r"C:\Users\me\AppData\Code.exe.bak")), None);
+    // The path, with either separator.
+    assert_eq!(rule_for(process(r"c:\program files\jetbrains\idea64.exe")), Some(1));
+    assert_eq!(rule_for(process("C:/Program Files/JetBrains/idea64.exe")), Some(1));
+    assert_eq!(rule_for(process("/usr/bin/alacritty")), Some(2));
+    assert_eq!(rule_for(process(r"D:\Program Files\JetBrains\idea64.exe")), None);
+  }
+
+  #[test]
+  fn windows_without_a_process_only_match_negated_process_rules() {
+    let rules = rules(
+      "window_rules:
+  - Match: Process
+    glob: '*'
+    active_border_color: red
+  - when:
+      not:
+        Match: Process
+        equals: code.exe
+    active_border_color: blue
+",
+    );
+    let window = WindowInfo::new("", "", 0).with_process(None);
+    let rule = rules.rule_for(&window).map(|rule| rules.config_index(rule));
+    assert_eq!(rule, Some(1));
+    assert_eq!(rules.explain(&window).checks[0].reason, "process 0 unknown");
+    // Pid 0 is never looked up.
+    let window = WindowInfo::new("", "", 0);
+    let rule = rules.rule_for(&window).map(|rule| rules.config_index(rule));
+    assert_eq!(rule, Some(1));
+  }
+
+  #[test]
+  fn process_regexes_see_the_path_as_it_is() {
+    let rules = rules(
+      r#"window_rules:
+  - Match: Process
+    regex: '^C:\\Tools\\'
+    active_border_color: red
+"#,
+    );
+    let matches = |process| {
+      let window = WindowInfo::new("", "", 0).with_process(process);
+      rules.rule_for(&window).is_some()
+    };
+    assert!(matches(process(r"C:\Tools\a.exe")));
+    assert!(!matches(process("C:/Tools/a.exe")));
+    assert!(!matches(process(r"c:\tools\a.exe")));
+  }
+
+  #[test]
+  fn conditions_combine() {
+    let rules = rules(
+      "window_rules:
+  - when:
+      all:
+        - Match: Class
+          equals: Chrome_WidgetWin_1
+        - any:
+            - Match: Title
+              contains: Slack
+            - Match: Title
+              contains: Discord
+        - not:
+            Match: Title
+            contains: call
+    active_border_color: red
+  - Match: Title
+    contains: vim
+    when:
+      not:
+        any:
+          - Match: Class
+            equals: Alacritty
+          - Match: Class
+            equals: kitty
+    active_border_color: blue
+",
+    );
+    assert_eq!(rule_for(&rules, "Slack | general", "Chrome_WidgetWin_1"), Some(0));
+    assert_eq!(rule_for(&rules, "Discord", "Chrome_WidgetWin_1"), Some(0));
+    assert_eq!(rule_for(&rules, "Slack | call", "Chrome_WidgetWin_1"), None);
+    assert_eq!(rule_for(&rules, "Slack", "MozillaWindowClass"), None);
+    // The flat Match and `when` both have to match.
+    assert_eq!(rule_for(&rules, "vim", "WindowsTerminal"), Some(1));
+    assert_eq!(rule_for(&rules, "vim", "kitty"), None);
+    assert_eq!(rule_for(&rules, "emacs", "WindowsTerminal"), None);
+  }
+
+  #[test]
+  fn empty_all_matches_everything_and_empty_any_nothing() {
+    let rules = rules(
+      "window_rules:
+  - when:
+      any: []
+    active_border_color: red
+  - when:
+      all: []
+    active_border_color: blue
+",
+    );
+    assert_eq!(rule_for(&rules, "anything", "Any"), Some(1));
+    assert_eq!(rule_for(&rules, "", ""), Some(1));
+
+    let trace = rules.explain(&WindowInfo::new("", "", 0));
+    assert!(!trace.checks[0].matched);
+    assert!(trace.checks[1].matched);
+  }
+
+  #[test]
+  fn explain_follows_the_tree() {
+    let rules = rules(
+      "window_rules:
+  - when:
+      all:
+        - Match: Title
+          contains: vim
+        - not:
+            Match: Class
+            equals: kitty
+    active_border_color: red
+",
+    );
+    let reason = |title, class| {
+      let trace = rules.explain(&WindowInfo::new(title, class, 0));
+      trace.checks[0].reason.clone()
+    };
+    assert_eq!(
+      reason("vim", "Alacritty"),
+      "Title \"vim\" contains \"vim\" and not (Class \"Alacritty\" isn't \"kitty\")"
+    );
+    assert_eq!(reason("vim", "kitty"), "not (Class \"kitty\" equals \"kitty\")");
+  }
+
+  #[test]
+  fn buckets_keep_config_order() {
+    let config = Config::from_yaml(
+      "window_rules:
+  - Match: Global
+    active_border_color: red
+  - Match: Class
+    equals: Chrome_WidgetWin_1
+    active_border_color: red
+  - Match: Title
+    contains: slack
+    active_border_color: red
+  - when:
+      any:
+        - Match: Title
+          contains: code
+        - Match: Process
+          equals: code.exe
+    active_border_color: red
+  - Match: Process
+    glob: '*.exe'
+    active_border_color: red
+  - Match: Title
+    contains: visual studio
+    active_border_color: red
+  - Match: Class
+    contains: window
+    active_border_color: red
+  - Match: Global
+    active_border_color: blue
+",
+    )
+    .unwrap();
+    let rules = RuleSet::compile(&config).unwrap();
+    // What the paint loop did before the RuleSet: every rule in config
+    // order, the first non-Global match wins, otherwise the last Global.
+    let alone: Vec<RuleSet> = config
+      .window_rules
+      .iter()
+      .map(|rule| {
+        let config = Config {
+          window_rules: vec![rule.clone()],
+          ..config.clone()
+        };
+        RuleSet::compile(&config).unwrap()
+      })
+      .collect();
+    let linear = |window: &WindowInfo| {
+      let matches = |index: usize| alone[index].rule_for(window).is_some();
+      (0..alone.len())
+        .find(|&index| !config.window_rules[index].is_global() && matches(index))
+        .or_else(|| config.window_rules.iter().rposition(Rule::is_global))
+    };
+
+    let titles = ["Slack", "Visual Studio Code", "Visual Studio", "notes", ""];
+    let classes = ["Chrome_WidgetWin_1", "ConsoleWindowClass", "Notepad"];
+    let processes = [
+      None,
+      process(r"C:\Apps\Code.exe"),
+      process(r"C:\Apps\Notepad.exe"),
+      process("/usr/bin/slack"),
+    ];
+    let mut seen = Vec::new();
+    for title in titles {
+      for class in classes {
+        for process in &processes {
+          let window = || WindowInfo::new(title, class, 0).with_process(process.clone());
+          let expected = linear(&window());
+          let rule = rules.rule_for(&window()).map(|rule| rules.config_index(rule));
+          assert_eq!(rule, expected, "{:?} {:?} {:?}", title, class, process);
+          seen.push(expected);
+        }
+      }
+    }
+    // Every rule but the overridden Global wins somewhere.
+    seen.sort();
+    seen.dedup();
+    assert_eq!(seen, (1..=7).map(Some).collect::<Vec<_>>());
+  }
+
+  #[test]
+  fn rainbow_offsets_vary_per_window() {
+    let rainbow = rules(
+      "window_rules:
+  - Match: Global
+    active_border_color: red
+    inactive_border_color: rainbow
+",
+    );
+    assert_eq!(rainbow.varying_inactive_colors(false), None);
+    assert!(rainbow.varying_inactive_colors(true).is_some());
+  }
+}
@@ -0,0 +1,139 @@
+use check_elevation::is_elevated;
+use planif::{
+  enums::TaskCreationFlags,
+  schedule::TaskScheduler,
+  schedule_builder::{Action, ScheduleBuilder},
+  settings::{Duration, LogonType, PrincipalSettings, RunLevel, Settings},
+};
+use std::ffi::CString;
+use std::{
+  env, fs,
+  path::{Path, PathBuf},
+};
+use winapi::um::winnt::{KEY_READ, KEY_WRITE};
+use winapi::um::winuser::{MessageBoxA, MB_ICONERROR, MB_OK};
+use winreg::{enums::HKEY_CURRENT_USER, RegKey};
+
+use cute_borders::logger::Logger;
+use cute_borders::util::get_file_path;
+
+fn clean_old_registry_key() {
+  let key = match RegKey::predef(HKEY_CURRENT_USER).open_subkey_with_flags(
+    "Software\\Microsoft\\Windows\\CurrentVersion\\Run",
+    KEY_READ | KEY_WRITE,
+  ) {
+    Ok(key) => Some(key),
+    Err(_) => None,
+  };
+
+  if let Some(key) = key {
+    let _ = key.delete_value("cute-borders");
+  }
+}
+
+pub fn get_exe_path() -> PathBuf {
+  let exe_path: PathBuf = match env::current_exe() {
+    Ok(path) => path,
+    Err(err) => {
+      Logger::log("[ERROR] Failed to find own executable path");
+      Logger::log(&format!("[DEBUG] {:?}", err));
+      std::process::exit(1);
+    }
+  };
+
+  let new_exe_path = match get_file_path("cute-borders.exe") {
+    Ok(path) => PathBuf::from(path),
+    Err(err) => {
+      Logger::log(&format!("[ERROR] {}", err));
+      std::process::exit(1);
+    }
+  };
+
+  if exe_path != new_exe_path {
+    if Path::new(&new_exe_path).exists() {
+      match fs::remove_file(&new_exe_path) {
+        Ok(_) => {}
+        Err(_err) => {
+          unsafe {
+            let title = CString::new("Failed to update").unwrap();
+            let message = CString::new(
+              "Please close currently running cute-borders to be able to update to this version.",
+            )
+            .unwrap();
+            MessageBoxA(
+              std::ptr::null_mut(),
+              message.as_ptr(),
+              title.as_ptr(),
+              MB_OK | MB_ICONERROR,
+            );
+          }
+          std::process::exit(1);
+        }
+      }
+    }
+
+    match fs::copy(&exe_path, &new_exe_path) {
+      Ok(_) => {}
+      Err(err) => {
+        Logger::log(&format!(
+          "[ERROR] Failed to copy file: {} to: {}",
+          &exe_path.to_string_lossy(),
+          &new_exe_path.to_string_lossy()
+        ));
+        Logger::log(&format!("[DEBUG] {:?}", err));
+        std::process::exit(1);
+      }
+    }
+  }
+
+  return new_exe_path;
+}
+
+pub fn set_startup(enabled: bool) -> Result<(), Box<dyn std::error::Error>> {
+  clean_old_registry_key();
+  let exe_path = get_exe_path();
+  let is_elevated = is_elevated().unwrap_or(false);
+
+  if !is_elevated {
+    return Ok(());
+  }
+
+  let ts = TaskScheduler::new()?;
+  let com = ts.get_com();
+  let sb = ScheduleBuilder::new(&com).unwrap();
+
+  let mut settings = Settings::new();
+  settings.stop_if_going_on_batteries = Some(false);
+  settings.disallow_start_if_on_batteries = Some(false);
+  settings.enabled = Some(true);
+
+  let action = Action::new("cute-borders-action", &exe_path.to_string_lossy(), "", "");
+
+  let delay = Duration {
+    seconds: Some(5),
+    // see https://github.com/mattrobineau/planif/commit/ac2e7f79ec8de8935c6292d64533a6c7ce37212e
+    // github has 1.0.1 but crates.io doesnt
+    hours: Some(0),
+    ..Default::default()
+  };
+
+  sb.create_logon()
+    .settings(settings)?
+    .author("keifufu")?
+    .description("cute-borders startup")?
+    .principal(PrincipalSettings {
+      display_name: "".to_string(),
+      group_id: None,
+      id: "".to_string(),
+      logon_type: LogonType::Password,
+      run_level: RunLevel::Highest,
+      user_id: None,
+    })?
+    .trigger("cute-borders-trigger", enabled)?
+    .delay(delay)?
+    .action(action)?
+    .build()?
+    .register("cute-borders", TaskCreationFlags::CreateOrUpdate as i32)?;
+
+  Ok(())
+}
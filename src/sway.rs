@@ -0,0 +1,748 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::backend::{EventHandler, Rect, WindowAttributes, WindowBackend, WindowEvent};
+use crate::logger::Logger;
+use crate::{DWMWA_COLOR_DEFAULT, DWMWA_COLOR_NONE};
+
+const MAGIC: &[u8; 6] = b"i3-ipc";
+const RUN_COMMAND: u32 = 0;
+const SUBSCRIBE: u32 = 2;
+const GET_TREE: u32 = 4;
+const GET_CONFIG: u32 = 9;
+// Events have the highest bit set, window events are event type 3.
+const WINDOW_EVENT: u32 = 0x8000_0003;
+
+/// Writes one message of the i3/sway IPC protocol: the magic string, the
+/// payload length and message type in native byte order, then the payload.
+pub fn write_message(stream: &mut impl Write, kind: u32, payload: &[u8]) -> io::Result<()> {
+  let mut message = Vec::with_capacity(14 + payload.len());
+  message.extend_from_slice(MAGIC);
+  message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+  message.extend_from_slice(&kind.to_ne_bytes());
+  message.extend_from_slice(payload);
+  stream.write_all(&message)
+}
+
+/// Reads one message, returns its type and payload.
+pub fn read_message(stream: &mut impl Read) -> io::Result<(u32, Vec<u8>)> {
+  let mut header = [0u8; 14];
+  stream.read_exact(&mut header)?;
+  if &header[..6] != MAGIC {
+    return Err(io::Error::new(
+      io::ErrorKind::InvalidData,
+      "not an i3 IPC message",
+    ));
+  }
+  let length = u32::from_ne_bytes(header[6..10].try_into().unwrap());
+  let kind = u32::from_ne_bytes(header[10..14].try_into().unwrap());
+  let mut payload = vec![0u8; length as usize];
+  stream.read_exact(&mut payload)?;
+  Ok((kind, payload))
+}
+
+/// A node of `get_tree`, also the `container` of window events. Only the
+/// fields we use; sway and i3 send many more.
+#[derive(Debug, Deserialize)]
+struct Node {
+  id: usize,
+  #[serde(default)]
+  name: Option<String>,
+  /// Wayland windows on sway.
+  #[serde(default)]
+  app_id: Option<String>,
+  /// X11 windows, on i3 and Xwayland.
+  #[serde(default)]
+  window_properties: Option<WindowProperties>,
+  /// Only sway knows the pid.
+  #[serde(default)]
+  pid: Option<u32>,
+  #[serde(default)]
+  focused: bool,
+  rect: NodeRect,
+  #[serde(default)]
+  nodes: Vec<Node>,
+  #[serde(default)]
+  floating_nodes: Vec<Node>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WindowProperties {
+  #[serde(default)]
+  class: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NodeRect {
+  x: i32,
+  y: i32,
+  width: i32,
+  height: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct WindowEventPayload {
+  change: String,
+  container: Node,
+}
+
+/// The reply to `get_config`. sway also sends the files the config
+/// includes, i3 only the main one.
+#[derive(Debug, Deserialize)]
+struct ConfigReply {
+  config: String,
+  #[serde(default)]
+  included_configs: Vec<IncludedConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IncludedConfig {
+  raw_contents: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommandResult {
+  success: bool,
+  #[serde(default)]
+  error: Option<String>,
+}
+
+impl Node {
+  /// Containers holding an application window, as opposed to splits,
+  /// workspaces and outputs.
+  fn is_window(&self) -> bool {
+    self.app_id.is_some() || self.window_properties.is_some()
+  }
+
+  fn attributes(&self) -> WindowAttributes {
+    let class = self
+      .app_id
+      .clone()
+      .or_else(|| {
+        self
+          .window_properties
+          .as_ref()
+          .and_then(|properties| properties.class.clone())
+      })
+      .unwrap_or_default();
+    WindowAttributes {
+      title: self.name.clone().unwrap_or_default(),
+      class,
+      pid: self.pid.unwrap_or(0),
+      is_menu: false,
+    }
+  }
+
+  fn bounds(&self) -> Rect {
+    Rect {
+      left: self.rect.x,
+      top: self.rect.y,
+      right: self.rect.x + self.rect.width,
+      bottom: self.rect.y + self.rect.height,
+    }
+  }
+
+  fn collect_windows<'a>(&'a self, windows: &mut Vec<&'a Node>) {
+    if self.is_window() {
+      windows.push(self);
+    }
+    for child in self.nodes.iter().chain(&self.floating_nodes) {
+      child.collect_windows(windows);
+    }
+  }
+}
+
+/// The `client.*` classes cute-borders sets. `focused_inactive` is the
+/// focused window of a workspace that isn't, which looks unfocused.
+const CLIENT_CLASSES: [&str; 3] = ["focused", "focused_inactive", "unfocused"];
+
+/// sway's and i3's colors for a config without `client.*` lines.
+const DEFAULT_CLIENT_COLORS: [&str; 3] = [
+  "client.focused #4c7899 #285577 #ffffff #2e9ef4 #285577",
+  "client.focused_inactive #333333 #5f676a #ffffff #484e50 #5f676a",
+  "client.unfocused #333333 #222222 #888888 #292d2e #222222",
+];
+
+/// Borders on sway and i3, driven over their IPC socket. Windows are
+/// identified by container id, `app_id` (or the X11 class) is the class.
+///
+/// Neither has per-container border colors (no command or criteria sets
+/// them), only the `client.*` colors: one for the focused window and one
+/// for the rest. The focused window gets its own color, the others share
+/// the one most of them asked for, see `has_per_window_colors`. Default
+/// and transparent put back the colors from the sway or i3 config.
+pub struct SwayBackend {
+  socket: PathBuf,
+  // Commands and queries. Events come in on a second connection.
+  commands: Mutex<UnixStream>,
+  state: Mutex<SwayState>,
+  // The `client.*` commands from the config, by class, to restore them.
+  original: HashMap<&'static str, String>,
+}
+
+#[derive(Default)]
+struct SwayState {
+  windows: HashMap<usize, SwayWindow>,
+  focused: Option<usize>,
+  desktop: Rect,
+  // The color the paint loop asked for, per window.
+  colors: HashMap<usize, u32>,
+  // The `client.*` command last run, by class.
+  applied: HashMap<&'static str, String>,
+}
+
+struct SwayWindow {
+  attributes: WindowAttributes,
+  bounds: Rect,
+}
+
+impl SwayBackend {
+  /// Connects to the socket in `$SWAYSOCK` or `$I3SOCK`. `None` when
+  /// neither is set, i.e. when we're not running under sway or i3.
+  pub fn from_env() -> Option<io::Result<SwayBackend>> {
+    let socket = std::env::var_os("SWAYSOCK").or_else(|| std::env::var_os("I3SOCK"))?;
+    Some(SwayBackend::connect(Path::new(&socket)))
+  }
+
+  pub fn connect(socket: &Path) -> io::Result<SwayBackend> {
+    let mut backend = SwayBackend {
+      socket: socket.to_path_buf(),
+      commands: Mutex::new(UnixStream::connect(socket)?),
+      state: Mutex::new(SwayState::default()),
+      original: HashMap::new(),
+    };
+    let config = backend
+      .request(GET_CONFIG, "")
+      .map_err(|e| e.to_string())
+      .and_then(|reply| serde_json::from_slice::<ConfigReply>(&reply).map_err(|e| e.to_string()));
+    let config = match config {
+      Ok(config) => config,
+      Err(e) => {
+        Logger::log(&format!(
+          "[ERROR] Failed to read the config, borders will be reset to the default colors: {}",
+          e
+        ));
+        ConfigReply {
+          config: String::new(),
+          included_configs: Vec::new(),
+        }
+      }
+    };
+    backend.original = original_client_colors(&config);
+    // Focus is only known from the tree and events, read it before the first frame asks.
+    backend.enumerate();
+    Ok(backend)
+  }
+
+  /// Sends a message on the command connection and waits for its reply.
+  fn request(&self, kind: u32, payload: &str) -> io::Result<Vec<u8>> {
+    let mut stream = self.commands.lock().unwrap();
+    write_message(&mut *stream, kind, payload.as_bytes())?;
+    let (_, reply) = read_message(&mut *stream)?;
+    Ok(reply)
+  }
+
+  fn run_command(&self, command: &str) {
+    let results = self
+      .request(RUN_COMMAND, command)
+      .map_err(|e| e.to_string())
+      .and_then(|reply| {
+        serde_json::from_slice::<Vec<CommandResult>>(&reply).map_err(|e| e.to_string())
+      });
+    match results {
+      Ok(results) => {
+        for result in results.iter().filter(|result| !result.success) {
+          Logger::log(&format!(
+            "[ERROR] \"{}\" failed: {}",
+            command,
+            result.error.as_deref().unwrap_or("unknown error")
+          ));
+        }
+      }
+      Err(e) => Logger::log(&format!("[ERROR] Failed to run \"{}\": {}", command, e)),
+    }
+  }
+
+  /// The `client.*` command for each class given what the windows asked
+  /// for. A class no window asks a color for gets its color from the config.
+  fn client_commands(&self, state: &SwayState) -> Vec<(&'static str, String)> {
+    let focused_color = state
+      .focused
+      .and_then(|focused| state.colors.get(&focused).copied());
+    let unfocused_color = most_common(
+      state
+        .colors
+        .iter()
+        .filter(|(window, _)| Some(**window) != state.focused)
+        .map(|(_, color)| *color),
+    );
+    CLIENT_CLASSES
+      .into_iter()
+      .map(|class| {
+        let color = if class == "focused" {
+          focused_color
+        } else {
+          unfocused_color
+        };
+        let command = color
+          .and_then(|color| client_color(class, color))
+          .unwrap_or_else(|| self.original[class].clone());
+        (class, command)
+      })
+      .collect()
+  }
+
+  /// Updates the cached window from the container of a window event.
+  fn apply_event(&self, change: &str, container: &Node) -> Option<WindowEvent> {
+    let mut state = self.state.lock().unwrap();
+    let window = container.id;
+    match change {
+      "new" | "title" | "focus" | "move" | "floating" | "fullscreen_mode" => {
+        state.windows.insert(
+          window,
+          SwayWindow {
+            attributes: container.attributes(),
+            bounds: container.bounds(),
+          },
+        );
+      }
+      "close" => {
+        state.windows.remove(&window);
+        state.colors.remove(&window);
+        if state.focused == Some(window) {
+          state.focused = None;
+        }
+      }
+      _ => {}
+    }
+    match change {
+      "new" => Some(WindowEvent::Created(window)),
+      "close" => Some(WindowEvent::Destroyed(window)),
+      "title" => Some(WindowEvent::NameChanged(window)),
+      "focus" => {
+        state.focused = Some(window);
+        Some(WindowEvent::Foreground(window))
+      }
+      "move" | "floating" | "fullscreen_mode" => Some(WindowEvent::Shown(window)),
+      _ => None,
+    }
+  }
+}
+
+impl WindowBackend for SwayBackend {
+  fn enumerate(&self) -> Vec<usize> {
+    let tree = match self
+      .request(GET_TREE, "")
+      .map_err(|e| e.to_string())
+      .and_then(|reply| serde_json::from_slice::<Node>(&reply).map_err(|e| e.to_string()))
+    {
+      Ok(tree) => tree,
+      Err(e) => {
+        Logger::log(&format!("[ERROR] Failed to get the window tree: {}", e));
+        return Vec::new();
+      }
+    };
+
+    let mut nodes = Vec::new();
+    tree.collect_windows(&mut nodes);
+    let mut state = self.state.lock().unwrap();
+    state.desktop = tree.bounds();
+    state.focused = nodes.iter().find(|node| node.focused).map(|node| node.id);
+    state.windows = nodes
+      .iter()
+      .map(|node| {
+        (
+          node.id,
+          SwayWindow {
+            attributes: node.attributes(),
+            bounds: node.bounds(),
+          },
+        )
+      })
+      .collect();
+    nodes.iter().map(|node| node.id).collect()
+  }
+
+  fn describe(&self, window: usize) -> Option<WindowAttributes> {
+    let state = self.state.lock().unwrap();
+    state
+      .windows
+      .get(&window)
+      .map(|window| window.attributes.clone())
+  }
+
+  fn is_alive(&self, window: usize) -> bool {
+    self.state.lock().unwrap().windows.contains_key(&window)
+  }
+
+  fn foreground(&self) -> Option<usize> {
+    self.state.lock().unwrap().focused
+  }
+
+  // Tiling window managers have no owned windows, dialogs are containers of their own.
+  fn owner(&self, _window: usize) -> Option<usize> {
+    None
+  }
+
+  fn pid(&self, window: usize) -> u32 {
+    let state = self.state.lock().unwrap();
+    state
+      .windows
+      .get(&window)
+      .map_or(0, |window| window.attributes.pid)
+  }
+
+  fn bounds(&self, window: usize) -> Option<Rect> {
+    let state = self.state.lock().unwrap();
+    state.windows.get(&window).map(|window| window.bounds)
+  }
+
+  fn desktop(&self) -> Rect {
+    self.state.lock().unwrap().desktop
+  }
+
+  fn set_border_color(&self, window: usize, color: u32) {
+    self.state.lock().unwrap().colors.insert(window, color);
+  }
+
+  /// Runs the `client.*` commands that changed since the last frame. Done
+  /// once per frame, while the paint loop goes through the windows the
+  /// ones it already painted would disagree with the rest.
+  fn flush(&self) {
+    let mut state = self.state.lock().unwrap();
+    for (class, command) in self.client_commands(&state) {
+      if state.applied.get(class) != Some(&command) {
+        self.run_command(&command);
+        state.applied.insert(class, command);
+      }
+    }
+  }
+
+  fn has_per_window_colors(&self) -> bool {
+    false
+  }
+
+  fn subscribe(&self, mut on_event: EventHandler) {
+    let mut stream = match UnixStream::connect(&self.socket) {
+      Ok(stream) => stream,
+      Err(e) => {
+        Logger::log(&format!(
+          "[ERROR] Failed to connect to {:?}: {}",
+          self.socket, e
+        ));
+        return;
+      }
+    };
+    if let Err(e) = write_message(&mut stream, SUBSCRIBE, br#"["window"]"#)
+      .and_then(|_| read_message(&mut stream))
+    {
+      Logger::log(&format!(
+        "[ERROR] Failed to subscribe to window events: {}",
+        e
+      ));
+      return;
+    }
+
+    loop {
+      let (kind, payload) = match read_message(&mut stream) {
+        Ok(message) => message,
+        Err(e) => {
+          Logger::log(&format!("[ERROR] Lost the window event connection: {}", e));
+          return;
+        }
+      };
+      if kind != WINDOW_EVENT {
+        continue;
+      }
+      let event = match serde_json::from_slice::<WindowEventPayload>(&payload) {
+        Ok(event) => event,
+        Err(e) => {
+          Logger::log(&format!("[ERROR] Failed to parse a window event: {}", e));
+          continue;
+        }
+      };
+      if let Some(window_event) = self.apply_event(&event.change, &event.container) {
+        on_event(window_event);
+      }
+    }
+  }
+}
+
+/// The `client.<class>` command for a COLORREF. The title text is black or
+/// white, whichever is easier to read on it. `None` for the default border
+/// and no border, which can't be expressed as a `client.*` color.
+fn client_color(class: &str, color: u32) -> Option<String> {
+  if color == DWMWA_COLOR_DEFAULT || color == DWMWA_COLOR_NONE {
+    return None;
+  }
+  let (r, g, b) = (color & 0xFF, (color >> 8) & 0xFF, (color >> 16) & 0xFF);
+  let hex = format!("#{:02x}{:02x}{:02x}", r, g, b);
+  let luma = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+  let text = if luma > 150.0 { "#000000" } else { "#ffffff" };
+  Some(format!(
+    "client.{} {} {} {} {} {}",
+    class, hex, hex, text, hex, hex
+  ))
+}
+
+/// The `client.*` commands of a sway or i3 config, by class, with `$`
+/// variables from `set` filled in. Classes the config doesn't set get
+/// the default colors.
+fn original_client_colors(config: &ConfigReply) -> HashMap<&'static str, String> {
+  let lines: Vec<&str> = std::iter::once(config.config.as_str())
+    .chain(
+      config
+        .included_configs
+        .iter()
+        .map(|c| c.raw_contents.as_str()),
+    )
+    .flat_map(str::lines)
+    .map(str::trim)
+    .collect();
+  let mut variables: HashMap<&str, &str> = HashMap::new();
+  for line in &lines {
+    let mut words = line.split_whitespace();
+    if let (Some("set"), Some(name), Some(value)) = (words.next(), words.next(), words.next()) {
+      variables.insert(name, value);
+    }
+  }
+
+  let mut colors: HashMap<&'static str, String> = CLIENT_CLASSES
+    .into_iter()
+    .zip(DEFAULT_CLIENT_COLORS)
+    .map(|(class, command)| (class, command.to_string()))
+    .collect();
+  for line in &lines {
+    let mut words = line.split_whitespace();
+    let Some(class) = words
+      .next()
+      .and_then(|word| word.strip_prefix("client."))
+      .and_then(|class| CLIENT_CLASSES.into_iter().find(|c| *c == class))
+    else {
+      continue;
+    };
+    let values: Vec<&str> = words
+      .map(|word| variables.get(word).copied().unwrap_or(word))
+      .collect();
+    colors.insert(class, format!("client.{} {}", class, values.join(" ")));
+  }
+  colors
+}
+
+/// The value that occurs most often, the smallest one on a tie so the
+/// result doesn't depend on iteration order.
+fn most_common(values: impl Iterator<Item = u32>) -> Option<u32> {
+  let mut counts: HashMap<u32, usize> = HashMap::new();
+  for value in values {
+    *counts.entry(value).or_default() += 1;
+  }
+  counts
+    .into_iter()
+    .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then(b.cmp(a)))
+    .map(|(value, _)| value)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::os::unix::net::UnixListener;
+  use std::sync::Arc;
+
+  type Commands = Arc<Mutex<Vec<String>>>;
+
+  /// A sway that answers from fixed JSON: `get_tree` with `tree`,
+  /// `get_config` with `config`, records commands, and sends `events` to
+  /// subscribers before hanging up.
+  fn fake_sway(name: &str, tree: String, config: &str, events: Vec<String>) -> (PathBuf, Commands) {
+    let socket = std::env::temp_dir().join(format!(
+      "cute-borders-sway-{}-{}.sock",
+      std::process::id(),
+      name
+    ));
+    let _ = std::fs::remove_file(&socket);
+    let listener = UnixListener::bind(&socket).unwrap();
+    let commands = Commands::default();
+    let config = serde_json::json!({ "config": config }).to_string();
+    let recorded = commands.clone();
+    std::thread::spawn(move || {
+      for stream in listener.incoming() {
+        let mut stream = stream.unwrap();
+        let (tree, config, events, recorded) = (
+          tree.clone(),
+          config.clone(),
+          events.clone(),
+          recorded.clone(),
+        );
+        std::thread::spawn(move || {
+          while let Ok((kind, payload)) = read_message(&mut stream) {
+            let reply = match kind {
+              GET_TREE => tree.clone(),
+              GET_CONFIG => config.clone(),
+              RUN_COMMAND => {
+                recorded
+                  .lock()
+                  .unwrap()
+                  .push(String::from_utf8(payload).unwrap());
+                r#"[{"success":true}]"#.to_string()
+              }
+              SUBSCRIBE => {
+                write_message(&mut stream, SUBSCRIBE, br#"{"success":true}"#).unwrap();
+                for event in &events {
+                  write_message(&mut stream, WINDOW_EVENT, event.as_bytes()).unwrap();
+                }
+                return;
+              }
+              _ => panic!("unexpected message {}", kind),
+            };
+            write_message(&mut stream, kind, reply.as_bytes()).unwrap();
+          }
+        });
+      }
+    });
+    (socket, commands)
+  }
+
+  fn con(id: usize, name: &str, app_id: &str, focused: bool) -> serde_json::Value {
+    serde_json::json!({
+      "id": id,
+      "name": name,
+      "app_id": app_id,
+      "pid": 100 + id,
+      "focused": focused,
+      "rect": { "x": 10 * id, "y": 0, "width": 100, "height": 50 },
+      "nodes": [],
+    })
+  }
+
+  fn tree() -> String {
+    let xwayland = serde_json::json!({
+      "id": 3,
+      "name": "Mozilla Firefox",
+      "window_properties": { "class": "firefox" },
+      "focused": false,
+      "rect": { "x": 0, "y": 0, "width": 800, "height": 600 },
+    });
+    serde_json::json!({
+      "id": 0,
+      "rect": { "x": 0, "y": 0, "width": 1920, "height": 1080 },
+      "nodes": [{
+        "id": 10,
+        "name": "1",
+        "rect": { "x": 0, "y": 0, "width": 1920, "height": 1080 },
+        "nodes": [con(1, "vim", "foot", true), con(2, "htop", "foot", false)],
+        "floating_nodes": [xwayland],
+      }],
+    })
+    .to_string()
+  }
+
+  #[test]
+  fn enumerate_reads_the_tree() {
+    let (socket, _) = fake_sway("tree", tree(), "", Vec::new());
+    let sway = SwayBackend::connect(&socket).unwrap();
+    assert_eq!(sway.enumerate(), [1, 2, 3]);
+    assert_eq!(sway.foreground(), Some(1));
+    let firefox = sway.describe(3).unwrap();
+    assert_eq!(
+      (firefox.title.as_str(), firefox.class.as_str()),
+      ("Mozilla Firefox", "firefox")
+    );
+    assert_eq!(sway.pid(2), 102);
+    assert_eq!(sway.bounds(2).unwrap().left, 20);
+    assert_eq!(sway.desktop().right, 1920);
+    assert!(!sway.has_per_window_colors());
+  }
+
+  #[test]
+  fn client_colors_are_set_once_per_change() {
+    let config = "set $accent #c6a0f6\nclient.focused $accent $accent #000000 $accent $accent\n";
+    let (socket, commands) = fake_sway("colors", tree(), config, Vec::new());
+    let sway = SwayBackend::connect(&socket).unwrap();
+    sway.enumerate();
+
+    sway.set_border_color(1, 0x0000ff);
+    sway.set_border_color(2, 0xff0000);
+    sway.set_border_color(3, 0xff0000);
+    sway.flush();
+    assert_eq!(
+      *commands.lock().unwrap(),
+      [
+        "client.focused #ff0000 #ff0000 #ffffff #ff0000 #ff0000",
+        "client.focused_inactive #0000ff #0000ff #ffffff #0000ff #0000ff",
+        "client.unfocused #0000ff #0000ff #ffffff #0000ff #0000ff",
+      ]
+    );
+
+    // Same colors, nothing to run.
+    sway.set_border_color(2, 0xff0000);
+    sway.flush();
+    assert_eq!(commands.lock().unwrap().len(), 3);
+
+    // The default border is the one from the config.
+    commands.lock().unwrap().clear();
+    for window in [1, 2, 3] {
+      sway.set_border_color(window, DWMWA_COLOR_DEFAULT);
+    }
+    sway.flush();
+    assert_eq!(
+      *commands.lock().unwrap(),
+      [
+        "client.focused #c6a0f6 #c6a0f6 #000000 #c6a0f6 #c6a0f6",
+        DEFAULT_CLIENT_COLORS[1],
+        DEFAULT_CLIENT_COLORS[2],
+      ]
+    );
+  }
+
+  #[test]
+  fn unfocused_windows_share_one_color() {
+    let (socket, commands) = fake_sway("shared", tree(), "", Vec::new());
+    let sway = SwayBackend::connect(&socket).unwrap();
+    sway.enumerate();
+    sway.set_border_color(1, 0x0000ff);
+    sway.set_border_color(2, 0xff0000);
+    sway.set_border_color(3, 0x00ff00);
+    sway.flush();
+    // On a tie the smaller COLORREF wins, whatever order they came in.
+    assert_eq!(
+      commands.lock().unwrap()[2],
+      "client.unfocused #00ff00 #00ff00 #ffffff #00ff00 #00ff00"
+    );
+  }
+
+  #[test]
+  fn window_events_update_the_windows() {
+    let event = |change: &str, container: serde_json::Value| {
+      serde_json::json!({ "change": change, "container": container }).to_string()
+    };
+    let events = vec![
+      event("new", con(4, "", "foot", false)),
+      event("title", con(4, "fish", "foot", false)),
+      event("focus", con(4, "fish", "foot", true)),
+      event("mark", con(4, "fish", "foot", true)),
+      event("close", con(4, "fish", "foot", true)),
+    ];
+    let (socket, _) = fake_sway("events", tree(), "", events);
+    let sway = SwayBackend::connect(&socket).unwrap();
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let record = received.clone();
+    // Returns once the fake hangs up.
+    sway.subscribe(Box::new(move |event| record.lock().unwrap().push(event)));
+    assert_eq!(
+      *received.lock().unwrap(),
+      [
+        WindowEvent::Created(4),
+        WindowEvent::NameChanged(4),
+        WindowEvent::Foreground(4),
+        WindowEvent::Destroyed(4),
+      ]
+    );
+    assert!(!sway.is_alive(4));
+    assert_eq!(sway.foreground(), None);
+  }
+}
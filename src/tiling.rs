@@ -0,0 +1,144 @@
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::config::{Config, TilingWm};
+#[cfg(feature = "glazewm")]
+use crate::glazewm;
+use crate::logger::Logger;
+use crate::{komorebi, registry};
+
+static TILING: Lazy<Mutex<Option<Arc<TilingSnapshot>>>> = Lazy::new(|| Mutex::new(None));
+
+// How long to wait before connecting again after the window manager went away.
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Where a tiling window manager put a window, for `Workspace`, `Layout`
+/// and `State` rules.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct WindowTiling {
+  /// 0-based, like `komorebic focus-workspace`.
+  pub workspace_index: usize,
+  /// The index for workspaces without a name.
+  pub workspace_name: String,
+  pub layout: String,
+  /// `tiled`, `stacked`, `monocle`, `floating`, `maximized`,
+  /// `fullscreen` or `minimized`; a stacked window can also be in monocle.
+  pub states: Vec<&'static str>,
+}
+
+/// The state of all windows managed by the window manager after one of its events.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TilingSnapshot {
+  pub windows: HashMap<usize, WindowTiling>,
+  /// The window the window manager considers focused.
+  pub focused: Option<usize>,
+}
+
+/// The latest snapshot, `None` without a tiling window manager.
+pub fn current() -> Option<Arc<TilingSnapshot>> {
+  TILING.lock().unwrap().clone()
+}
+
+/// Replaces the snapshot. Windows whose tiling changed get their rules evaluated again.
+pub fn update(snapshot: Option<TilingSnapshot>) {
+  let snapshot = snapshot.map(Arc::new);
+  *TILING.lock().unwrap() = snapshot.clone();
+  registry::set_tiling(snapshot);
+}
+
+/// The window that should get the active border. While the window manager
+/// moves windows around, the system foreground passes through windows it
+/// is about to hide; for windows it manages, its own idea of focus wins.
+pub fn focused_or(foreground: Option<usize>) -> Option<usize> {
+  focused_in(current().as_deref(), foreground)
+}
+
+/// `focused_or` for a given snapshot.
+pub fn focused_in(snapshot: Option<&TilingSnapshot>, foreground: Option<usize>) -> Option<usize> {
+  let Some(snapshot) = snapshot else {
+    return foreground;
+  };
+  match (foreground, snapshot.focused) {
+    (Some(window), Some(focused)) if snapshot.windows.contains_key(&window) => Some(focused),
+    _ => foreground,
+  }
+}
+
+/// Follows the window manager from `tiling_wm` and calls `on_change`
+/// after every snapshot. Connects again when the window manager restarts
+/// or the setting changes. Blocks the calling thread.
+pub fn watch(mut on_change: impl FnMut()) {
+  let mut last_error = None;
+  loop {
+    let Some(wm) = Config::get().tiling_wm else {
+      std::thread::sleep(RETRY_INTERVAL);
+      continue;
+    };
+
+    let result = follow(wm, &mut |snapshot| {
+      update(Some(snapshot));
+      on_change();
+      // Keep going until the setting changes.
+      Config::get().tiling_wm == Some(wm)
+    });
+    // Don't log the same error every few seconds while the window manager isn't running.
+    if let Err(e) = result {
+      if last_error.as_ref() != Some(&e) {
+        Logger::log(&format!("[ERROR] {:?}: {}", wm, e));
+        last_error = Some(e);
+      }
+    } else {
+      last_error = None;
+    }
+
+    if current().is_some() {
+      update(None);
+      on_change();
+    }
+    std::thread::sleep(RETRY_INTERVAL);
+  }
+}
+
+/// Calls `on_snapshot` for every event of `wm` until it disconnects or
+/// `on_snapshot` returns false.
+fn follow(wm: TilingWm, on_snapshot: &mut dyn FnMut(TilingSnapshot) -> bool) -> Result<(), String> {
+  match wm {
+    TilingWm::Komorebi => komorebi::subscribe(on_snapshot),
+    #[cfg(feature = "glazewm")]
+    TilingWm::GlazeWm => glazewm::subscribe(glazewm::DEFAULT_ADDRESS, on_snapshot),
+    #[cfg(not(feature = "glazewm"))]
+    TilingWm::GlazeWm => Err("built without the glazewm feature".to_string()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn managed_windows_follow_the_window_managers_focus() {
+    assert_eq!(focused_in(None, Some(1)), Some(1));
+
+    let mut snapshot = TilingSnapshot {
+      focused: Some(2),
+      ..TilingSnapshot::default()
+    };
+    snapshot.windows.insert(1, WindowTiling::default());
+    snapshot.windows.insert(2, WindowTiling::default());
+    let snapshot = Some(&snapshot);
+    // The foreground passing through a managed window.
+    assert_eq!(focused_in(snapshot, Some(1)), Some(2));
+    // Unmanaged windows and no foreground at all.
+    assert_eq!(focused_in(snapshot, Some(3)), Some(3));
+    assert_eq!(focused_in(snapshot, None), None);
+  }
+
+  #[test]
+  fn without_a_focused_window_the_foreground_stays() {
+    let mut snapshot = TilingSnapshot::default();
+    snapshot.windows.insert(1, WindowTiling::default());
+    assert_eq!(focused_in(Some(&snapshot), Some(1)), Some(1));
+  }
+}
@@ -0,0 +1,236 @@
+use once_cell::sync::Lazy;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::animation::{Clock, Easing, SystemClock};
+use crate::css::Rgba;
+use crate::{DWMWA_COLOR_DEFAULT, DWMWA_COLOR_NONE};
+
+static TRANSITIONS: Lazy<Mutex<Transitions>> =
+  Lazy::new(|| Mutex::new(Transitions::new(Box::new(SystemClock::new()))));
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransitionSettings {
+  pub duration_ms: f32,
+  pub easing: Easing,
+}
+
+/// Fades between the inactive and active color when focus moves, tracked
+/// per window so a fade that gets interrupted turns around from wherever
+/// it was instead of jumping.
+pub struct Transitions {
+  clock: Box<dyn Clock>,
+  windows: HashMap<usize, Fade>,
+}
+
+struct Fade {
+  active: bool,
+  // How active the window looked when the fade started, 0.0 to 1.0.
+  from: f32,
+  started: Duration,
+}
+
+impl Fade {
+  fn target(&self) -> f32 {
+    if self.active {
+      1.0
+    } else {
+      0.0
+    }
+  }
+
+  fn duration_ms(&self, settings: &TransitionSettings) -> f32 {
+    // Turning around halfway only takes half the time.
+    settings.duration_ms * (self.target() - self.from).abs()
+  }
+
+  fn elapsed_ms(&self, now: Duration) -> f32 {
+    now.saturating_sub(self.started).as_secs_f32() * 1000.0
+  }
+
+  fn activeness(&self, now: Duration, settings: &TransitionSettings) -> f32 {
+    let (to, duration) = (self.target(), self.duration_ms(settings));
+    if duration <= 0.0 {
+      return to;
+    }
+    let progress = (self.elapsed_ms(now) / duration).min(1.0);
+    self.from + (to - self.from) * settings.easing.apply(progress)
+  }
+
+  /// Until its end time has passed. A fade that started this very tick is
+  /// still at its first color but running.
+  fn is_running(&self, now: Duration, settings: &TransitionSettings) -> bool {
+    self.elapsed_ms(now) < self.duration_ms(settings)
+  }
+}
+
+impl Transitions {
+  pub fn new(clock: Box<dyn Clock>) -> Transitions {
+    Transitions {
+      clock,
+      windows: HashMap::new(),
+    }
+  }
+
+  /// The color to paint `window` with right now. A window seen for the first
+  /// time starts at its color instead of fading in.
+  pub fn color_for(
+    &mut self,
+    window: usize,
+    is_active: bool,
+    (active, inactive): (u32, u32),
+    settings: &TransitionSettings,
+  ) -> u32 {
+    let now = self.clock.elapsed();
+    let fade = self.windows.entry(window).or_insert(Fade {
+      active: is_active,
+      from: if is_active { 1.0 } else { 0.0 },
+      started: now,
+    });
+    if fade.active != is_active {
+      *fade = Fade {
+        active: is_active,
+        from: fade.activeness(now, settings),
+        started: now,
+      };
+    }
+    blend(inactive, active, fade.activeness(now, settings))
+  }
+
+  /// Whether any window is still fading, i.e. whether it needs more frames.
+  pub fn is_running(&self, settings: &TransitionSettings) -> bool {
+    let now = self.clock.elapsed();
+    self
+      .windows
+      .values()
+      .any(|fade| fade.is_running(now, settings))
+  }
+
+  /// Forgets windows that were closed.
+  pub fn retain(&mut self, windows: &HashSet<usize>) {
+    self.windows.retain(|window, _| windows.contains(window));
+  }
+}
+
+/// Mixes in OKLab. The default border and no border at all can't be blended,
+/// so fades from or to them switch halfway through.
+fn blend(inactive: u32, active: u32, activeness: f32) -> u32 {
+  if activeness >= 1.0 {
+    return active;
+  }
+  if activeness <= 0.0 {
+    return inactive;
+  }
+  let is_color = |c: u32| c != DWMWA_COLOR_DEFAULT && c != DWMWA_COLOR_NONE;
+  if !is_color(active) || !is_color(inactive) {
+    return if activeness >= 0.5 { active } else { inactive };
+  }
+  let lerp = |x: f32, y: f32| x + (y - x) * activeness;
+  let (l1, a1, b1) = Rgba::from_colorref(inactive).to_oklab();
+  let (l2, a2, b2) = Rgba::from_colorref(active).to_oklab();
+  Rgba::from_oklab(lerp(l1, l2), lerp(a1, a2), lerp(b1, b2), 255).to_colorref()
+}
+
+pub(crate) fn lock() -> MutexGuard<'static, Transitions> {
+  TRANSITIONS.lock().unwrap()
+}
+
+pub fn is_running(settings: &TransitionSettings) -> bool {
+  TRANSITIONS.lock().unwrap().is_running(settings)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::animation::ManualClock;
+
+  const RED: u32 = 0x0000ff;
+  const BLUE: u32 = 0xff0000;
+  const COLORS: (u32, u32) = (BLUE, RED);
+  const LINEAR: TransitionSettings = TransitionSettings {
+    duration_ms: 1000.0,
+    easing: Easing::Linear,
+  };
+
+  fn transitions() -> (Transitions, ManualClock) {
+    let clock = ManualClock::default();
+    (Transitions::new(Box::new(clock.clone())), clock)
+  }
+
+  #[test]
+  fn new_windows_start_at_their_color() {
+    let (mut transitions, _) = transitions();
+    assert_eq!(transitions.color_for(1, true, COLORS, &LINEAR), BLUE);
+    assert_eq!(transitions.color_for(2, false, COLORS, &LINEAR), RED);
+    assert!(!transitions.is_running(&LINEAR));
+  }
+
+  #[test]
+  fn fades_pass_the_midpoint_and_complete() {
+    let (mut transitions, clock) = transitions();
+    transitions.color_for(1, false, COLORS, &LINEAR);
+    clock.set_ms(100);
+    assert_eq!(transitions.color_for(1, true, COLORS, &LINEAR), RED);
+    clock.set_ms(600);
+    assert_eq!(
+      transitions.color_for(1, true, COLORS, &LINEAR),
+      blend(RED, BLUE, 0.5)
+    );
+    assert!(transitions.is_running(&LINEAR));
+    clock.set_ms(1100);
+    assert_eq!(transitions.color_for(1, true, COLORS, &LINEAR), BLUE);
+    assert!(!transitions.is_running(&LINEAR));
+  }
+
+  #[test]
+  fn a_fade_is_running_from_the_tick_it_starts() {
+    let (mut transitions, _) = transitions();
+    transitions.color_for(1, false, COLORS, &LINEAR);
+    // Same instant: nothing has moved yet, but frames are still needed.
+    assert_eq!(transitions.color_for(1, true, COLORS, &LINEAR), RED);
+    assert!(transitions.is_running(&LINEAR));
+  }
+
+  #[test]
+  fn reversing_midway_turns_around_from_where_it_was() {
+    let (mut transitions, clock) = transitions();
+    transitions.color_for(1, false, COLORS, &LINEAR);
+    transitions.color_for(1, true, COLORS, &LINEAR);
+    clock.set_ms(250);
+    let quarter = transitions.color_for(1, true, COLORS, &LINEAR);
+    assert_eq!(quarter, blend(RED, BLUE, 0.25));
+    assert_eq!(transitions.color_for(1, false, COLORS, &LINEAR), quarter);
+    // Going back from a quarter only takes a quarter of the time.
+    clock.set_ms(375);
+    assert_eq!(
+      transitions.color_for(1, false, COLORS, &LINEAR),
+      blend(RED, BLUE, 0.125)
+    );
+    assert!(transitions.is_running(&LINEAR));
+    clock.set_ms(500);
+    assert_eq!(transitions.color_for(1, false, COLORS, &LINEAR), RED);
+    assert!(!transitions.is_running(&LINEAR));
+  }
+
+  #[test]
+  fn zero_duration_switches_at_once() {
+    let instant = TransitionSettings {
+      duration_ms: 0.0,
+      ..LINEAR
+    };
+    let (mut transitions, _) = transitions();
+    transitions.color_for(1, false, COLORS, &instant);
+    assert_eq!(transitions.color_for(1, true, COLORS, &instant), BLUE);
+    assert!(!transitions.is_running(&instant));
+  }
+
+  #[test]
+  fn closed_windows_are_forgotten() {
+    let (mut transitions, _) = transitions();
+    transitions.color_for(1, false, COLORS, &LINEAR);
+    transitions.color_for(1, true, COLORS, &LINEAR);
+    transitions.retain(&HashSet::new());
+    assert!(!transitions.is_running(&LINEAR));
+  }
+}
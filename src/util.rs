@@ -1,267 +1,165 @@
-use check_elevation::is_elevated;
-use planif::enums::TaskCreationFlags;
-use planif::schedule::TaskScheduler;
-use planif::schedule_builder::Action;
-use planif::schedule_builder::ScheduleBuilder;
-use planif::settings::Duration;
-use planif::settings::LogonType;
-use planif::settings::PrincipalSettings;
-use planif::settings::RunLevel;
-use planif::settings::Settings;
-use std::ffi::CString;
+#[cfg(windows)]
+use std::ffi::OsStr;
+#[cfg(windows)]
+use std::os::windows::ffi::OsStrExt;
 use std::{
-  env,
   fs::{self, File, OpenOptions},
-  io::Write,
-  path::{Path, PathBuf},
+  io::{self, Write},
+  path::Path,
+};
+#[cfg(windows)]
+use winapi::um::{
+  fileapi::{
+    FindCloseChangeNotification, FindFirstChangeNotificationW, FindNextChangeNotification,
+  },
+  handleapi::INVALID_HANDLE_VALUE,
+  synchapi::WaitForSingleObject,
+  winbase::{INFINITE, WAIT_OBJECT_0},
+  winnt::{FILE_NOTIFY_CHANGE_FILE_NAME, FILE_NOTIFY_CHANGE_LAST_WRITE},
 };
-use winapi::shared::minwindef::BOOL;
-use winapi::shared::winerror::SUCCEEDED;
-use winapi::um::dwmapi::DwmGetColorizationColor;
-use winapi::um::winnt::{KEY_READ, KEY_WRITE};
-use winapi::um::winuser::MessageBoxA;
-use winapi::um::winuser::MB_ICONERROR;
-use winapi::um::winuser::MB_OK;
-use winreg::{enums::HKEY_CURRENT_USER, RegKey};
-
-use crate::rainbow::Rainbow;
-use crate::{logger::Logger, COLOR_INVALID, DWMWA_COLOR_DEFAULT, DWMWA_COLOR_NONE};
-
-pub fn get_file_path(filename: &str) -> String {
-  let user_profile_path = match std::env::var("USERPROFILE") {
-    Ok(user_profile_path) => user_profile_path,
-    Err(err) => {
-      Logger::log("[ERROR] Failed to find USERPROFILE environment variable");
-      Logger::log(&format!("[DEBUG] {:?}", err));
-      std::process::exit(1);
-    }
-  };
-  let dirpath = format!("{}\\.cuteborders", user_profile_path);
-  let filepath = format!("{}\\{}", dirpath, filename);
 
-  if !Path::new(&dirpath).exists() {
-    if let Err(err) = fs::create_dir(&dirpath) {
-      Logger::log(&format!("[ERROR] Failed to create directory: {}", &dirpath));
-      Logger::log(&format!("[DEBUG] {:?}", err));
-      std::process::exit(1);
-    }
+#[cfg(windows)]
+use crate::logger::Logger;
+use crate::css;
+
+/// The environment variable holding the user's home folder.
+const HOME_VARIABLE: &str = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+
+/// The path of `filename` in `~/.cuteborders`, creating the folder if it
+/// doesn't exist yet.
+pub fn get_file_path(filename: &str) -> io::Result<String> {
+  let user_profile_path = std::env::var(HOME_VARIABLE).map_err(|err| {
+    io::Error::new(
+      io::ErrorKind::NotFound,
+      format!(
+        "Failed to find {} environment variable: {}",
+        HOME_VARIABLE, err
+      ),
+    )
+  })?;
+  let dirpath = Path::new(&user_profile_path).join(".cuteborders");
+  let filepath = dirpath.join(filename).to_string_lossy().into_owned();
+
+  if !dirpath.exists() {
+    fs::create_dir(&dirpath).map_err(|err| {
+      io::Error::new(
+        err.kind(),
+        format!("Failed to create directory {}: {}", dirpath.display(), err),
+      )
+    })?;
   }
-  return filepath;
+  Ok(filepath)
 }
 
-pub fn get_file(filename: &str, default_content: &str) -> std::fs::File {
-  let filepath = get_file_path(filename);
+/// Opens `filename` in `~/.cuteborders` for appending, creating it with
+/// `default_content` if it doesn't exist yet.
+pub fn get_file(filename: &str, default_content: &str) -> io::Result<File> {
+  let filepath = get_file_path(filename)?;
+  let with_path = |err: io::Error| io::Error::new(err.kind(), format!("{}: {}", filepath, err));
 
   if !Path::new(&filepath).exists() {
-    let mut file = match File::create(&filepath) {
-      Ok(file) => file,
-      Err(err) => {
-        Logger::log(&format!("[ERROR] Failed to create file: {}", &filepath));
-        Logger::log(&format!("[DEBUG] {:?}", err));
-        std::process::exit(1);
-      }
-    };
-
-    if let Err(err) = file.write_all(default_content.as_bytes()) {
-      Logger::log(&format!("[ERROR] Failed to write to file: {}", &filepath));
-      Logger::log(&format!("[DEBUG] {:?}", err));
-      std::process::exit(1);
-    }
+    let mut file = File::create(&filepath).map_err(with_path)?;
+    file
+      .write_all(default_content.as_bytes())
+      .map_err(with_path)?;
   }
 
-  let file = match OpenOptions::new()
+  OpenOptions::new()
     .read(true)
-    .write(true)
     .append(true)
     .open(&filepath)
-  {
-    Ok(file) => file,
-    Err(err) => {
-      Logger::log(&format!("[ERROR] Failed to open file: {}", &filepath));
-      Logger::log(&format!("[DEBUG] {:?}", err));
-      std::process::exit(1);
-    }
-  };
-
-  file
+    .map_err(with_path)
 }
 
-pub fn hex_to_colorref(hex: &str) -> u32 {
-  if hex == "default" {
-    return DWMWA_COLOR_DEFAULT;
-  }
-
-  if hex == "transparent" {
-    return DWMWA_COLOR_NONE;
+/// Calls `on_change` whenever a file in `dir` is created, renamed or written.
+/// Blocks the calling thread.
+#[cfg(windows)]
+pub fn watch_directory(dir: &str, mut on_change: impl FnMut()) {
+  let wide_dir: Vec<u16> = OsStr::new(dir)
+    .encode_wide()
+    .chain(std::iter::once(0))
+    .collect();
+  let handle = unsafe {
+    FindFirstChangeNotificationW(
+      wide_dir.as_ptr(),
+      0,
+      FILE_NOTIFY_CHANGE_LAST_WRITE | FILE_NOTIFY_CHANGE_FILE_NAME,
+    )
+  };
+  if handle == INVALID_HANDLE_VALUE {
+    Logger::log(&format!("[ERROR] Failed to watch directory: {}", dir));
+    return;
   }
 
-  if hex == "accent" {
-    let mut colorization: u32 = 0;
-    let mut opaqueblend: BOOL = 0;
-    // should not call this every single fucking time but whatever
-    let result = unsafe { DwmGetColorizationColor(&mut colorization, &mut opaqueblend) };
-    if SUCCEEDED(result) {
-      let red = (colorization & 0x00FF0000) >> 16;
-      let green = (colorization & 0x0000FF00) >> 8;
-      let blue = (colorization & 0x000000FF) >> 0;
-      let bbggrr = (blue << 16) | (green << 8) | red;
-      return bbggrr;
-    } else {
+  loop {
+    match unsafe { WaitForSingleObject(handle, INFINITE) } {
+      WAIT_OBJECT_0 => on_change(),
+      result => {
+        Logger::log(&format!(
+          "[ERROR] Failed to wait for directory changes: {}",
+          result
+        ));
+        break;
+      }
+    }
+    if unsafe { FindNextChangeNotification(handle) } == 0 {
       Logger::log(&format!(
-        "[ERROR] Failed to retrieve accent color: 0x{:08X})",
-        result
+        "[ERROR] Failed to keep watching directory: {}",
+        dir
       ));
-      // Not returning COLOR_INVALID here since the config is not invalid,
-      // instead returning DWMWA_COLOR_DEFAULT to let the system handle it.
-      return DWMWA_COLOR_DEFAULT;
-    }
-  }
-
-  if hex == "rainbow" {
-    return Rainbow::get_color();
-  }
-
-  if hex.len() != 7 || !hex.starts_with('#') {
-    Logger::log(&format!("[ERROR] Invalid hex: {}", hex));
-    return COLOR_INVALID;
-  }
-
-  let r = u8::from_str_radix(&hex[1..3], 16);
-  let g = u8::from_str_radix(&hex[3..5], 16);
-  let b = u8::from_str_radix(&hex[5..7], 16);
-
-  match (r, g, b) {
-    (Ok(r), Ok(g), Ok(b)) => (b as u32) << 16 | (g as u32) << 8 | r as u32,
-    _ => {
-      Logger::log(&format!("[ERROR] Invalid hex: {}", hex));
-      COLOR_INVALID
+      break;
     }
   }
+  unsafe { FindCloseChangeNotification(handle) };
 }
 
-fn clean_old_registry_key() {
-  let key = match RegKey::predef(HKEY_CURRENT_USER).open_subkey_with_flags(
-    "Software\\Microsoft\\Windows\\CurrentVersion\\Run",
-    KEY_READ | KEY_WRITE,
-  ) {
-    Ok(key) => Some(key),
-    Err(_) => None,
-  };
-
-  if let Some(key) = key {
-    let _ = key.delete_value("cute-borders");
+/// Without change notifications, `on_change` is called once a second and
+/// has to check for itself whether something changed.
+#[cfg(not(windows))]
+pub fn watch_directory(_dir: &str, mut on_change: impl FnMut()) {
+  loop {
+    std::thread::sleep(std::time::Duration::from_secs(1));
+    on_change();
   }
 }
 
-pub fn get_exe_path() -> PathBuf {
-  let exe_path: PathBuf = match env::current_exe() {
-    Ok(path) => path,
-    Err(err) => {
-      Logger::log("[ERROR] Failed to find own executable path");
-      Logger::log(&format!("[DEBUG] {:?}", err));
-      std::process::exit(1);
-    }
-  };
-
-  let user_profile_path = match std::env::var("USERPROFILE") {
-    Ok(user_profile_path) => user_profile_path,
-    Err(err) => {
-      Logger::log("[ERROR] Failed to find USERPROFILE environment variable");
-      Logger::log(&format!("[DEBUG] {:?}", err));
-      std::process::exit(1);
-    }
-  };
-  get_file_path("cute-borders.exe"); // this creates the folder
-  let new_exe_path = PathBuf::from(format!(
-    "{}\\.cuteborders\\cute-borders.exe",
-    user_profile_path,
-  ));
-
-  if exe_path != new_exe_path {
-    if Path::new(&new_exe_path).exists() {
-      match fs::remove_file(&new_exe_path) {
-        Ok(_) => {}
-        Err(_err) => {
-          unsafe {
-            let title = CString::new("Failed to update").unwrap();
-            let message = CString::new(
-              "Please close currently running cute-borders to be able to update to this version.",
-            )
-            .unwrap();
-            MessageBoxA(
-              std::ptr::null_mut(),
-              message.as_ptr(),
-              title.as_ptr(),
-              MB_OK | MB_ICONERROR,
-            );
-          }
-          std::process::exit(1);
-        }
-      }
-    }
-
-    match fs::copy(&exe_path, &new_exe_path) {
-      Ok(_) => {}
-      Err(err) => {
-        Logger::log(&format!(
-          "[ERROR] Failed to copy file: {} to: {}",
-          &exe_path.to_string_lossy(),
-          &new_exe_path.to_string_lossy()
-        ));
-        Logger::log(&format!("[DEBUG] {:?}", err));
-        std::process::exit(1);
-      }
-    }
+/// Parses an opaque CSS color (hex, `rgb()`, `hsl()`, `oklch()` or a named color)
+/// into a COLORREF (0x00bbggrr).
+pub fn hex_to_colorref(hex: &str) -> Result<u32, String> {
+  let color = css::parse(hex)?;
+  if color.a != 255 {
+    return Err(format!(
+      "\"{}\" is not opaque, borders can't be translucent",
+      hex.trim()
+    ));
   }
-
-  return new_exe_path;
+  Ok(color.to_colorref())
 }
 
-pub fn set_startup(enabled: bool) -> Result<(), Box<dyn std::error::Error>> {
-  clean_old_registry_key();
-  let exe_path = get_exe_path();
-  let is_elevated = is_elevated().unwrap_or(false);
-
-  if !is_elevated {
-    return Ok(());
+/// Case-insensitive wildcard match where `*` matches any run of characters
+/// and `?` matches exactly one.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+  let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+  let text: Vec<char> = text.to_lowercase().chars().collect();
+  let (mut p, mut t) = (0, 0);
+  let mut backtrack: Option<(usize, usize)> = None;
+
+  while t < text.len() {
+    if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+      p += 1;
+      t += 1;
+    } else if p < pattern.len() && pattern[p] == '*' {
+      backtrack = Some((p, t));
+      p += 1;
+    } else if let Some((star_p, star_t)) = backtrack {
+      // let the last `*` swallow one more character and try again
+      p = star_p + 1;
+      t = star_t + 1;
+      backtrack = Some((star_p, star_t + 1));
+    } else {
+      return false;
+    }
   }
 
-  let ts = TaskScheduler::new()?;
-  let com = ts.get_com();
-  let sb = ScheduleBuilder::new(&com).unwrap();
-
-  let mut settings = Settings::new();
-  settings.stop_if_going_on_batteries = Some(false);
-  settings.disallow_start_if_on_batteries = Some(false);
-  settings.enabled = Some(true);
-
-  let action = Action::new("cute-borders-action", &exe_path.to_string_lossy(), "", "");
-
-  let delay = Duration {
-    seconds: Some(5),
-    // see https://github.com/mattrobineau/planif/commit/ac2e7f79ec8de8935c6292d64533a6c7ce37212e
-    // github has 1.0.1 but crates.io doesnt
-    hours: Some(0),
-    ..Default::default()
-  };
-
-  sb.create_logon()
-    .settings(settings)?
-    .author("keifufu")?
-    .description("cute-borders startup")?
-    .principal(PrincipalSettings {
-      display_name: "".to_string(),
-      group_id: None,
-      id: "".to_string(),
-      logon_type: LogonType::Password,
-      run_level: RunLevel::Highest,
-      user_id: None,
-    })?
-    .trigger("cute-borders-trigger", enabled)?
-    .delay(delay)?
-    .action(action)?
-    .build()?
-    .register("cute-borders", TaskCreationFlags::CreateOrUpdate as i32)?;
-
-  Ok(())
+  pattern[p..].iter().all(|&c| c == '*')
 }
@@ -0,0 +1,211 @@
+use std::cell::RefCell;
+use std::ffi::{c_ulong, OsString};
+use std::mem;
+use std::os::windows::prelude::OsStringExt;
+use std::ptr;
+use winapi::ctypes::{c_int, c_void};
+use winapi::shared::minwindef::{BOOL, DWORD, LPARAM};
+use winapi::shared::windef::{HWINEVENTHOOK, HWND, RECT};
+use winapi::um::dwmapi::DwmSetWindowAttribute;
+use winapi::um::winuser::{
+  DispatchMessageW, EnumWindows, GetAncestor, GetClassNameW, GetForegroundWindow, GetMessageW,
+  GetSystemMetrics, GetWindow, GetWindowLongW, GetWindowRect, GetWindowTextLengthW, GetWindowTextW,
+  GetWindowThreadProcessId, IsWindow, IsWindowVisible, SetWinEventHook, TranslateMessage,
+  UnhookWinEvent, CHILDID_SELF, EVENT_OBJECT_CREATE, EVENT_OBJECT_DESTROY, EVENT_OBJECT_HIDE,
+  EVENT_OBJECT_NAMECHANGE, EVENT_OBJECT_SHOW, EVENT_SYSTEM_FOREGROUND, EVENT_SYSTEM_MINIMIZEEND,
+  GA_ROOT, GWL_EXSTYLE, GW_OWNER, OBJID_WINDOW, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN,
+  SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN, WINEVENT_OUTOFCONTEXT, WS_EX_TOOLWINDOW,
+};
+
+use crate::backend::{EventHandler, Rect, WindowAttributes, WindowBackend, WindowEvent};
+
+const DWMWA_BORDER_COLOR: u32 = 34;
+
+// Events that keep the registry up to date or can change a border color.
+const WINDOW_EVENTS: [u32; 7] = [
+  EVENT_SYSTEM_FOREGROUND,
+  EVENT_SYSTEM_MINIMIZEEND,
+  EVENT_OBJECT_CREATE,
+  EVENT_OBJECT_DESTROY,
+  EVENT_OBJECT_SHOW,
+  EVENT_OBJECT_HIDE,
+  EVENT_OBJECT_NAMECHANGE,
+];
+
+thread_local! {
+  // Out-of-context hooks are called on the thread that set them, the one
+  // running `subscribe`'s message loop.
+  static ON_EVENT: RefCell<Option<EventHandler>> = RefCell::new(None);
+}
+
+/// Windows as Win32 and DWM see them.
+pub struct Win32Backend;
+
+impl WindowBackend for Win32Backend {
+  fn enumerate(&self) -> Vec<usize> {
+    let mut windows: Vec<usize> = Vec::new();
+    let lparam = &mut windows as *mut _ as LPARAM;
+    unsafe { EnumWindows(Some(enum_windows_callback), lparam) };
+    windows
+  }
+
+  fn describe(&self, window: usize) -> Option<WindowAttributes> {
+    let hwnd = window as HWND;
+    unsafe {
+      if IsWindow(hwnd) == 0 || IsWindowVisible(hwnd) == 0 || GetAncestor(hwnd, GA_ROOT) != hwnd {
+        return None;
+      }
+      let mut class_buffer: [u16; 256] = [0; 256];
+      let length = GetClassNameW(hwnd, class_buffer.as_mut_ptr(), class_buffer.len() as c_int);
+      let class = from_wide(&class_buffer[..length.max(0) as usize]);
+      let is_menu = class.contains("#32768");
+      let ex_style = GetWindowLongW(hwnd, GWL_EXSTYLE) as u32;
+      if ex_style & WS_EX_TOOLWINDOW != 0 && !is_menu {
+        return None;
+      }
+
+      let mut title_buffer: [u16; 512] = [0; 512];
+      let mut length = 0;
+      if GetWindowTextLengthW(hwnd) > 0 {
+        length = GetWindowTextW(hwnd, title_buffer.as_mut_ptr(), title_buffer.len() as c_int);
+      }
+      let title = from_wide(&title_buffer[..length.max(0) as usize]);
+
+      Some(WindowAttributes {
+        title,
+        class,
+        pid: self.pid(window),
+        is_menu,
+      })
+    }
+  }
+
+  fn is_alive(&self, window: usize) -> bool {
+    unsafe { IsWindow(window as HWND) != 0 }
+  }
+
+  fn foreground(&self) -> Option<usize> {
+    let hwnd = unsafe { GetForegroundWindow() };
+    (!hwnd.is_null()).then_some(hwnd as usize)
+  }
+
+  fn owner(&self, window: usize) -> Option<usize> {
+    let owner = unsafe { GetWindow(window as HWND, GW_OWNER) };
+    (!owner.is_null()).then_some(owner as usize)
+  }
+
+  fn pid(&self, window: usize) -> u32 {
+    let mut pid: DWORD = 0;
+    unsafe { GetWindowThreadProcessId(window as HWND, &mut pid) };
+    pid
+  }
+
+  fn bounds(&self, window: usize) -> Option<Rect> {
+    let mut rect: RECT = unsafe { mem::zeroed() };
+    if unsafe { GetWindowRect(window as HWND, &mut rect) } == 0 {
+      return None;
+    }
+    Some(Rect {
+      left: rect.left,
+      top: rect.top,
+      right: rect.right,
+      bottom: rect.bottom,
+    })
+  }
+
+  fn desktop(&self) -> Rect {
+    unsafe {
+      let left = GetSystemMetrics(SM_XVIRTUALSCREEN);
+      let top = GetSystemMetrics(SM_YVIRTUALSCREEN);
+      Rect {
+        left,
+        top,
+        right: left + GetSystemMetrics(SM_CXVIRTUALSCREEN),
+        bottom: top + GetSystemMetrics(SM_CYVIRTUALSCREEN),
+      }
+    }
+  }
+
+  fn set_border_color(&self, window: usize, color: u32) {
+    unsafe {
+      let _ = DwmSetWindowAttribute(
+        window as HWND,
+        DWMWA_BORDER_COLOR,
+        &color as *const _ as *const c_void,
+        mem::size_of::<c_ulong>() as u32,
+      );
+    }
+  }
+
+  fn subscribe(&self, on_event: EventHandler) {
+    ON_EVENT.with(|handler| *handler.borrow_mut() = Some(on_event));
+    unsafe {
+      let hooks: Vec<HWINEVENTHOOK> = WINDOW_EVENTS
+        .iter()
+        .map(|&event| {
+          SetWinEventHook(
+            event,
+            event,
+            ptr::null_mut(),
+            Some(win_event_proc),
+            0,
+            0,
+            WINEVENT_OUTOFCONTEXT,
+          )
+        })
+        .collect();
+      let mut msg = mem::zeroed();
+      while GetMessageW(&mut msg, ptr::null_mut(), 0, 0) != 0 {
+        TranslateMessage(&msg);
+        DispatchMessageW(&msg);
+      }
+      for hook in hooks {
+        if !hook.is_null() {
+          UnhookWinEvent(hook);
+        }
+      }
+    }
+    ON_EVENT.with(|handler| *handler.borrow_mut() = None);
+  }
+}
+
+unsafe extern "system" fn win_event_proc(
+  _h_win_event_hook: HWINEVENTHOOK,
+  event: u32,
+  hwnd: HWND,
+  id_object: i32,
+  id_child: i32,
+  _id_event_thread: u32,
+  _dwms_event_time: u32,
+) {
+  // Events of objects inside a window (caret, controls) don't matter.
+  let is_window = id_object == OBJID_WINDOW && id_child == CHILDID_SELF;
+  if event != EVENT_SYSTEM_FOREGROUND && !is_window {
+    return;
+  }
+  let window = hwnd as usize;
+  let window_event = match event {
+    EVENT_SYSTEM_FOREGROUND => WindowEvent::Foreground(window),
+    EVENT_OBJECT_CREATE => WindowEvent::Created(window),
+    EVENT_OBJECT_DESTROY => WindowEvent::Destroyed(window),
+    EVENT_OBJECT_SHOW | EVENT_SYSTEM_MINIMIZEEND => WindowEvent::Shown(window),
+    EVENT_OBJECT_HIDE => WindowEvent::Hidden(window),
+    EVENT_OBJECT_NAMECHANGE => WindowEvent::NameChanged(window),
+    _ => return,
+  };
+  ON_EVENT.with(|handler| {
+    if let Some(on_event) = handler.borrow_mut().as_mut() {
+      on_event(window_event);
+    }
+  });
+}
+
+unsafe extern "system" fn enum_windows_callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
+  let windows = &mut *(lparam as *mut Vec<usize>);
+  windows.push(hwnd as usize);
+  1
+}
+
+fn from_wide(buffer: &[u16]) -> String {
+  OsString::from_wide(buffer).to_string_lossy().into_owned()
+}